@@ -0,0 +1,84 @@
+use wasm_game_of_life::expr::Expr;
+use wasm_game_of_life::sketch::{Equation, GroupSolved, HEquation, HParam, Param};
+use wasm_game_of_life::system::{SolveResult, SolverBackend, System};
+
+use std::time::Instant;
+
+const POINTS: u32 = 500;
+
+fn param(v: u32, val: f64) -> Param {
+    Param {
+        tag: 0,
+        h: HParam { v },
+        val,
+        known: false,
+        free: false,
+        substd: HParam { v: 0 },
+    }
+}
+
+// A zig-zag chain of POINTS points in the plane, i.e. 2*POINTS unknowns:
+// the first point is pinned, each link has unit length and rises by 0.6.
+fn chain(backend: SolverBackend) -> System {
+    let mut sys = System::new();
+    sys.backend = backend;
+    let x = |i: u32| Expr::from_param(HParam { v: 2 * i + 1 });
+    let y = |i: u32| Expr::from_param(HParam { v: 2 * i + 2 });
+    let eq = |e: Expr, sys: &mut System| {
+        let h = HEquation {
+            v: sys.eq.len() as u32,
+        };
+        sys.add_equation(Equation { tag: 0, h, e });
+    };
+
+    for i in 0..POINTS {
+        let wobble = ((i * 7919) % 13) as f64 * 0.01;
+        sys.add_param(param(2 * i + 1, 0.8 * (i as f64) + wobble));
+        sys.add_param(param(2 * i + 2, 0.6 * (i as f64) - wobble));
+    }
+    eq(x(0), &mut sys);
+    eq(y(0), &mut sys);
+    for i in 1..POINTS {
+        let dx = x(i) - x(i - 1);
+        let dy = y(i) - y(i - 1);
+        eq(
+            (dx.square() + dy.clone().square()).sqrt() - Expr::from_constant(1.0),
+            &mut sys,
+        );
+        eq(dy - Expr::from_constant(0.6), &mut sys);
+    }
+    sys
+}
+
+fn run(name: &str, backend: SolverBackend) -> (SolveResult, i32, Vec<f64>) {
+    let mut sys = chain(backend);
+    let mut solved = GroupSolved::default();
+    let start = Instant::now();
+    let how = sys.solve(&mut solved, false, false, false);
+    let elapsed = start.elapsed();
+    println!(
+        "{:>6}: {} unknowns, {} equations, {:?} (dof {}) in {:.3} ms",
+        name,
+        2 * POINTS,
+        sys.eq.len(),
+        how,
+        solved.dof,
+        elapsed.as_secs_f64() * 1000.0
+    );
+
+    let mut p: Vec<(HParam, f64)> = sys.param.values().map(|p| (p.h, p.val)).collect();
+    p.sort_by_key(|(h, _)| *h);
+    (how, solved.dof, p.into_iter().map(|(_, v)| v).collect())
+}
+
+// A faster answer is no use if it's a different one, so the two backends
+// have to agree before either timing means anything.
+fn main() {
+    let (sparse_how, sparse_dof, sparse) = run("sparse", SolverBackend::Sparse);
+    let (dense_how, dense_dof, dense) = run("dense", SolverBackend::Dense);
+    assert_eq!(sparse_how, SolveResult::Okay);
+    assert_eq!((sparse_how, sparse_dof), (dense_how, dense_dof));
+    for (s, d) in sparse.iter().zip(dense.iter()) {
+        assert!((s - d).abs() < 1e-6, "sparse {} vs dense {}", s, d);
+    }
+}
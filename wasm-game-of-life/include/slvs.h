@@ -0,0 +1,206 @@
+#ifndef SLVS_H
+#define SLVS_H
+
+/* Generated by cbindgen from src/ffi.rs; don't edit by hand. */
+
+#include <stdint.h>
+
+#define SLVS_FREE_IN_3D 0
+
+#define SLVS_E_POINT_IN_3D 50000
+#define SLVS_E_POINT_IN_2D 50001
+#define SLVS_E_NORMAL_IN_3D 60000
+#define SLVS_E_NORMAL_IN_2D 60001
+#define SLVS_E_DISTANCE 70000
+#define SLVS_E_WORKPLANE 80000
+#define SLVS_E_LINE_SEGMENT 80001
+#define SLVS_E_CUBIC 80002
+#define SLVS_E_CIRCLE 80003
+#define SLVS_E_ARC_OF_CIRCLE 80004
+
+#define SLVS_C_POINTS_COINCIDENT 100000
+#define SLVS_C_PT_PT_DISTANCE 100001
+#define SLVS_C_PT_PLANE_DISTANCE 100002
+#define SLVS_C_PT_LINE_DISTANCE 100003
+#define SLVS_C_PT_FACE_DISTANCE 100004
+#define SLVS_C_PT_IN_PLANE 100005
+#define SLVS_C_PT_ON_LINE 100006
+#define SLVS_C_PT_ON_FACE 100007
+#define SLVS_C_EQUAL_LENGTH_LINES 100008
+#define SLVS_C_LENGTH_RATIO 100009
+#define SLVS_C_EQ_LEN_PT_LINE_D 100010
+#define SLVS_C_EQ_PT_LN_DISTANCES 100011
+#define SLVS_C_EQUAL_ANGLE 100012
+#define SLVS_C_EQUAL_LINE_ARC_LEN 100013
+#define SLVS_C_SYMMETRIC 100014
+#define SLVS_C_SYMMETRIC_HORIZ 100015
+#define SLVS_C_SYMMETRIC_VERT 100016
+#define SLVS_C_SYMMETRIC_LINE 100017
+#define SLVS_C_AT_MIDPOINT 100018
+#define SLVS_C_HORIZONTAL 100019
+#define SLVS_C_VERTICAL 100020
+#define SLVS_C_DIAMETER 100021
+#define SLVS_C_PT_ON_CIRCLE 100022
+#define SLVS_C_SAME_ORIENTATION 100023
+#define SLVS_C_ANGLE 100024
+#define SLVS_C_PARALLEL 100025
+#define SLVS_C_PERPENDICULAR 100026
+#define SLVS_C_ARC_LINE_TANGENT 100027
+#define SLVS_C_CUBIC_LINE_TANGENT 100028
+#define SLVS_C_EQUAL_RADIUS 100029
+#define SLVS_C_PROJ_PT_DISTANCE 100030
+#define SLVS_C_WHERE_DRAGGED 100031
+#define SLVS_C_CURVE_CURVE_TANGENT 100032
+#define SLVS_C_LENGTH_DIFFERENCE 100033
+
+#define SLVS_RESULT_OKAY 0
+#define SLVS_RESULT_INCONSISTENT 1
+#define SLVS_RESULT_DIDNT_CONVERGE 2
+#define SLVS_RESULT_TOO_MANY_UNKNOWNS 3
+
+/*
+ Not a result of solving: an entity or constraint had a type this solver
+ doesn't know. Kept well clear of libslvs's own results.
+ */
+#define SLVS_RESULT_UNKNOWN_TYPE 100
+
+/*
+ Not a result of solving: the solver failed in a way it should have
+ caught, and reported as one of the results above.
+ */
+#define SLVS_RESULT_INTERNAL_ERROR 101
+
+/*
+ Not a result of solving: something refers to a handle that isn't there,
+ or to an entity of the wrong type.
+ */
+#define SLVS_RESULT_BAD_INPUT 102
+
+typedef uint32_t Slvs_hParam;
+
+typedef uint32_t Slvs_hEntity;
+
+typedef uint32_t Slvs_hConstraint;
+
+typedef uint32_t Slvs_hGroup;
+
+typedef struct Slvs_Param {
+  Slvs_hParam h;
+  Slvs_hGroup group;
+  double val;
+} Slvs_Param;
+
+typedef struct Slvs_Entity {
+  Slvs_hEntity h;
+  Slvs_hGroup group;
+  int type;
+  Slvs_hEntity wrkpl;
+  Slvs_hEntity point[4];
+  Slvs_hEntity normal;
+  Slvs_hEntity distance;
+  Slvs_hParam param[4];
+} Slvs_Entity;
+
+typedef struct Slvs_Constraint {
+  Slvs_hConstraint h;
+  Slvs_hGroup group;
+  int type;
+  Slvs_hEntity wrkpl;
+  double valA;
+  Slvs_hEntity ptA;
+  Slvs_hEntity ptB;
+  Slvs_hEntity entityA;
+  Slvs_hEntity entityB;
+  Slvs_hEntity entityC;
+  Slvs_hEntity entityD;
+  int other;
+  int other2;
+} Slvs_Constraint;
+
+typedef struct Slvs_System {
+  Slvs_Param *param;
+  int params;
+  Slvs_Entity *entity;
+  int entities;
+  Slvs_Constraint *constraint;
+  int constraints;
+  Slvs_hParam dragged[4];
+  int calculateFaileds;
+  Slvs_hConstraint *failed;
+  int faileds;
+  int dof;
+  int result;
+} Slvs_System;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/*
+ Solve the given group, writing the new param values back into
+ `ssys->param`. An entity or constraint of an unknown type makes this
+ return without solving, with `ssys->result` set to
+ `SLVS_RESULT_UNKNOWN_TYPE`, and one that refers to a handle that isn't
+ there, or to an entity of the wrong type, sets it to
+ `SLVS_RESULT_BAD_INPUT`; the params are left as they were. If the solver
+ fails some other way, `ssys->result` is `SLVS_RESULT_INTERNAL_ERROR` and
+ the params may be partly written. Any way, `ssys->dof` and
+ `ssys->faileds` are 0.
+
+ # Safety
+
+ `ssys` must point to a valid `Slvs_System`, whose arrays are valid for
+ the lengths given.
+ */
+void Slvs_Solve(Slvs_System *ssys, Slvs_hGroup shg);
+
+/*
+ The first basis vector of the workplane a quaternion describes.
+
+ # Safety
+
+ The output pointers must be valid for writes.
+ */
+void Slvs_QuaternionU(double qw, double qx, double qy, double qz, double *x, double *y, double *z);
+
+/*
+ The second basis vector of the workplane a quaternion describes.
+
+ # Safety
+
+ The output pointers must be valid for writes.
+ */
+void Slvs_QuaternionV(double qw, double qx, double qy, double qz, double *x, double *y, double *z);
+
+/*
+ The normal of the workplane a quaternion describes.
+
+ # Safety
+
+ The output pointers must be valid for writes.
+ */
+void Slvs_QuaternionN(double qw, double qx, double qy, double qz, double *x, double *y, double *z);
+
+/*
+ The unit quaternion for the workplane with basis vectors u and v.
+
+ # Safety
+
+ The output pointers must be valid for writes.
+ */
+void Slvs_MakeQuaternion(double ux,
+                         double uy,
+                         double uz,
+                         double vx,
+                         double vy,
+                         double vz,
+                         double *qw,
+                         double *qx,
+                         double *qy,
+                         double *qz);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* SLVS_H */
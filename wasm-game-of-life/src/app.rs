@@ -1,10 +1,16 @@
 use crate::component_designer::ComponentDesigner;
+use crate::delete::{Removed, SketchItem};
+use crate::expr::ExprError;
+use crate::sketch::{
+    Constraint, ConstraintType, EntityBaseType, Group, GroupType, HConstraint, HEntity, HGroup,
+    HParam, HRequest, Request, RequestType, Sketch, SketchError, NO_ENTITY, NO_PARAM,
+};
+use crate::step_dimension::{step_frames_csv, StepFrame};
+use crate::system::SolveResult;
 use sodium_rust::Cell;
 use sodium_rust::CellSink;
 use sodium_rust::SodiumCtx;
 
-pub struct Sketch {}
-
 #[derive(Clone)]
 pub enum AppView {
     LogInOrRegister,
@@ -15,6 +21,7 @@ pub struct App {
     pub sodium_ctx: SodiumCtx,
     pub c_current_view: Cell<AppView>,
     cs_current_view: CellSink<AppView>,
+    pub sketch: Sketch,
 }
 
 impl App {
@@ -25,6 +32,7 @@ impl App {
             sodium_ctx: sodium_ctx.clone(),
             c_current_view: cs_current_view.cell(),
             cs_current_view,
+            sketch: Sketch::new(),
         }
     }
 
@@ -32,4 +40,223 @@ impl App {
         self.cs_current_view
             .send(AppView::ComponentDesigner(ComponentDesigner {}));
     }
+
+    // Also finds the free params, so the designer can highlight whatever
+    // is still under-constrained.
+    pub fn solve_group(&mut self, hg: HGroup) -> Result<SolveResult, SketchError> {
+        self.sketch.find_group(hg)?;
+        Ok(self.sketch.solve_group(hg, true, true))
+    }
+
+    // Redoes just the groups an edit since the last time has touched.
+    pub fn regenerate(&mut self) -> Vec<(HGroup, SolveResult)> {
+        self.sketch.regenerate_dirty()
+    }
+
+    pub fn set_solve_timeout(&mut self, ms: Option<f64>) {
+        self.sketch.solve_timeout = ms;
+    }
+
+    pub fn solve_timed_out(&self, hg: HGroup) -> Result<bool, SketchError> {
+        Ok(self.sketch.find_group(hg)?.solved.timeout)
+    }
+
+    pub fn group_dof(&self, hg: HGroup) -> Result<i32, SketchError> {
+        Ok(self.sketch.find_group(hg)?.solved.dof)
+    }
+
+    pub fn free_entities(&self, hg: HGroup) -> Result<Vec<HEntity>, SketchError> {
+        self.sketch.find_group(hg)?;
+        Ok(self.sketch.free_entities(hg))
+    }
+
+    pub fn set_variable(&mut self, name: &str, expr: &str) -> Result<(), ExprError> {
+        self.sketch.set_variable(name, expr)
+    }
+
+    pub fn remove_variable(&mut self, name: &str) {
+        self.sketch.remove_variable(name);
+    }
+
+    // As of the last solve; None if there's no such variable.
+    pub fn variable_value(&self, name: &str) -> Option<f64> {
+        self.sketch.variable.get(name).map(|v| v.val)
+    }
+
+    pub fn variable_error(&self, name: &str) -> Option<ExprError> {
+        self.sketch.variable.get(name).and_then(|v| v.error.clone())
+    }
+
+    pub fn set_dimension_expr(
+        &mut self,
+        hc: HConstraint,
+        expr: Option<&str>,
+    ) -> Result<(), SketchError> {
+        self.sketch.find_constraint(hc)?;
+        self.sketch.set_dimension_expr(hc, expr);
+        Ok(())
+    }
+
+    pub fn dimension_error(&self, hc: HConstraint) -> Result<Option<ExprError>, SketchError> {
+        Ok(self.sketch.find_constraint(hc)?.expr_error.clone())
+    }
+
+    // Saves the sketch as it is now under that name, replacing any
+    // configuration that already had it.
+    pub fn save_configuration(&mut self, name: &str) {
+        let cfg = self.sketch.capture_configuration(name);
+        self.sketch.set_configuration(cfg);
+    }
+
+    pub fn remove_configuration(&mut self, name: &str) {
+        self.sketch.remove_configuration(name);
+    }
+
+    pub fn configuration_names(&self) -> Vec<String> {
+        self.sketch
+            .configuration
+            .iter()
+            .map(|c| c.name.clone())
+            .collect()
+    }
+
+    pub fn regenerate_configuration(&mut self, name: &str) -> Option<Vec<(HGroup, SolveResult)>> {
+        self.sketch.regenerate_configuration(name)
+    }
+
+    pub fn step_dimension(
+        &mut self,
+        hc: HConstraint,
+        a: f64,
+        b: f64,
+        steps: u32,
+    ) -> Result<Vec<StepFrame>, SketchError> {
+        self.sketch.step_dimension(hc, a, b, steps)
+    }
+
+    // The same sweep, written out for the viewer to play back.
+    pub fn step_dimension_csv(
+        &mut self,
+        hc: HConstraint,
+        a: f64,
+        b: f64,
+        steps: u32,
+    ) -> Result<String, SketchError> {
+        Ok(step_frames_csv(&self.step_dimension(hc, a, b, steps)?))
+    }
+
+    // Drags a point of group hg towards (x, y, z), or (x, y) if it's in a
+    // workplane. Only a free point's params are its coordinates, as for
+    // point_force_to(..); a point made by a group is moved through that
+    // group's params, so it can't be dragged here.
+    pub fn drag_point(
+        &mut self,
+        hg: HGroup,
+        he: HEntity,
+        pos: &[f64],
+    ) -> Result<SolveResult, SketchError> {
+        self.sketch.find_group(hg)?;
+        let e = self.sketch.find_entity(he)?;
+        if !e.is_point() {
+            return Err(SketchError::NotAPoint(he.v));
+        }
+        if !matches!(
+            e.type_,
+            EntityBaseType::PointIn3D | EntityBaseType::PointIn2D
+        ) {
+            return Err(SketchError::NotDraggable(he.v));
+        }
+        // Only hg is solved, so a point of any other group would be moved
+        // without its own group knowing.
+        if e.group != hg {
+            return Err(SketchError::NotInGroup(he.v));
+        }
+        let dragged: Vec<(HParam, f64)> = e
+            .param
+            .iter()
+            .filter(|h| **h != NO_PARAM)
+            .cloned()
+            .zip(pos.iter().cloned())
+            .collect();
+        Ok(self.sketch.drag(hg, &dragged))
+    }
+
+    // The new group goes after all the others, made from op_a if it's
+    // made from a group, and drawn in wrkpl if that's not FREE_IN_3D.
+    pub fn add_group(
+        &mut self,
+        type_: GroupType,
+        op_a: HGroup,
+        wrkpl: HEntity,
+    ) -> Result<HGroup, SketchError> {
+        let mut g = Group::new(type_);
+        g.op_a = op_a;
+        g.active_workplane = wrkpl;
+        self.sketch.add_group(g)
+    }
+
+    // Only the points and entities a constraint of that type uses are
+    // looked at; the rest can be anything, or left out.
+    pub fn add_constraint(
+        &mut self,
+        type_: ConstraintType,
+        hg: HGroup,
+        wrkpl: HEntity,
+        val_a: f64,
+        pts: &[HEntity],
+        entities: &[HEntity],
+    ) -> Result<HConstraint, SketchError> {
+        let mut c = Constraint::new(HConstraint { v: 0 }, type_, hg);
+        c.workplane = wrkpl;
+        c.val_a = val_a;
+        let at = |l: &[HEntity], i: usize| l.get(i).cloned().unwrap_or(NO_ENTITY);
+        c.pt_a = at(pts, 0);
+        c.pt_b = at(pts, 1);
+        c.entity_a = at(entities, 0);
+        c.entity_b = at(entities, 1);
+        c.entity_c = at(entities, 2);
+        c.entity_d = at(entities, 3);
+        self.sketch.add_constraint(c)
+    }
+
+    pub fn add_request(
+        &mut self,
+        type_: RequestType,
+        hg: HGroup,
+        wrkpl: HEntity,
+        extra_points: usize,
+    ) -> Result<HRequest, SketchError> {
+        let mut r = Request::new(type_, hg, wrkpl);
+        r.extra_points = extra_points;
+        self.sketch.add_request(r)
+    }
+
+    pub fn delete(&mut self, items: &[SketchItem]) -> Removed {
+        self.sketch.delete(items)
+    }
+
+    pub fn set_traced(&mut self, he: HEntity, traced: bool) -> Result<(), SketchError> {
+        if !self.sketch.find_entity(he)?.is_point() {
+            return Err(SketchError::NotAPoint(he.v));
+        }
+        self.sketch.set_traced(he, traced);
+        Ok(())
+    }
+
+    pub fn clear_traces(&mut self) {
+        self.sketch.clear_traces();
+    }
+
+    pub fn trace_csv(&self, he: HEntity) -> String {
+        self.sketch.trace_csv(he)
+    }
+
+    pub fn trace_to_cubic(
+        &mut self,
+        he: HEntity,
+        hg: HGroup,
+        wrkpl: HEntity,
+    ) -> Result<HEntity, SketchError> {
+        self.sketch.trace_to_cubic(he, hg, wrkpl)
+    }
 }
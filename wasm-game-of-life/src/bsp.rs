@@ -0,0 +1,281 @@
+use crate::dsc::{Vector, LENGTH_EPS};
+use crate::polygon::{BspClass, SMesh, STriMeta};
+
+// A convex polygon, along with the plane it's in.
+#[derive(Clone)]
+struct BspPolygon {
+    l: Vec<Vector>,
+    n: Vector,
+    d: f64,
+    meta: STriMeta,
+}
+
+// A binary space partition of a mesh's triangles, as in SolveSpace's
+// SBsp3: each node splits space by the plane of the polygons on it, with
+// what's in front and behind in the two subtrees. Clipping one tree
+// against another keeps just the parts of its polygons outside the other
+// solid, which is all the booleans need.
+struct SBsp3 {
+    n: Vector,
+    d: f64,
+    on: Vec<BspPolygon>,
+    pos: Option<Box<SBsp3>>,
+    neg: Option<Box<SBsp3>>,
+}
+
+impl BspPolygon {
+    fn flip(&mut self) {
+        self.l.reverse();
+        self.n = -self.n;
+        self.d = -self.d;
+    }
+}
+
+fn class_of(n: Vector, d: f64, p: Vector) -> BspClass {
+    let t = n.dot(p) - d;
+    if t > LENGTH_EPS {
+        BspClass::Pos
+    } else if t < -LENGTH_EPS {
+        BspClass::Neg
+    } else {
+        BspClass::Coplaner
+    }
+}
+
+// Sorts p by which side of the plane it's on, cutting it in two if it's on
+// both. Coplanar polygons go with the side their normal faces.
+fn split(
+    n: Vector,
+    d: f64,
+    p: BspPolygon,
+    on_pos: &mut Vec<BspPolygon>,
+    on_neg: &mut Vec<BspPolygon>,
+    pos: &mut Vec<BspPolygon>,
+    neg: &mut Vec<BspPolygon>,
+) {
+    let class: Vec<BspClass> = p.l.iter().map(|v| class_of(n, d, *v)).collect();
+    let any_pos = class.contains(&BspClass::Pos);
+    let any_neg = class.contains(&BspClass::Neg);
+    match (any_pos, any_neg) {
+        (false, false) => {
+            if n.dot(p.n) > 0.0 {
+                on_pos.push(p)
+            } else {
+                on_neg.push(p)
+            }
+        }
+        (true, false) => pos.push(p),
+        (false, true) => neg.push(p),
+        (true, true) => {
+            let (mut lp, mut ln) = (Vec::new(), Vec::new());
+            for i in 0..p.l.len() {
+                let j = (i + 1) % p.l.len();
+                let (a, b) = (p.l[i], p.l[j]);
+                if class[i] != BspClass::Neg {
+                    lp.push(a);
+                }
+                if class[i] != BspClass::Pos {
+                    ln.push(a);
+                }
+                if (class[i] == BspClass::Pos && class[j] == BspClass::Neg)
+                    || (class[i] == BspClass::Neg && class[j] == BspClass::Pos)
+                {
+                    let t = (d - n.dot(a)) / n.dot(b - a);
+                    let x = a + (b - a) * t;
+                    lp.push(x);
+                    ln.push(x);
+                }
+            }
+            if lp.len() >= 3 {
+                pos.push(BspPolygon { l: lp, ..p.clone() });
+            }
+            if ln.len() >= 3 {
+                neg.push(BspPolygon { l: ln, ..p });
+            }
+        }
+    }
+}
+
+impl SBsp3 {
+    fn from_mesh(m: &SMesh) -> Option<Box<SBsp3>> {
+        let l =
+            m.l.iter()
+                .map(|t| {
+                    let n = t.normal().with_magnitude(1.0);
+                    BspPolygon {
+                        l: vec![t.a, t.b, t.c],
+                        n,
+                        d: n.dot(t.a),
+                        meta: t.meta,
+                    }
+                })
+                .collect();
+        SBsp3::insert(None, l)
+    }
+
+    fn insert(node: Option<Box<SBsp3>>, l: Vec<BspPolygon>) -> Option<Box<SBsp3>> {
+        if l.is_empty() {
+            return node;
+        }
+        let mut node = node.unwrap_or_else(|| {
+            Box::new(SBsp3 {
+                n: l[0].n,
+                d: l[0].d,
+                on: Vec::new(),
+                pos: None,
+                neg: None,
+            })
+        });
+        let (mut pos, mut neg) = (Vec::new(), Vec::new());
+        let mut on = Vec::new();
+        for p in l {
+            let (mut on_pos, mut on_neg) = (Vec::new(), Vec::new());
+            split(
+                node.n,
+                node.d,
+                p,
+                &mut on_pos,
+                &mut on_neg,
+                &mut pos,
+                &mut neg,
+            );
+            on.extend(on_pos);
+            on.extend(on_neg);
+        }
+        node.on.extend(on);
+        node.pos = SBsp3::insert(node.pos.take(), pos);
+        node.neg = SBsp3::insert(node.neg.take(), neg);
+        Some(node)
+    }
+
+    // Turns the solid inside out.
+    fn invert(&mut self) {
+        for p in self.on.iter_mut() {
+            p.flip();
+        }
+        self.n = -self.n;
+        self.d = -self.d;
+        std::mem::swap(&mut self.pos, &mut self.neg);
+        if let Some(pos) = self.pos.as_mut() {
+            pos.invert();
+        }
+        if let Some(neg) = self.neg.as_mut() {
+            neg.invert();
+        }
+    }
+
+    // The parts of the polygons outside this solid.
+    fn clip_polygons(&self, l: Vec<BspPolygon>) -> Vec<BspPolygon> {
+        let (mut pos, mut neg) = (Vec::new(), Vec::new());
+        for p in l {
+            let (mut on_pos, mut on_neg) = (Vec::new(), Vec::new());
+            split(
+                self.n,
+                self.d,
+                p,
+                &mut on_pos,
+                &mut on_neg,
+                &mut pos,
+                &mut neg,
+            );
+            pos.extend(on_pos);
+            neg.extend(on_neg);
+        }
+        let mut r = match &self.pos {
+            Some(node) => node.clip_polygons(pos),
+            None => pos,
+        };
+        if let Some(node) = &self.neg {
+            r.extend(node.clip_polygons(neg));
+        }
+        r
+    }
+
+    // Removes whatever of this tree's polygons is inside b.
+    fn clip_to(&mut self, b: &SBsp3) {
+        self.on = b.clip_polygons(std::mem::take(&mut self.on));
+        if let Some(pos) = self.pos.as_mut() {
+            pos.clip_to(b);
+        }
+        if let Some(neg) = self.neg.as_mut() {
+            neg.clip_to(b);
+        }
+    }
+
+    fn all_polygons(&self, l: &mut Vec<BspPolygon>) {
+        l.extend(self.on.iter().cloned());
+        if let Some(pos) = &self.pos {
+            pos.all_polygons(l);
+        }
+        if let Some(neg) = &self.neg {
+            neg.all_polygons(l);
+        }
+    }
+
+    fn into_mesh(self) -> SMesh {
+        let mut l = Vec::new();
+        self.all_polygons(&mut l);
+        let mut m = SMesh::new();
+        for p in l {
+            for i in 1..p.l.len() - 1 {
+                m.add_triangle(p.meta, p.l[0], p.l[i], p.l[i + 1]);
+            }
+        }
+        m
+    }
+}
+
+impl SMesh {
+    // The booleans here assume both meshes are closed. Faces keep their
+    // metadata through them, so a face cut up by the other solid is still
+    // the same face.
+    pub fn make_from_union_of(a: &SMesh, b: &SMesh) -> SMesh {
+        let (mut ta, mut tb) = match (SBsp3::from_mesh(a), SBsp3::from_mesh(b)) {
+            (Some(ta), Some(tb)) => (ta, tb),
+            _ => return SMesh::make_from_assembly_of(a, b),
+        };
+        ta.clip_to(&tb);
+        tb.clip_to(&ta);
+        tb.invert();
+        tb.clip_to(&ta);
+        tb.invert();
+        let mut l = Vec::new();
+        tb.all_polygons(&mut l);
+        SBsp3::insert(Some(ta), l).unwrap().into_mesh()
+    }
+
+    pub fn make_from_difference_of(a: &SMesh, b: &SMesh) -> SMesh {
+        let (mut ta, mut tb) = match (SBsp3::from_mesh(a), SBsp3::from_mesh(b)) {
+            (Some(ta), Some(tb)) => (ta, tb),
+            _ => return a.clone(),
+        };
+        ta.invert();
+        ta.clip_to(&tb);
+        tb.clip_to(&ta);
+        tb.invert();
+        tb.clip_to(&ta);
+        tb.invert();
+        let mut l = Vec::new();
+        tb.all_polygons(&mut l);
+        let mut r = SBsp3::insert(Some(ta), l).unwrap();
+        r.invert();
+        r.into_mesh()
+    }
+
+    pub fn make_from_intersection_of(a: &SMesh, b: &SMesh) -> SMesh {
+        let (mut ta, mut tb) = match (SBsp3::from_mesh(a), SBsp3::from_mesh(b)) {
+            (Some(ta), Some(tb)) => (ta, tb),
+            _ => return SMesh::new(),
+        };
+        ta.invert();
+        tb.clip_to(&ta);
+        tb.invert();
+        ta.clip_to(&tb);
+        tb.clip_to(&ta);
+        let mut l = Vec::new();
+        tb.all_polygons(&mut l);
+        let mut r = SBsp3::insert(Some(ta), l).unwrap();
+        r.invert();
+        r.into_mesh()
+    }
+}
@@ -0,0 +1,95 @@
+use crate::sketch::{Configuration, DimensionValue, HGroup, Sketch};
+use crate::system::SolveResult;
+
+impl Sketch {
+    pub fn get_configuration(&self, name: &str) -> Option<&Configuration> {
+        self.configuration.iter().find(|c| c.name == name)
+    }
+
+    // Replaces any configuration with the same name, else adds a row.
+    pub fn set_configuration(&mut self, cfg: Configuration) {
+        match self.configuration.iter_mut().find(|c| c.name == cfg.name) {
+            Some(c) => *c = cfg,
+            None => self.configuration.push(cfg),
+        }
+    }
+
+    pub fn remove_configuration(&mut self, name: &str) {
+        self.configuration.retain(|c| c.name != name);
+        if self.active_configuration.as_deref() == Some(name) {
+            self.active_configuration = None;
+        }
+    }
+
+    // The sketch as it is now, as a configuration: every driving dimension,
+    // every variable and every group's suppress state.
+    pub fn capture_configuration(&self, name: &str) -> Configuration {
+        let dimension = self
+            .constraint
+            .values()
+            .filter(|c| c.is_dimension() && !c.reference)
+            .map(|c| {
+                let val = match &c.expr {
+                    Some(e) => DimensionValue::Expr(e.clone()),
+                    None => DimensionValue::Value(c.val_a),
+                };
+                (c.h, val)
+            })
+            .collect();
+        let variable = self
+            .variable
+            .values()
+            .map(|v| (v.name.clone(), v.expr.clone()))
+            .collect();
+        let suppress = self.group.values().map(|g| (g.h, g.suppress)).collect();
+        Configuration {
+            name: name.to_string(),
+            dimension,
+            variable,
+            suppress,
+        }
+    }
+
+    // Sets the sketch up as the named configuration, without solving.
+    // Whatever the configuration doesn't mention is left as it is, and
+    // anything it mentions that's since been deleted is skipped.
+    pub fn apply_configuration(&mut self, name: &str) -> bool {
+        let cfg = match self.get_configuration(name) {
+            Some(cfg) => cfg.clone(),
+            None => return false,
+        };
+        for (hc, val) in cfg.dimension.iter() {
+            if let Some(c) = self.constraint.get_mut(hc) {
+                match val {
+                    DimensionValue::Value(v) => {
+                        c.val_a = *v;
+                        c.expr = None;
+                    }
+                    DimensionValue::Expr(e) => c.expr = Some(e.clone()),
+                }
+                c.expr_error = None;
+            }
+        }
+        for (name, text) in cfg.variable.iter() {
+            if let Some(v) = self.variable.get_mut(name) {
+                v.expr = text.clone();
+            }
+        }
+        for (hg, suppress) in cfg.suppress.iter() {
+            if let Some(g) = self.group.get_mut(hg) {
+                g.suppress = *suppress;
+            }
+        }
+        self.active_configuration = Some(cfg.name);
+        self.mark_groups_dirty_from(i32::MIN);
+        true
+    }
+
+    // None if there's no such configuration.
+    pub fn regenerate_configuration(&mut self, name: &str) -> Option<Vec<(HGroup, SolveResult)>> {
+        if !self.apply_configuration(name) {
+            return None;
+        }
+        Some(self.regenerate_all())
+    }
+}
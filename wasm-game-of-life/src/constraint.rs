@@ -0,0 +1,968 @@
+use crate::expr::{Expr, ExprVector};
+use crate::sketch::{
+    Constraint, ConstraintType, EntityBase, EntityBaseType, Equation, HConstraint, HEntity, HGroup,
+    HParam, Param, Sketch, SketchError, FREE_IN_3D, NO_ENTITY, NO_PARAM,
+};
+
+use std::f64::consts::PI;
+
+impl ConstraintType {
+    pub fn from_u32(v: u32) -> Option<Self> {
+        let types = [
+            ConstraintType::PointsCoincident,
+            ConstraintType::PtPtDistance,
+            ConstraintType::PtPlaneDistance,
+            ConstraintType::PtLineDistance,
+            ConstraintType::PtFaceDistance,
+            ConstraintType::ProjPtDistance,
+            ConstraintType::PtInPlane,
+            ConstraintType::PtOnLine,
+            ConstraintType::PtOnFace,
+            ConstraintType::EqualLengthLines,
+            ConstraintType::LengthRatio,
+            ConstraintType::EqLenPtLineD,
+            ConstraintType::EqPtLnDistances,
+            ConstraintType::EqualAngle,
+            ConstraintType::LengthDifference,
+            ConstraintType::Symmetric,
+            ConstraintType::SymmetricHoriz,
+            ConstraintType::SymmetricVert,
+            ConstraintType::SymmetricLine,
+            ConstraintType::AtMidpoint,
+            ConstraintType::Horizontal,
+            ConstraintType::Vertical,
+            ConstraintType::Diameter,
+            ConstraintType::PtOnCircle,
+            ConstraintType::SameOrientation,
+            ConstraintType::Angle,
+            ConstraintType::Parallel,
+            ConstraintType::Perpendicular,
+            ConstraintType::ArcLineTangent,
+            ConstraintType::CubicLineTangent,
+            ConstraintType::CurveCurveTangent,
+            ConstraintType::EqualRadius,
+            ConstraintType::WhereDragged,
+            ConstraintType::Comment,
+        ];
+        types.iter().cloned().find(|t| *t as u32 == v)
+    }
+}
+
+impl Constraint {
+    pub fn new(h: HConstraint, type_: ConstraintType, group: HGroup) -> Self {
+        Constraint {
+            tag: 0,
+            h,
+            type_,
+            group,
+            workplane: FREE_IN_3D,
+            val_a: 0.0,
+            val_p: NO_PARAM,
+            pt_a: NO_ENTITY,
+            pt_b: NO_ENTITY,
+            entity_a: NO_ENTITY,
+            entity_b: NO_ENTITY,
+            entity_c: NO_ENTITY,
+            entity_d: NO_ENTITY,
+            other: false,
+            other2: false,
+            reference: false,
+            expr: None,
+            expr_error: None,
+            measured: None,
+            comment: String::new(),
+        }
+    }
+
+    pub fn has_label(&self) -> bool {
+        matches!(
+            self.type_,
+            ConstraintType::PtPtDistance
+                | ConstraintType::ProjPtDistance
+                | ConstraintType::PtLineDistance
+                | ConstraintType::PtPlaneDistance
+                | ConstraintType::PtFaceDistance
+                | ConstraintType::LengthRatio
+                | ConstraintType::LengthDifference
+                | ConstraintType::Diameter
+                | ConstraintType::Angle
+                | ConstraintType::Comment
+        )
+    }
+
+    pub fn is_dimension(&self) -> bool {
+        self.has_label() && self.type_ != ConstraintType::Comment
+    }
+
+    // The value this dimension would need to be satisfied by the geometry
+    // as it is now; reference dimensions are kept up to date with this.
+    // Nothing for a constraint that isn't a single equation in its value.
+    pub fn measure(&self, sk: &Sketch) -> Option<f64> {
+        if self.type_ == ConstraintType::Angle {
+            let mut a = sk.get_entity(self.entity_a).vector_get_exprs(sk);
+            let b = sk.get_entity(self.entity_b).vector_get_exprs(sk);
+            if self.other {
+                a = a.scaled_by(Expr::from_constant(-1.0));
+            }
+            let c = Constraint::direction_cosine(sk, self.workplane, &a, &b).eval(&sk.param);
+            return Some(c.clamp(-1.0, 1.0).acos() * 180.0 / PI);
+        }
+
+        // Everything else is written as f(...) - val_a = 0.
+        let mut l = Vec::new();
+        self.generate_equations(sk, &mut l, true);
+        if l.len() != 1 {
+            return None;
+        }
+        Some(self.val_a + l[0].e.eval(&sk.param))
+    }
+
+    pub fn distance(sk: &Sketch, wrkpl: HEntity, hpa: HEntity, hpb: HEntity) -> Expr {
+        let pa = sk.get_entity(hpa);
+        let pb = sk.get_entity(hpb);
+
+        if wrkpl == FREE_IN_3D {
+            (pa.point_get_exprs(sk) - pb.point_get_exprs(sk)).magnitude()
+        } else {
+            let (ua, va) = pa.point_get_exprs_in_workplane(sk, wrkpl);
+            let (ub, vb) = pb.point_get_exprs_in_workplane(sk, wrkpl);
+            ((ua - ub).square() + (va - vb).square()).sqrt()
+        }
+    }
+
+    // Signed in a workplane, unsigned in 3d.
+    pub fn point_line_distance(sk: &Sketch, wrkpl: HEntity, hpt: HEntity, hln: HEntity) -> Expr {
+        let ln = sk.get_entity(hln);
+        let a = sk.get_entity(ln.point[0]);
+        let b = sk.get_entity(ln.point[1]);
+        let p = sk.get_entity(hpt);
+
+        if wrkpl == FREE_IN_3D {
+            let ep = p.point_get_exprs(sk);
+            let ea = a.point_get_exprs(sk);
+            let eb = b.point_get_exprs(sk);
+            let eab = ea.clone() - eb;
+            let m = eab.magnitude();
+            eab.cross(&(ea - ep)).magnitude() / m
+        } else {
+            let (ua, va) = a.point_get_exprs_in_workplane(sk, wrkpl);
+            let (ub, vb) = b.point_get_exprs_in_workplane(sk, wrkpl);
+            let (u, v) = p.point_get_exprs_in_workplane(sk, wrkpl);
+
+            let du = ua.clone() - ub;
+            let dv = va.clone() - vb;
+            let m = (du.clone().square() + dv.clone().square()).sqrt();
+            let proj = dv * (ua - u) - du * (va - v);
+            proj / m
+        }
+    }
+
+    pub fn point_plane_distance(sk: &Sketch, p: &ExprVector, hpl: HEntity) -> Expr {
+        let (n, d) = sk.get_entity(hpl).workplane_get_plane_exprs(sk);
+        p.dot(&n) - d
+    }
+
+    pub fn point_in_three_space(sk: &Sketch, workplane: HEntity, u: Expr, v: Expr) -> ExprVector {
+        let w = sk.get_entity(workplane);
+        let ub = w.normal_entity(sk).normal_exprs_u(sk);
+        let vb = w.normal_entity(sk).normal_exprs_v(sk);
+        let ob = w.workplane_get_offset_exprs(sk);
+        ub.scaled_by(u) + vb.scaled_by(v) + ob
+    }
+
+    pub fn direction_cosine(sk: &Sketch, wrkpl: HEntity, ae: &ExprVector, be: &ExprVector) -> Expr {
+        if wrkpl == FREE_IN_3D {
+            let mags = ae.magnitude() * be.magnitude();
+            return ae.dot(be) / mags;
+        }
+
+        let w = sk.get_entity(wrkpl);
+        let u = w.normal_entity(sk).normal_exprs_u(sk);
+        let v = w.normal_entity(sk).normal_exprs_v(sk);
+        let ua = u.dot(ae);
+        let va = v.dot(ae);
+        let ub = u.dot(be);
+        let vb = v.dot(be);
+        let maga = (ua.clone().square() + va.clone().square()).sqrt();
+        let magb = (ub.clone().square() + vb.clone().square()).sqrt();
+        let dot = ua * ub + va * vb;
+        dot / (maga * magb)
+    }
+
+    fn vectors_parallel_3d(a: ExprVector, b: ExprVector, p: HParam) -> ExprVector {
+        a - b.scaled_by(Expr::from_param(p))
+    }
+
+    fn needs_param(&self) -> bool {
+        match self.type_ {
+            // Only in 3d does parallelism need the extra scale factor.
+            ConstraintType::Parallel | ConstraintType::CubicLineTangent => {
+                self.workplane == FREE_IN_3D
+            }
+            ConstraintType::SameOrientation | ConstraintType::PtOnLine => true,
+            _ => false,
+        }
+    }
+
+    // Some constraints are written in terms of an extra unknown of their
+    // own; hand back that param, with a sensible starting value, if the
+    // sketch doesn't have it yet.
+    pub fn generate_params(&mut self, sk: &Sketch) -> Option<Param> {
+        if !self.needs_param() {
+            return None;
+        }
+        self.val_p = self.h.param(0);
+        if sk.param.contains_key(&self.val_p) {
+            return None;
+        }
+
+        let val = match self.type_ {
+            ConstraintType::PtOnLine => {
+                let ln = sk.get_entity(self.entity_a);
+                let a = sk
+                    .get_entity(ln.point[0])
+                    .point_get_exprs(sk)
+                    .eval(&sk.param);
+                let b = sk
+                    .get_entity(ln.point[1])
+                    .point_get_exprs(sk)
+                    .eval(&sk.param);
+                let p = sk.get_entity(self.pt_a).point_get_exprs(sk).eval(&sk.param);
+                let ab = b - a;
+                let m = ab.mag_squared();
+                if m == 0.0 {
+                    0.0
+                } else {
+                    (p - a).dot(ab) / m
+                }
+            }
+            ConstraintType::SameOrientation => {
+                let an = sk
+                    .get_entity(self.entity_a)
+                    .normal_exprs_n(sk)
+                    .eval(&sk.param);
+                let bn = sk
+                    .get_entity(self.entity_b)
+                    .normal_exprs_n(sk)
+                    .eval(&sk.param);
+                an.dot(bn)
+            }
+            _ => {
+                let a = if self.type_ == ConstraintType::CubicLineTangent {
+                    let cubic = sk.get_entity(self.entity_a);
+                    if self.other {
+                        cubic.cubic_get_finish_tangent_exprs(sk)
+                    } else {
+                        cubic.cubic_get_start_tangent_exprs(sk)
+                    }
+                } else {
+                    sk.get_entity(self.entity_a).vector_get_exprs(sk)
+                };
+                let a = a.eval(&sk.param);
+                let b = sk
+                    .get_entity(self.entity_b)
+                    .vector_get_exprs(sk)
+                    .eval(&sk.param);
+                let m = b.mag_squared();
+                if m == 0.0 {
+                    0.0
+                } else {
+                    a.dot(b) / m
+                }
+            }
+        };
+
+        Some(Param {
+            tag: 0,
+            h: self.val_p,
+            val,
+            known: false,
+            free: false,
+            substd: NO_PARAM,
+        })
+    }
+
+    fn add_eq(&self, l: &mut Vec<Equation>, e: Expr, index: u32) {
+        l.push(Equation {
+            tag: 0,
+            h: self.h.equation(index),
+            e,
+        });
+    }
+
+    fn add_eq_vector(&self, l: &mut Vec<Equation>, v: ExprVector) {
+        self.add_eq(l, v.x, 0);
+        self.add_eq(l, v.y, 1);
+        self.add_eq(l, v.z, 2);
+    }
+
+    pub fn generate_equations(&self, sk: &Sketch, l: &mut Vec<Equation>, for_reference: bool) {
+        if self.reference && !for_reference {
+            return;
+        }
+
+        let ex_a = Expr::from_constant(self.val_a);
+
+        match self.type_ {
+            ConstraintType::PtPtDistance => {
+                let d = Constraint::distance(sk, self.workplane, self.pt_a, self.pt_b);
+                self.add_eq(l, d - ex_a, 0);
+            }
+
+            ConstraintType::ProjPtDistance => {
+                let pa = sk.get_entity(self.pt_a).point_get_exprs(sk);
+                let pb = sk.get_entity(self.pt_b).point_get_exprs(sk);
+                let dp = pb - pa;
+                let pp = sk
+                    .get_entity(self.entity_a)
+                    .vector_get_exprs(sk)
+                    .with_magnitude(Expr::from_constant(1.0));
+                self.add_eq(l, dp.dot(&pp) - ex_a, 0);
+            }
+
+            ConstraintType::PtLineDistance => {
+                let d =
+                    Constraint::point_line_distance(sk, self.workplane, self.pt_a, self.entity_a);
+                self.add_eq(l, d - ex_a, 0);
+            }
+
+            ConstraintType::PtPlaneDistance => {
+                let pt = sk.get_entity(self.pt_a).point_get_exprs(sk);
+                let d = Constraint::point_plane_distance(sk, &pt, self.entity_a);
+                self.add_eq(l, d - ex_a, 0);
+            }
+
+            ConstraintType::PtFaceDistance => {
+                let pt = sk.get_entity(self.pt_a).point_get_exprs(sk);
+                let f = sk.get_entity(self.entity_a);
+                let p0 = f.face_get_point_exprs(sk);
+                let n = f.face_get_normal_exprs();
+                self.add_eq(l, (pt - p0).dot(&n) - ex_a, 0);
+            }
+
+            ConstraintType::EqualLengthLines => {
+                let a = sk.get_entity(self.entity_a);
+                let b = sk.get_entity(self.entity_b);
+                let la = Constraint::distance(sk, self.workplane, a.point[0], a.point[1]);
+                let lb = Constraint::distance(sk, self.workplane, b.point[0], b.point[1]);
+                self.add_eq(l, la - lb, 0);
+            }
+
+            // These work on distance squared, since the pt-line distances
+            // are signed, and we want the absolute value.
+            ConstraintType::EqLenPtLineD => {
+                let for_len = sk.get_entity(self.entity_a);
+                let d1 =
+                    Constraint::distance(sk, self.workplane, for_len.point[0], for_len.point[1]);
+                let d2 =
+                    Constraint::point_line_distance(sk, self.workplane, self.pt_a, self.entity_b);
+                self.add_eq(l, d1.square() - d2.square(), 0);
+            }
+            ConstraintType::EqPtLnDistances => {
+                let d1 =
+                    Constraint::point_line_distance(sk, self.workplane, self.pt_a, self.entity_a);
+                let d2 =
+                    Constraint::point_line_distance(sk, self.workplane, self.pt_b, self.entity_b);
+                self.add_eq(l, d1.square() - d2.square(), 0);
+            }
+
+            ConstraintType::LengthRatio | ConstraintType::LengthDifference => {
+                let a = sk.get_entity(self.entity_a);
+                let b = sk.get_entity(self.entity_b);
+                let la = Constraint::distance(sk, self.workplane, a.point[0], a.point[1]);
+                let lb = Constraint::distance(sk, self.workplane, b.point[0], b.point[1]);
+                if self.type_ == ConstraintType::LengthRatio {
+                    self.add_eq(l, la / lb - ex_a, 0);
+                } else {
+                    self.add_eq(l, la - lb - ex_a, 0);
+                }
+            }
+
+            ConstraintType::Diameter => {
+                let r = sk.get_entity(self.entity_a).circle_get_radius_expr(sk);
+                self.add_eq(l, r * Expr::from_constant(2.0) - ex_a, 0);
+            }
+
+            ConstraintType::EqualRadius => {
+                let ra = sk.get_entity(self.entity_a).circle_get_radius_expr(sk);
+                let rb = sk.get_entity(self.entity_b).circle_get_radius_expr(sk);
+                self.add_eq(l, ra - rb, 0);
+            }
+
+            ConstraintType::PointsCoincident => {
+                let a = sk.get_entity(self.pt_a);
+                let b = sk.get_entity(self.pt_b);
+                if self.workplane == FREE_IN_3D {
+                    let pa = a.point_get_exprs(sk);
+                    let pb = b.point_get_exprs(sk);
+                    self.add_eq(l, pa.x - pb.x, 0);
+                    self.add_eq(l, pa.y - pb.y, 1);
+                    self.add_eq(l, pa.z - pb.z, 2);
+                } else {
+                    let (au, av) = a.point_get_exprs_in_workplane(sk, self.workplane);
+                    let (bu, bv) = b.point_get_exprs_in_workplane(sk, self.workplane);
+                    self.add_eq(l, au - bu, 0);
+                    self.add_eq(l, av - bv, 1);
+                }
+            }
+
+            // This one works the same, whether projected or not.
+            ConstraintType::PtInPlane => {
+                let pt = sk.get_entity(self.pt_a).point_get_exprs(sk);
+                self.add_eq(
+                    l,
+                    Constraint::point_plane_distance(sk, &pt, self.entity_a),
+                    0,
+                );
+            }
+
+            ConstraintType::PtOnFace => {
+                let p = sk.get_entity(self.pt_a).point_get_exprs(sk);
+                let f = sk.get_entity(self.entity_a);
+                let p0 = f.face_get_point_exprs(sk);
+                let n = f.face_get_normal_exprs();
+                self.add_eq(l, (p - p0).dot(&n), 0);
+            }
+
+            ConstraintType::PtOnLine => {
+                let ln = sk.get_entity(self.entity_a);
+                let ea = sk.get_entity(ln.point[0]).point_get_exprs(sk);
+                let eb = sk.get_entity(ln.point[1]).point_get_exprs(sk);
+                let ep = sk.get_entity(self.pt_a).point_get_exprs(sk);
+
+                let pt_on_line = ea.clone() + (eb - ea).scaled_by(Expr::from_param(self.val_p));
+                let eq = pt_on_line - ep;
+
+                if self.workplane == FREE_IN_3D {
+                    self.add_eq_vector(l, eq);
+                } else {
+                    let w = sk.get_entity(self.workplane);
+                    let u = w.normal_entity(sk).normal_exprs_u(sk);
+                    let v = w.normal_entity(sk).normal_exprs_v(sk);
+                    self.add_eq(l, eq.dot(&u), 0);
+                    self.add_eq(l, eq.dot(&v), 1);
+                }
+            }
+
+            // This actually constrains the point to lie on the cylinder.
+            ConstraintType::PtOnCircle => {
+                let circle = sk.get_entity(self.entity_a);
+                let center = sk.get_entity(circle.point[0]).point_get_exprs(sk);
+                let pt = sk.get_entity(self.pt_a).point_get_exprs(sk);
+                let normal = circle.normal_entity(sk);
+                let u = normal.normal_exprs_u(sk);
+                let v = normal.normal_exprs_v(sk);
+
+                let d = center - pt;
+                let du = d.dot(&u);
+                let dv = d.dot(&v);
+                let r = circle.circle_get_radius_expr(sk);
+                self.add_eq(l, (du.square() + dv.square()).sqrt() - r, 0);
+            }
+
+            ConstraintType::AtMidpoint => {
+                let ln = sk.get_entity(self.entity_a);
+                if self.workplane == FREE_IN_3D {
+                    let a = sk.get_entity(ln.point[0]).point_get_exprs(sk);
+                    let b = sk.get_entity(ln.point[1]).point_get_exprs(sk);
+                    let m = (a + b).scaled_by(Expr::from_constant(0.5));
+
+                    if self.pt_a != NO_ENTITY {
+                        let p = sk.get_entity(self.pt_a).point_get_exprs(sk);
+                        self.add_eq_vector(l, m - p);
+                    } else {
+                        self.add_eq(
+                            l,
+                            Constraint::point_plane_distance(sk, &m, self.entity_b),
+                            0,
+                        );
+                    }
+                } else {
+                    let a = sk.get_entity(ln.point[0]);
+                    let b = sk.get_entity(ln.point[1]);
+                    let (au, av) = a.point_get_exprs_in_workplane(sk, self.workplane);
+                    let (bu, bv) = b.point_get_exprs_in_workplane(sk, self.workplane);
+                    let mu = Expr::from_constant(0.5) * (au + bu);
+                    let mv = Expr::from_constant(0.5) * (av + bv);
+
+                    if self.pt_a != NO_ENTITY {
+                        let p = sk.get_entity(self.pt_a);
+                        let (pu, pv) = p.point_get_exprs_in_workplane(sk, self.workplane);
+                        self.add_eq(l, pu - mu, 0);
+                        self.add_eq(l, pv - mv, 1);
+                    } else {
+                        let m = Constraint::point_in_three_space(sk, self.workplane, mu, mv);
+                        self.add_eq(
+                            l,
+                            Constraint::point_plane_distance(sk, &m, self.entity_b),
+                            0,
+                        );
+                    }
+                }
+            }
+
+            ConstraintType::Symmetric => {
+                let plane = self.entity_a;
+                let ea = sk.get_entity(self.pt_a);
+                let eb = sk.get_entity(self.pt_b);
+                if self.workplane == FREE_IN_3D {
+                    // The midpoint of the line connecting the symmetric
+                    // points lies on the plane of symmetry...
+                    let a = ea.point_get_exprs(sk);
+                    let b = eb.point_get_exprs(sk);
+                    let m = (a + b).scaled_by(Expr::from_constant(0.5));
+                    self.add_eq(l, Constraint::point_plane_distance(sk, &m, plane), 0);
+
+                    // ...and projected into that plane, the points are
+                    // coincident.
+                    let (au, av) = ea.point_get_exprs_in_workplane(sk, plane);
+                    let (bu, bv) = eb.point_get_exprs_in_workplane(sk, plane);
+                    self.add_eq(l, au - bu, 1);
+                    self.add_eq(l, av - bv, 2);
+                } else {
+                    let (au, av) = ea.point_get_exprs_in_workplane(sk, self.workplane);
+                    let (bu, bv) = eb.point_get_exprs_in_workplane(sk, self.workplane);
+                    let mu = Expr::from_constant(0.5) * (au + bu);
+                    let mv = Expr::from_constant(0.5) * (av + bv);
+                    let m = Constraint::point_in_three_space(sk, self.workplane, mu, mv);
+                    self.add_eq(l, Constraint::point_plane_distance(sk, &m, plane), 0);
+
+                    // A vector in the workplane that lies in the plane of
+                    // symmetry is perpendicular to the line joining the
+                    // points.
+                    let w = sk.get_entity(self.workplane);
+                    let u = w.normal_entity(sk).normal_exprs_u(sk);
+                    let v = w.normal_entity(sk).normal_exprs_v(sk);
+                    let pa = ea.point_get_exprs(sk);
+                    let pb = eb.point_get_exprs(sk);
+                    let (n, _) = sk.get_entity(plane).workplane_get_plane_exprs(sk);
+                    self.add_eq(l, n.cross(&u.cross(&v)).dot(&(pa - pb)), 1);
+                }
+            }
+
+            ConstraintType::SymmetricHoriz | ConstraintType::SymmetricVert => {
+                if self.workplane == FREE_IN_3D {
+                    panic!("Unexpected horizontal/vertical symmetric constraint in 3d");
+                }
+                let a = sk.get_entity(self.pt_a);
+                let b = sk.get_entity(self.pt_b);
+                let (au, av) = a.point_get_exprs_in_workplane(sk, self.workplane);
+                let (bu, bv) = b.point_get_exprs_in_workplane(sk, self.workplane);
+                if self.type_ == ConstraintType::SymmetricHoriz {
+                    self.add_eq(l, av - bv, 0);
+                    self.add_eq(l, au + bu, 1);
+                } else {
+                    self.add_eq(l, au - bu, 0);
+                    self.add_eq(l, av + bv, 1);
+                }
+            }
+
+            ConstraintType::SymmetricLine => {
+                let pa = sk.get_entity(self.pt_a);
+                let pb = sk.get_entity(self.pt_b);
+                let (pau, pav) = pa.point_get_exprs_in_workplane(sk, self.workplane);
+                let (pbu, pbv) = pb.point_get_exprs_in_workplane(sk, self.workplane);
+                let ln = sk.get_entity(self.entity_a);
+                let la = sk.get_entity(ln.point[0]);
+                let lb = sk.get_entity(ln.point[1]);
+                let (lau, lav) = la.point_get_exprs_in_workplane(sk, self.workplane);
+                let (lbu, lbv) = lb.point_get_exprs_in_workplane(sk, self.workplane);
+
+                let dlu = lbu - lau.clone();
+                let dlv = lbv - lav.clone();
+
+                // The line through the points is perpendicular to the line
+                // of symmetry...
+                self.add_eq(
+                    l,
+                    dlu.clone() * (pau.clone() - pbu.clone())
+                        + dlv.clone() * (pav.clone() - pbv.clone()),
+                    0,
+                );
+
+                // ...and the signed distances of the points to that line
+                // are equal and opposite, so they sum to zero.
+                let dista = dlv.clone() * (lau.clone() - pau) - dlu.clone() * (lav.clone() - pav);
+                let distb = dlv * (lau - pbu) - dlu * (lav - pbv);
+                self.add_eq(l, dista + distb, 1);
+            }
+
+            ConstraintType::Horizontal | ConstraintType::Vertical => {
+                if self.workplane == FREE_IN_3D {
+                    panic!("Unexpected horizontal/vertical constraint in 3d");
+                }
+                let (ha, hb) = if self.entity_a != NO_ENTITY {
+                    let e = sk.get_entity(self.entity_a);
+                    (e.point[0], e.point[1])
+                } else {
+                    (self.pt_a, self.pt_b)
+                };
+                let (au, av) = sk
+                    .get_entity(ha)
+                    .point_get_exprs_in_workplane(sk, self.workplane);
+                let (bu, bv) = sk
+                    .get_entity(hb)
+                    .point_get_exprs_in_workplane(sk, self.workplane);
+                if self.type_ == ConstraintType::Horizontal {
+                    self.add_eq(l, av - bv, 0);
+                } else {
+                    self.add_eq(l, au - bu, 0);
+                }
+            }
+
+            ConstraintType::SameOrientation => {
+                let a = sk.get_entity(self.entity_a);
+                let b = sk.get_entity(self.entity_b);
+                let au = a.normal_exprs_u(sk);
+                let an = a.normal_exprs_n(sk);
+                let bu = b.normal_exprs_u(sk);
+                let bv = b.normal_exprs_v(sk);
+                let bn = b.normal_exprs_n(sk);
+
+                self.add_eq_vector(l, Constraint::vectors_parallel_3d(an, bn, self.val_p));
+
+                // Allow either orientation for the coordinate system,
+                // depending on how it was drawn.
+                let d1 = au.dot(&bv);
+                let d2 = au.dot(&bu);
+                if d1.eval(&sk.param).abs() < d2.eval(&sk.param).abs() {
+                    self.add_eq(l, d1, 3);
+                } else {
+                    self.add_eq(l, d2, 3);
+                }
+            }
+
+            ConstraintType::Perpendicular | ConstraintType::Angle => {
+                let mut ae = sk.get_entity(self.entity_a).vector_get_exprs(sk);
+                let be = sk.get_entity(self.entity_b).vector_get_exprs(sk);
+                if self.other {
+                    ae = ae.scaled_by(Expr::from_constant(-1.0));
+                }
+                let c = Constraint::direction_cosine(sk, self.workplane, &ae, &be);
+
+                if self.type_ == ConstraintType::Angle {
+                    // The direction cosine is equal to the cosine of the
+                    // specified angle. Gain up as that gets close to one, so
+                    // that small angles aren't mistaken for redundancy by
+                    // the rank test.
+                    let rc = (self.val_a * PI / 180.0).cos();
+                    let arc = rc.abs();
+                    let mult = if arc > 0.99 {
+                        0.01 / (1.00001 - arc)
+                    } else {
+                        1.0
+                    };
+                    self.add_eq(
+                        l,
+                        (c - Expr::from_constant(rc)) * Expr::from_constant(mult),
+                        0,
+                    );
+                } else {
+                    self.add_eq(l, c, 0);
+                }
+            }
+
+            ConstraintType::EqualAngle => {
+                let mut a = sk.get_entity(self.entity_a).vector_get_exprs(sk);
+                let b = sk.get_entity(self.entity_b).vector_get_exprs(sk);
+                let c = sk.get_entity(self.entity_c).vector_get_exprs(sk);
+                let d = sk.get_entity(self.entity_d).vector_get_exprs(sk);
+                if self.other {
+                    a = a.scaled_by(Expr::from_constant(-1.0));
+                }
+                let cab = Constraint::direction_cosine(sk, self.workplane, &a, &b);
+                let ccd = Constraint::direction_cosine(sk, self.workplane, &c, &d);
+                self.add_eq(l, cab - ccd, 0);
+            }
+
+            ConstraintType::ArcLineTangent => {
+                let arc = sk.get_entity(self.entity_a);
+                let line = sk.get_entity(self.entity_b);
+                let ac = sk.get_entity(arc.point[0]).point_get_exprs(sk);
+                let ap = sk
+                    .get_entity(arc.point[if self.other { 2 } else { 1 }])
+                    .point_get_exprs(sk);
+                let ld = line.vector_get_exprs(sk);
+
+                // The line is perpendicular to the radius.
+                self.add_eq(l, ld.dot(&(ac - ap)), 0);
+            }
+
+            ConstraintType::CubicLineTangent => {
+                let cubic = sk.get_entity(self.entity_a);
+                let line = sk.get_entity(self.entity_b);
+                let a = if self.other {
+                    cubic.cubic_get_finish_tangent_exprs(sk)
+                } else {
+                    cubic.cubic_get_start_tangent_exprs(sk)
+                };
+                let b = line.vector_get_exprs(sk);
+
+                if self.workplane == FREE_IN_3D {
+                    self.add_eq_vector(l, Constraint::vectors_parallel_3d(a, b, self.val_p));
+                } else {
+                    let w = sk.get_entity(self.workplane);
+                    let wn = w.normal_entity(sk).normal_exprs_n(sk);
+                    self.add_eq(l, a.cross(&b).dot(&wn), 0);
+                }
+            }
+
+            ConstraintType::CurveCurveTangent => {
+                let mut parallel = true;
+                let mut dir = Vec::new();
+                for i in 0..2 {
+                    let e = sk.get_entity(if i == 0 { self.entity_a } else { self.entity_b });
+                    let oth = if i == 0 { self.other } else { self.other2 };
+
+                    match e.type_ {
+                        EntityBaseType::ArcOfCircle => {
+                            let center = sk.get_entity(e.point[0]).point_get_exprs(sk);
+                            let endpoint = sk
+                                .get_entity(e.point[if oth { 2 } else { 1 }])
+                                .point_get_exprs(sk);
+                            // The radius is normal to the tangent, not
+                            // parallel to it.
+                            dir.push(endpoint - center);
+                            parallel = !parallel;
+                        }
+                        EntityBaseType::Cubic => {
+                            dir.push(if oth {
+                                e.cubic_get_finish_tangent_exprs(sk)
+                            } else {
+                                e.cubic_get_start_tangent_exprs(sk)
+                            });
+                        }
+                        _ => panic!("Unexpected entity types for curve-curve tangent"),
+                    }
+                }
+                if parallel {
+                    let w = sk.get_entity(self.workplane);
+                    let wn = w.normal_entity(sk).normal_exprs_n(sk);
+                    self.add_eq(l, dir[0].cross(&dir[1]).dot(&wn), 0);
+                } else {
+                    self.add_eq(l, dir[0].dot(&dir[1]), 0);
+                }
+            }
+
+            ConstraintType::Parallel => {
+                let a = sk
+                    .get_entity(self.entity_a)
+                    .vector_get_exprs_in_workplane(sk, self.workplane);
+                let b = sk
+                    .get_entity(self.entity_b)
+                    .vector_get_exprs_in_workplane(sk, self.workplane);
+
+                if self.workplane == FREE_IN_3D {
+                    self.add_eq_vector(l, Constraint::vectors_parallel_3d(a, b, self.val_p));
+                } else {
+                    // Written in the workplane's csys, so the cross product
+                    // only has a z component.
+                    self.add_eq(l, a.x * b.y - a.y * b.x, 0);
+                }
+            }
+
+            ConstraintType::WhereDragged => {
+                let ep = sk.get_entity(self.pt_a);
+                if self.workplane == FREE_IN_3D {
+                    let ev = ep.point_get_exprs(sk);
+                    let v = ev.eval(&sk.param);
+                    self.add_eq(l, ev.x - Expr::from_constant(v.x), 0);
+                    self.add_eq(l, ev.y - Expr::from_constant(v.y), 1);
+                    self.add_eq(l, ev.z - Expr::from_constant(v.z), 2);
+                } else {
+                    let (u, v) = ep.point_get_exprs_in_workplane(sk, self.workplane);
+                    let uv = u.eval(&sk.param);
+                    let vv = v.eval(&sk.param);
+                    self.add_eq(l, u - Expr::from_constant(uv), 0);
+                    self.add_eq(l, v - Expr::from_constant(vv), 1);
+                }
+            }
+
+            ConstraintType::Comment => {}
+        }
+    }
+}
+
+fn check_point(sk: &Sketch, h: HEntity) -> Result<(), SketchError> {
+    if sk.find_entity(h)?.is_point() {
+        Ok(())
+    } else {
+        Err(SketchError::NotAPoint(h.v))
+    }
+}
+
+fn check_workplane(sk: &Sketch, h: HEntity) -> Result<(), SketchError> {
+    if sk.find_entity(h)?.is_workplane() {
+        Ok(())
+    } else {
+        Err(SketchError::NotAWorkplane(h.v))
+    }
+}
+
+fn check_entity(sk: &Sketch, h: HEntity, ok: fn(&EntityBase) -> bool) -> Result<(), SketchError> {
+    if ok(sk.find_entity(h)?) {
+        Ok(())
+    } else {
+        Err(SketchError::WrongEntityType(h.v))
+    }
+}
+
+fn is_line(e: &EntityBase) -> bool {
+    e.type_ == EntityBaseType::LineSegment
+}
+
+impl Constraint {
+    // Whether everything this constraint refers to is there and of the
+    // type generate_equations(..) takes it to be, so that can't panic.
+    pub fn check(&self, sk: &Sketch) -> Result<(), SketchError> {
+        let in_3d = self.workplane == FREE_IN_3D;
+        if !in_3d {
+            check_workplane(sk, self.workplane)?;
+        }
+        let needs_workplane = matches!(
+            self.type_,
+            ConstraintType::SymmetricHoriz
+                | ConstraintType::SymmetricVert
+                | ConstraintType::SymmetricLine
+                | ConstraintType::Horizontal
+                | ConstraintType::Vertical
+                | ConstraintType::CurveCurveTangent
+        );
+        if in_3d && needs_workplane {
+            return Err(SketchError::NotInWorkplane);
+        }
+
+        match self.type_ {
+            ConstraintType::PtPtDistance
+            | ConstraintType::PointsCoincident
+            | ConstraintType::SymmetricHoriz
+            | ConstraintType::SymmetricVert => {
+                check_point(sk, self.pt_a)?;
+                check_point(sk, self.pt_b)?;
+            }
+            ConstraintType::ProjPtDistance => {
+                check_point(sk, self.pt_a)?;
+                check_point(sk, self.pt_b)?;
+                check_entity(sk, self.entity_a, EntityBase::has_vector)?;
+            }
+            ConstraintType::PtLineDistance | ConstraintType::PtOnLine => {
+                check_point(sk, self.pt_a)?;
+                check_entity(sk, self.entity_a, is_line)?;
+            }
+            ConstraintType::PtPlaneDistance | ConstraintType::PtInPlane => {
+                check_point(sk, self.pt_a)?;
+                check_workplane(sk, self.entity_a)?;
+            }
+            ConstraintType::PtFaceDistance | ConstraintType::PtOnFace => {
+                check_point(sk, self.pt_a)?;
+                check_entity(sk, self.entity_a, EntityBase::is_face)?;
+            }
+            ConstraintType::EqualLengthLines
+            | ConstraintType::LengthRatio
+            | ConstraintType::LengthDifference => {
+                check_entity(sk, self.entity_a, is_line)?;
+                check_entity(sk, self.entity_b, is_line)?;
+            }
+            ConstraintType::EqLenPtLineD => {
+                check_entity(sk, self.entity_a, is_line)?;
+                check_point(sk, self.pt_a)?;
+                check_entity(sk, self.entity_b, is_line)?;
+            }
+            ConstraintType::EqPtLnDistances => {
+                check_point(sk, self.pt_a)?;
+                check_point(sk, self.pt_b)?;
+                check_entity(sk, self.entity_a, is_line)?;
+                check_entity(sk, self.entity_b, is_line)?;
+            }
+            ConstraintType::Diameter => {
+                check_entity(sk, self.entity_a, EntityBase::is_circle)?;
+            }
+            ConstraintType::EqualRadius => {
+                check_entity(sk, self.entity_a, EntityBase::is_circle)?;
+                check_entity(sk, self.entity_b, EntityBase::is_circle)?;
+            }
+            ConstraintType::PtOnCircle => {
+                check_point(sk, self.pt_a)?;
+                check_entity(sk, self.entity_a, EntityBase::is_circle)?;
+            }
+            ConstraintType::AtMidpoint => {
+                check_entity(sk, self.entity_a, is_line)?;
+                if self.pt_a != NO_ENTITY {
+                    check_point(sk, self.pt_a)?;
+                } else {
+                    check_workplane(sk, self.entity_b)?;
+                }
+            }
+            ConstraintType::Symmetric => {
+                check_point(sk, self.pt_a)?;
+                check_point(sk, self.pt_b)?;
+                check_workplane(sk, self.entity_a)?;
+            }
+            ConstraintType::SymmetricLine => {
+                check_point(sk, self.pt_a)?;
+                check_point(sk, self.pt_b)?;
+                check_entity(sk, self.entity_a, is_line)?;
+            }
+            ConstraintType::Horizontal | ConstraintType::Vertical => {
+                if self.entity_a != NO_ENTITY {
+                    check_entity(sk, self.entity_a, is_line)?;
+                } else {
+                    check_point(sk, self.pt_a)?;
+                    check_point(sk, self.pt_b)?;
+                }
+            }
+            ConstraintType::SameOrientation => {
+                check_entity(sk, self.entity_a, EntityBase::is_normal)?;
+                check_entity(sk, self.entity_b, EntityBase::is_normal)?;
+            }
+            ConstraintType::Perpendicular | ConstraintType::Angle | ConstraintType::Parallel => {
+                check_entity(sk, self.entity_a, EntityBase::has_vector)?;
+                check_entity(sk, self.entity_b, EntityBase::has_vector)?;
+            }
+            ConstraintType::EqualAngle => {
+                for h in [self.entity_a, self.entity_b, self.entity_c, self.entity_d] {
+                    check_entity(sk, h, EntityBase::has_vector)?;
+                }
+            }
+            ConstraintType::ArcLineTangent => {
+                check_entity(sk, self.entity_a, |e| {
+                    e.type_ == EntityBaseType::ArcOfCircle
+                })?;
+                check_entity(sk, self.entity_b, is_line)?;
+            }
+            ConstraintType::CubicLineTangent => {
+                check_entity(sk, self.entity_a, |e| e.type_ == EntityBaseType::Cubic)?;
+                check_entity(sk, self.entity_b, is_line)?;
+            }
+            ConstraintType::CurveCurveTangent => {
+                for h in [self.entity_a, self.entity_b] {
+                    check_entity(sk, h, |e| {
+                        matches!(e.type_, EntityBaseType::ArcOfCircle | EntityBaseType::Cubic)
+                    })?;
+                }
+            }
+            ConstraintType::WhereDragged => {
+                check_point(sk, self.pt_a)?;
+            }
+            ConstraintType::Comment => {}
+        }
+        Ok(())
+    }
+}
+
+impl Sketch {
+    // Adds the constraint under a new handle, once everything it refers to
+    // is known to be there and of the right type; its group is solved
+    // again on regenerating.
+    pub fn add_constraint(&mut self, c: Constraint) -> Result<HConstraint, SketchError> {
+        self.find_group(c.group)?;
+        c.check(self)?;
+        let hg = c.group;
+        let hc = self
+            .constraint
+            .add_and_assign_id(c)
+            .ok_or(SketchError::OutOfHandles)?;
+        self.mark_group_dirty(hg);
+        Ok(hc)
+    }
+}
@@ -0,0 +1,284 @@
+use crate::sketch::{HConstraint, HEntity, HGroup, HParam, HRequest, Sketch};
+
+use std::collections::HashSet;
+
+// Something picked for deletion.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum SketchItem {
+    Group(HGroup),
+    Request(HRequest),
+    Entity(HEntity),
+    Constraint(HConstraint),
+}
+
+// Everything a delete(..) took out, each in handle order.
+#[derive(Default)]
+pub struct Removed {
+    pub group: Vec<HGroup>,
+    pub request: Vec<HRequest>,
+    pub entity: Vec<HEntity>,
+    pub constraint: Vec<HConstraint>,
+    pub param: Vec<HParam>,
+}
+
+impl Removed {
+    // What went that wasn't asked for, like "also removed 3 constraints";
+    // empty if nothing else went. The entities a request makes count as
+    // that request.
+    pub fn also_removed(&self, items: &[SketchItem]) -> String {
+        let request_asked = |hr: HRequest| {
+            items.iter().any(|i| match i {
+                SketchItem::Request(h) => *h == hr,
+                SketchItem::Entity(he) => he.is_from_request() && he.request() == hr,
+                _ => false,
+            })
+        };
+        let groups = self
+            .group
+            .iter()
+            .filter(|h| !items.contains(&SketchItem::Group(**h)))
+            .count();
+        let requests = self.request.iter().filter(|h| !request_asked(**h)).count();
+        let entities = self
+            .entity
+            .iter()
+            .filter(|h| !(h.is_from_request() && self.request.contains(&h.request())))
+            .filter(|h| !items.contains(&SketchItem::Entity(**h)))
+            .count();
+        let constraints = self
+            .constraint
+            .iter()
+            .filter(|h| !items.contains(&SketchItem::Constraint(**h)))
+            .count();
+
+        let parts: Vec<String> = [
+            (groups, "group", "groups"),
+            (requests, "request", "requests"),
+            (entities, "entity", "entities"),
+            (constraints, "constraint", "constraints"),
+        ]
+        .iter()
+        .filter(|(n, _, _)| *n > 0)
+        .map(|(n, one, many)| format!("{} {}", n, if *n == 1 { one } else { many }))
+        .collect();
+        if parts.is_empty() {
+            String::new()
+        } else {
+            format!("also removed {}", parts.join(", "))
+        }
+    }
+}
+
+impl Sketch {
+    // Deletes the items and everything that depends on them, so nothing is
+    // left referring to something that's gone. As in SolveSpace, picking an
+    // entity that a request made takes the whole request.
+    pub fn delete(&mut self, items: &[SketchItem]) -> Removed {
+        self.group.clear_tags();
+        self.request.clear_tags();
+        self.entity.clear_tags();
+        self.constraint.clear_tags();
+        self.param.clear_tags();
+
+        for item in items {
+            match *item {
+                SketchItem::Group(hg) => self.group.tag(&hg, 1),
+                SketchItem::Request(hr) => self.request.tag(&hr, 1),
+                SketchItem::Entity(he) => {
+                    if he.is_from_request() && self.request.contains_key(&he.request()) {
+                        self.request.tag(&he.request(), 1);
+                    } else {
+                        self.entity.tag(&he, 1);
+                    }
+                }
+                SketchItem::Constraint(hc) => self.constraint.tag(&hc, 1),
+            }
+        }
+        while self.tag_dependents() {}
+
+        // Whatever came after the first group touched is built on something
+        // that's now gone.
+        let group = &self.group;
+        let order_of = |hg: HGroup| group.get(&hg).map(|g| g.order);
+        let dirty_from = self
+            .group
+            .values()
+            .filter(|g| g.tag != 0)
+            .map(|g| g.order)
+            .chain(
+                self.request
+                    .values()
+                    .filter(|r| r.tag != 0)
+                    .filter_map(|r| order_of(r.group)),
+            )
+            .chain(
+                self.entity
+                    .values()
+                    .filter(|e| e.tag != 0)
+                    .filter_map(|e| order_of(e.group)),
+            )
+            .chain(
+                self.constraint
+                    .values()
+                    .filter(|c| c.tag != 0)
+                    .filter_map(|c| order_of(c.group)),
+            )
+            .min();
+
+        // A group's own entities are made again whenever it regenerates, so
+        // they're only taken out, leaving their handles free to come back;
+        // whatever it can't make any more stays gone.
+        let made_by_groups: Vec<HEntity> = self
+            .entity
+            .values()
+            .filter(|e| e.tag != 0 && !e.h.is_from_request())
+            .map(|e| e.h)
+            .collect();
+        for h in made_by_groups.iter() {
+            self.entity.take(h);
+        }
+        let mut entity: Vec<HEntity> = self.entity.remove_tagged().iter().map(|e| e.h).collect();
+        entity.extend(made_by_groups);
+        entity.sort();
+
+        let removed = Removed {
+            group: self.group.remove_tagged().iter().map(|g| g.h).collect(),
+            request: self.request.remove_tagged().iter().map(|r| r.h).collect(),
+            entity,
+            constraint: self
+                .constraint
+                .remove_tagged()
+                .iter()
+                .map(|c| c.h)
+                .collect(),
+            param: self.param.remove_tagged().iter().map(|p| p.h).collect(),
+        };
+
+        let entity = &self.entity;
+        self.traced.retain(|h| entity.contains_key(h));
+        self.trace_path.retain(|h, _| entity.contains_key(h));
+        if let Some(order) = dirty_from {
+            self.mark_groups_dirty_from(order);
+        }
+        removed
+    }
+
+    // Tags whatever refers to something already tagged; false once there's
+    // nothing more to tag.
+    fn tag_dependents(&mut self) -> bool {
+        let group_tagged = |h: HGroup| self.group.get(&h).is_some_and(|g| g.tag != 0);
+        let request_tagged = |h: HRequest| self.request.get(&h).is_some_and(|r| r.tag != 0);
+        let entity_tagged = |h: HEntity| self.entity.get(&h).is_some_and(|e| e.tag != 0);
+
+        // A request can't lose just some of its entities; it would only
+        // make them again.
+        let partly_tagged: HashSet<HRequest> = self
+            .entity
+            .values()
+            .filter(|e| e.tag != 0 && e.h.is_from_request())
+            .map(|e| e.h.request())
+            .collect();
+
+        let groups: Vec<HGroup> = self
+            .group
+            .values()
+            .filter(|g| g.tag == 0)
+            .filter(|g| {
+                group_tagged(g.op_a)
+                    || group_tagged(g.op_b)
+                    || entity_tagged(g.active_workplane)
+                    || entity_tagged(g.predef.origin)
+                    || entity_tagged(g.predef.entity_b)
+                    || entity_tagged(g.predef.entity_c)
+            })
+            .map(|g| g.h)
+            .collect();
+        let requests: Vec<HRequest> = self
+            .request
+            .values()
+            .filter(|r| r.tag == 0)
+            .filter(|r| {
+                group_tagged(r.group) || entity_tagged(r.workplane) || partly_tagged.contains(&r.h)
+            })
+            .map(|r| r.h)
+            .collect();
+        let entities: Vec<HEntity> = self
+            .entity
+            .values()
+            .filter(|e| e.tag == 0)
+            .filter(|e| {
+                group_tagged(e.group)
+                    || (e.h.is_from_request() && request_tagged(e.h.request()))
+                    || entity_tagged(e.workplane)
+                    || e.point.iter().any(|h| entity_tagged(*h))
+                    || entity_tagged(e.normal)
+                    || entity_tagged(e.distance)
+            })
+            .map(|e| e.h)
+            .collect();
+        let constraints: Vec<HConstraint> = self
+            .constraint
+            .values()
+            .filter(|c| c.tag == 0)
+            .filter(|c| {
+                group_tagged(c.group)
+                    || [
+                        c.workplane,
+                        c.pt_a,
+                        c.pt_b,
+                        c.entity_a,
+                        c.entity_b,
+                        c.entity_c,
+                        c.entity_d,
+                    ]
+                    .iter()
+                    .any(|h| entity_tagged(*h))
+            })
+            .map(|c| c.h)
+            .collect();
+
+        // Params go with whatever they belong to. The params of a group's
+        // entities are the group's own, so they go with the group.
+        let mut params: Vec<HParam> = self
+            .entity
+            .values()
+            .filter(|e| e.tag != 0 && e.h.is_from_request())
+            .flat_map(|e| e.param.iter().cloned())
+            .chain(
+                self.constraint
+                    .values()
+                    .filter(|c| c.tag != 0)
+                    .map(|c| c.val_p),
+            )
+            .chain(
+                self.param
+                    .values()
+                    .filter(|p| p.h.is_from_group() && group_tagged(p.h.group()))
+                    .map(|p| p.h),
+            )
+            .collect();
+        params.retain(|h| self.param.get(h).is_some_and(|p| p.tag == 0));
+
+        let more = !(groups.is_empty()
+            && requests.is_empty()
+            && entities.is_empty()
+            && constraints.is_empty()
+            && params.is_empty());
+        for h in groups {
+            self.group.tag(&h, 1);
+        }
+        for h in requests {
+            self.request.tag(&h, 1);
+        }
+        for h in entities {
+            self.entity.tag(&h, 1);
+        }
+        for h in constraints {
+            self.constraint.tag(&h, 1);
+        }
+        for h in params {
+            self.param.tag(&h, 1);
+        }
+        more
+    }
+}
@@ -1,15 +1,9 @@
-use crate::app::Sketch;
-use crate::sketch::HParam;
-
 use std::ops::Add;
 use std::ops::Mul;
 use std::ops::Neg;
 use std::ops::Sub;
 
-const ANGLE_COS_EPS: f64 = 1e-6;
-const LENGTH_EPS: f64 = 1e-6;
-const VERY_POSITIVE: f64 = 1e10;
-const VERY_NEGATIVE: f64 = -1e10;
+pub const LENGTH_EPS: f64 = 1e-6;
 
 #[derive(Clone, Copy)]
 pub struct Quaternion {
@@ -19,7 +13,7 @@ pub struct Quaternion {
     pub vz: f64,
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Default)]
 pub struct Vector {
     pub x: f64,
     pub y: f64,
@@ -45,10 +39,6 @@ impl Quaternion {
         Quaternion { w, vx, vy, vz }
     }
 
-    pub fn from_hparams(sk: &mut Sketch, w: HParam, vx: HParam, vy: HParam, vz: HParam) -> Self {
-        unimplemented!();
-    }
-
     pub fn from_uv(u: Vector, v: Vector) -> Self {
         let n = u.cross(v);
         let q;
@@ -89,7 +79,7 @@ impl Quaternion {
             }
         }
 
-        return q.with_magnitude(1.0);
+        q.with_magnitude(1.0)
     }
 
     pub fn from_axis_angle(mut axis: Vector, dtheta: f64) -> Self {
@@ -240,15 +230,11 @@ impl Vector {
         Vector { x, y, z }
     }
 
-    pub fn from_hparams(x: HParam, y: HParam, z: HParam) -> Self {
-        unimplemented!();
-    }
-
     pub fn cross(&self, rhs: Self) -> Self {
         Vector {
             x: self.y * rhs.z - self.z * rhs.y,
             y: self.z * rhs.x - self.x * rhs.z,
-            z: self.x * rhs.y - self.y * rhs.z,
+            z: self.x * rhs.y - self.y * rhs.x,
         }
     }
 
@@ -354,9 +340,9 @@ impl Vector {
             if v.abs() > 1e-100 {
                 panic!("Vector::with_magnatude(...) of zero vector!");
             }
-            return Vector::new(0.0, 0.0, 0.0);
+            Vector::new(0.0, 0.0, 0.0)
         } else {
-            return *self * (v / m);
+            *self * (v / m)
         }
     }
 
@@ -371,7 +357,7 @@ impl Vector {
         if dv.z.abs() > tol {
             return false;
         };
-        return dv.mag_squared() < tol * tol;
+        dv.mag_squared() < tol * tol
     }
 
     pub fn equals_with_def_tol(&self, rhs: Self) -> bool {
@@ -427,6 +413,7 @@ impl Neg for Vector {
     }
 }
 
+#[derive(Clone, Copy)]
 pub struct RgbaColor {
     pub red: u8,
     pub green: u8,
@@ -0,0 +1,741 @@
+use crate::dsc::{Quaternion, Vector};
+use crate::expr::{Expr, ExprQuaternion, ExprVector};
+use crate::sketch::{
+    Constraint, ConstraintType, EntityBase, EntityBaseType, Equation, HEntity, HGroup, Sketch,
+    FREE_IN_3D, MAX_PARAMS_IN_ENTITY, MAX_POINTS_IN_ENTITY, NO_ENTITY, NO_PARAM,
+};
+use crate::surface::SBezier;
+use crate::system::System;
+
+use std::f64::consts::PI;
+
+impl EntityBase {
+    pub fn new(h: HEntity, type_: EntityBaseType) -> Self {
+        EntityBase {
+            tag: 0,
+            h,
+            type_,
+            group: HGroup { v: 0 },
+            workplane: FREE_IN_3D,
+            point: [NO_ENTITY; MAX_POINTS_IN_ENTITY],
+            extra_points: 0,
+            normal: NO_ENTITY,
+            distance: NO_ENTITY,
+            param: [NO_PARAM; MAX_PARAMS_IN_ENTITY],
+            num_point: Vector::new(0.0, 0.0, 0.0),
+            num_normal: Quaternion::new(1.0, 0.0, 0.0, 0.0),
+            num_distance: 0.0,
+            times_applied: 0,
+            construction: false,
+        }
+    }
+
+    pub fn is_point(&self) -> bool {
+        matches!(
+            self.type_,
+            EntityBaseType::PointIn3D
+                | EntityBaseType::PointIn2D
+                | EntityBaseType::PointNTrans
+                | EntityBaseType::PointNRotTrans
+                | EntityBaseType::PointNCopy
+                | EntityBaseType::PointNRotAA
+                | EntityBaseType::PointNRotAxisTrans
+        )
+    }
+
+    pub fn is_normal(&self) -> bool {
+        matches!(
+            self.type_,
+            EntityBaseType::NormalIn3D
+                | EntityBaseType::NormalIn2D
+                | EntityBaseType::NormalNCopy
+                | EntityBaseType::NormalNRot
+                | EntityBaseType::NormalRotAA
+        )
+    }
+
+    pub fn is_distance(&self) -> bool {
+        matches!(
+            self.type_,
+            EntityBaseType::Distance | EntityBaseType::DistanceNCopy
+        )
+    }
+
+    pub fn is_face(&self) -> bool {
+        matches!(
+            self.type_,
+            EntityBaseType::FaceNormalPt
+                | EntityBaseType::FaceXProd
+                | EntityBaseType::FaceNRotTrans
+                | EntityBaseType::FaceNTrans
+                | EntityBaseType::FaceNRotAA
+                | EntityBaseType::FaceRotNormalPt
+                | EntityBaseType::FaceNRotAxisTrans
+        )
+    }
+
+    pub fn is_workplane(&self) -> bool {
+        self.type_ == EntityBaseType::Workplane
+    }
+
+    pub fn is_circle(&self) -> bool {
+        matches!(
+            self.type_,
+            EntityBaseType::Circle | EntityBaseType::ArcOfCircle
+        )
+    }
+
+    pub fn has_vector(&self) -> bool {
+        self.type_ == EntityBaseType::LineSegment || self.is_normal() || self.is_face()
+    }
+
+    pub fn normal_entity<'a>(&self, sk: &'a Sketch) -> &'a EntityBase {
+        sk.get_entity(self.normal)
+    }
+
+    // Whether any of the params this entity depends on were found to be free
+    // by the last solve; a line is free if either of its points is.
+    pub fn is_free(&self, sk: &Sketch) -> bool {
+        let param_free = self
+            .param
+            .iter()
+            .any(|h| *h != NO_PARAM && sk.param.get(h).is_some_and(|p| p.free));
+        if param_free {
+            return true;
+        }
+        self.point
+            .iter()
+            .chain([self.normal, self.distance].iter())
+            .any(|h| *h != NO_ENTITY && sk.entity.get(h).is_some_and(|e| e.is_free(sk)))
+    }
+
+    fn axis_angle_quaternion_exprs(&self, param0: usize) -> ExprQuaternion {
+        let theta =
+            Expr::from_constant(self.times_applied as f64) * Expr::from_param(self.param[param0]);
+        let half = theta / Expr::from_constant(2.0);
+        let c = half.clone().cos();
+        let s = half.sin();
+        ExprQuaternion {
+            w: c,
+            vx: s.clone() * Expr::from_param(self.param[param0 + 1]),
+            vy: s.clone() * Expr::from_param(self.param[param0 + 2]),
+            vz: s * Expr::from_param(self.param[param0 + 3]),
+        }
+    }
+
+    fn translation_exprs(&self) -> ExprVector {
+        ExprVector::from_params(self.param[0], self.param[1], self.param[2])
+    }
+
+    fn num_normal_vector_exprs(&self) -> ExprVector {
+        ExprVector::from_vector(Vector::new(
+            self.num_normal.vx,
+            self.num_normal.vy,
+            self.num_normal.vz,
+        ))
+    }
+
+    pub fn point_get_exprs(&self, sk: &Sketch) -> ExprVector {
+        match self.type_ {
+            EntityBaseType::PointIn3D => {
+                ExprVector::from_params(self.param[0], self.param[1], self.param[2])
+            }
+            EntityBaseType::PointIn2D => {
+                let c = sk.get_entity(self.workplane);
+                let u = c.normal_entity(sk).normal_exprs_u(sk);
+                let v = c.normal_entity(sk).normal_exprs_v(sk);
+                c.workplane_get_offset_exprs(sk)
+                    + u.scaled_by(Expr::from_param(self.param[0]))
+                    + v.scaled_by(Expr::from_param(self.param[1]))
+            }
+            EntityBaseType::PointNTrans => {
+                let orig = ExprVector::from_vector(self.num_point);
+                let trans = self.translation_exprs();
+                orig + trans.scaled_by(Expr::from_constant(self.times_applied as f64))
+            }
+            EntityBaseType::PointNRotTrans => {
+                let orig = ExprVector::from_vector(self.num_point);
+                let trans = self.translation_exprs();
+                let q = ExprQuaternion::from_params(
+                    self.param[3],
+                    self.param[4],
+                    self.param[5],
+                    self.param[6],
+                );
+                q.rotate(&orig) + trans
+            }
+            EntityBaseType::PointNRotAA => {
+                let orig = ExprVector::from_vector(self.num_point);
+                let trans = self.translation_exprs();
+                let q = self.axis_angle_quaternion_exprs(3);
+                q.rotate(&(orig - trans.clone())) + trans
+            }
+            EntityBaseType::PointNRotAxisTrans => {
+                let orig = ExprVector::from_vector(self.num_point);
+                let trans = self.translation_exprs();
+                let displace = ExprVector::from_params(self.param[4], self.param[5], self.param[6])
+                    .with_magnitude(Expr::from_constant(1.0))
+                    .scaled_by(Expr::from_constant(self.times_applied as f64))
+                    .scaled_by(Expr::from_param(self.param[7]));
+                let q = self.axis_angle_quaternion_exprs(3);
+                q.rotate(&(orig - trans.clone())) + trans + displace
+            }
+            EntityBaseType::PointNCopy => ExprVector::from_vector(self.num_point),
+            _ => panic!("Unexpected entity type for point_get_exprs(...)"),
+        }
+    }
+
+    pub fn point_get_num(&self, sk: &Sketch) -> Vector {
+        self.point_get_exprs(sk).eval(&sk.param)
+    }
+
+    pub fn point_get_exprs_in_workplane(&self, sk: &Sketch, wrkpl: HEntity) -> (Expr, Expr) {
+        if self.type_ == EntityBaseType::PointIn2D && self.workplane == wrkpl {
+            // Already written in the coordinates of that workplane.
+            return (
+                Expr::from_param(self.param[0]),
+                Expr::from_param(self.param[1]),
+            );
+        }
+
+        let w = sk.get_entity(wrkpl);
+        let wp = w.workplane_get_offset_exprs(sk);
+        let wu = w.normal_entity(sk).normal_exprs_u(sk);
+        let wv = w.normal_entity(sk).normal_exprs_v(sk);
+
+        let ev = self.point_get_exprs(sk) - wp;
+        (ev.dot(&wu), ev.dot(&wv))
+    }
+
+    pub fn point_get_exprs_in_workplane_vector(&self, sk: &Sketch, wrkpl: HEntity) -> ExprVector {
+        if wrkpl == FREE_IN_3D {
+            return self.point_get_exprs(sk);
+        }
+        let (u, v) = self.point_get_exprs_in_workplane(sk, wrkpl);
+        ExprVector::new(u, v, Expr::from_constant(0.0))
+    }
+
+    pub fn normal_get_exprs(&self, sk: &Sketch) -> ExprQuaternion {
+        match self.type_ {
+            EntityBaseType::NormalIn3D => ExprQuaternion::from_params(
+                self.param[0],
+                self.param[1],
+                self.param[2],
+                self.param[3],
+            ),
+            EntityBaseType::NormalIn2D => {
+                let wrkpl = sk.get_entity(self.workplane);
+                wrkpl.normal_entity(sk).normal_get_exprs(sk)
+            }
+            EntityBaseType::NormalNCopy => ExprQuaternion::from_quaternion(self.num_normal),
+            EntityBaseType::NormalNRot => {
+                let orig = ExprQuaternion::from_quaternion(self.num_normal);
+                ExprQuaternion::from_params(
+                    self.param[0],
+                    self.param[1],
+                    self.param[2],
+                    self.param[3],
+                )
+                .times(&orig)
+            }
+            EntityBaseType::NormalRotAA => {
+                let orig = ExprQuaternion::from_quaternion(self.num_normal);
+                self.axis_angle_quaternion_exprs(0).times(&orig)
+            }
+            _ => panic!("Unexpected entity type for normal_get_exprs(...)"),
+        }
+    }
+
+    pub fn normal_exprs_u(&self, sk: &Sketch) -> ExprVector {
+        self.normal_get_exprs(sk).rotation_u()
+    }
+
+    pub fn normal_exprs_v(&self, sk: &Sketch) -> ExprVector {
+        self.normal_get_exprs(sk).rotation_v()
+    }
+
+    pub fn normal_exprs_n(&self, sk: &Sketch) -> ExprVector {
+        self.normal_get_exprs(sk).rotation_n()
+    }
+
+    pub fn workplane_get_offset_exprs(&self, sk: &Sketch) -> ExprVector {
+        sk.get_entity(self.point[0]).point_get_exprs(sk)
+    }
+
+    // The plane is n dot (p - p0) = 0, returned as n and n dot p0.
+    pub fn workplane_get_plane_exprs(&self, sk: &Sketch) -> (ExprVector, Expr) {
+        if self.is_face() {
+            let n = self.face_get_normal_exprs();
+            let dn = self.face_get_point_exprs(sk).dot(&n);
+            return (n, dn);
+        }
+        if !self.is_workplane() {
+            panic!("Unexpected entity type for workplane_get_plane_exprs(...)");
+        }
+        let n = self.normal_entity(sk).normal_exprs_n(sk);
+        let dn = self.workplane_get_offset_exprs(sk).dot(&n);
+        (n, dn)
+    }
+
+    pub fn distance_get_expr(&self) -> Expr {
+        match self.type_ {
+            EntityBaseType::Distance => Expr::from_param(self.param[0]),
+            EntityBaseType::DistanceNCopy => Expr::from_constant(self.num_distance),
+            _ => panic!("Unexpected entity type for distance_get_expr(...)"),
+        }
+    }
+
+    pub fn circle_get_radius_expr(&self, sk: &Sketch) -> Expr {
+        match self.type_ {
+            EntityBaseType::Circle => sk.get_entity(self.distance).distance_get_expr(),
+            EntityBaseType::ArcOfCircle => {
+                Constraint::distance(sk, self.workplane, self.point[0], self.point[1])
+            }
+            _ => panic!("Unexpected entity type for circle_get_radius_expr(...)"),
+        }
+    }
+
+    pub fn vector_get_exprs_in_workplane(&self, sk: &Sketch, wrkpl: HEntity) -> ExprVector {
+        if self.is_face() {
+            return self.face_get_normal_exprs();
+        }
+        if self.type_ == EntityBaseType::LineSegment {
+            return sk
+                .get_entity(self.point[0])
+                .point_get_exprs_in_workplane_vector(sk, wrkpl)
+                - sk.get_entity(self.point[1])
+                    .point_get_exprs_in_workplane_vector(sk, wrkpl);
+        }
+        if !self.is_normal() {
+            panic!("Unexpected entity type for vector_get_exprs(...)");
+        }
+
+        let ev = self.normal_exprs_n(sk);
+        if wrkpl == FREE_IN_3D {
+            return ev;
+        }
+        let w = sk.get_entity(wrkpl);
+        let wu = w.normal_entity(sk).normal_exprs_u(sk);
+        let wv = w.normal_entity(sk).normal_exprs_v(sk);
+        ExprVector::new(ev.dot(&wu), ev.dot(&wv), Expr::from_constant(0.0))
+    }
+
+    pub fn vector_get_exprs(&self, sk: &Sketch) -> ExprVector {
+        self.vector_get_exprs_in_workplane(sk, FREE_IN_3D)
+    }
+
+    pub fn cubic_get_start_tangent_exprs(&self, sk: &Sketch) -> ExprVector {
+        let poa = sk.get_entity(self.point[0]).point_get_exprs(sk);
+        let pob = sk.get_entity(self.point[1]).point_get_exprs(sk);
+        pob - poa
+    }
+
+    pub fn cubic_get_finish_tangent_exprs(&self, sk: &Sketch) -> ExprVector {
+        let poa = sk
+            .get_entity(self.point[3 + self.extra_points])
+            .point_get_exprs(sk);
+        let pob = sk
+            .get_entity(self.point[2 + self.extra_points])
+            .point_get_exprs(sk);
+        poa - pob
+    }
+
+    pub fn face_get_normal_exprs(&self) -> ExprVector {
+        match self.type_ {
+            EntityBaseType::FaceNormalPt => {
+                let v = Vector::new(self.num_normal.vx, self.num_normal.vy, self.num_normal.vz);
+                ExprVector::from_vector(v.with_magnitude(1.0))
+            }
+            EntityBaseType::FaceXProd => {
+                let vc = self.translation_exprs();
+                let vn = self.num_normal_vector_exprs();
+                vc.cross(&vn).with_magnitude(Expr::from_constant(1.0))
+            }
+            EntityBaseType::FaceNRotTrans => {
+                // The numerical normal has magnitude one, and the rotation
+                // doesn't change that.
+                let q = ExprQuaternion::from_params(
+                    self.param[3],
+                    self.param[4],
+                    self.param[5],
+                    self.param[6],
+                );
+                q.rotate(&self.num_normal_vector_exprs())
+            }
+            EntityBaseType::FaceNTrans => self.num_normal_vector_exprs(),
+            EntityBaseType::FaceNRotAA
+            | EntityBaseType::FaceRotNormalPt
+            | EntityBaseType::FaceNRotAxisTrans => {
+                let q = self.axis_angle_quaternion_exprs(3);
+                q.rotate(&self.num_normal_vector_exprs())
+            }
+            _ => panic!("Unexpected entity type for face_get_normal_exprs(...)"),
+        }
+    }
+
+    pub fn face_get_point_exprs(&self, sk: &Sketch) -> ExprVector {
+        match self.type_ {
+            EntityBaseType::FaceNormalPt | EntityBaseType::FaceRotNormalPt => {
+                sk.get_entity(self.point[0]).point_get_exprs(sk)
+            }
+            EntityBaseType::FaceXProd => ExprVector::from_vector(self.num_point),
+            EntityBaseType::FaceNRotTrans => {
+                let orig = ExprVector::from_vector(self.num_point);
+                let trans = self.translation_exprs();
+                let q = ExprQuaternion::from_params(
+                    self.param[3],
+                    self.param[4],
+                    self.param[5],
+                    self.param[6],
+                );
+                q.rotate(&orig) + trans
+            }
+            EntityBaseType::FaceNTrans => {
+                let orig = ExprVector::from_vector(self.num_point);
+                let trans = self.translation_exprs();
+                orig + trans.scaled_by(Expr::from_constant(self.times_applied as f64))
+            }
+            EntityBaseType::FaceNRotAA => {
+                let orig = ExprVector::from_vector(self.num_point);
+                let trans = self.translation_exprs();
+                let q = self.axis_angle_quaternion_exprs(3);
+                q.rotate(&(orig - trans.clone())) + trans
+            }
+            EntityBaseType::FaceNRotAxisTrans => {
+                let orig = ExprVector::from_vector(self.num_point);
+                let trans = self.translation_exprs();
+                let displace = ExprVector::from_params(self.param[4], self.param[5], self.param[6])
+                    .with_magnitude(Expr::from_constant(1.0))
+                    .scaled_by(Expr::from_constant(self.times_applied as f64))
+                    .scaled_by(Expr::from_param(self.param[7]));
+                let q = self.axis_angle_quaternion_exprs(3);
+                q.rotate(&(orig - trans.clone())) + trans + displace
+            }
+            _ => panic!("Unexpected entity type for face_get_point_exprs(...)"),
+        }
+    }
+
+    // Numeric versions of the above, evaluated where the params are now.
+    pub fn normal_get_num(&self, sk: &Sketch) -> Quaternion {
+        self.normal_get_exprs(sk).eval(&sk.param)
+    }
+
+    pub fn normal_u(&self, sk: &Sketch) -> Vector {
+        self.normal_get_num(sk).rotation_u()
+    }
+
+    pub fn normal_v(&self, sk: &Sketch) -> Vector {
+        self.normal_get_num(sk).rotation_v()
+    }
+
+    pub fn normal_n(&self, sk: &Sketch) -> Vector {
+        self.normal_get_num(sk).rotation_n()
+    }
+
+    pub fn workplane_get_offset(&self, sk: &Sketch) -> Vector {
+        sk.get_entity(self.point[0]).point_get_num(sk)
+    }
+
+    pub fn workplane_get_plane(&self, sk: &Sketch) -> (Vector, f64) {
+        let (n, dn) = self.workplane_get_plane_exprs(sk);
+        (n.eval(&sk.param), dn.eval(&sk.param))
+    }
+
+    // Origin, then the u, v and n axes.
+    pub fn workplane_get_csys(&self, sk: &Sketch) -> (Vector, Vector, Vector, Vector) {
+        let q = self.normal_entity(sk).normal_get_num(sk);
+        (
+            self.workplane_get_offset(sk),
+            q.rotation_u(),
+            q.rotation_v(),
+            q.rotation_n(),
+        )
+    }
+
+    pub fn distance_get_num(&self, sk: &Sketch) -> f64 {
+        self.distance_get_expr().eval(&sk.param)
+    }
+
+    pub fn circle_get_radius_num(&self, sk: &Sketch) -> f64 {
+        self.circle_get_radius_expr(sk).eval(&sk.param)
+    }
+
+    // Where the arc starts and finishes, measured about its normal from its
+    // u axis, and the angle it sweeps; coincident ends make a full circle,
+    // not an empty arc.
+    pub fn arc_get_angles(&self, sk: &Sketch) -> (f64, f64, f64) {
+        let q = self.normal_entity(sk).normal_get_num(sk);
+        let (u, v) = (q.rotation_u(), q.rotation_v());
+        let c = sk.get_entity(self.point[0]).point_get_num(sk);
+        let pa = sk.get_entity(self.point[1]).point_get_num(sk) - c;
+        let pb = sk.get_entity(self.point[2]).point_get_num(sk) - c;
+        let theta_a = pa.dot(v).atan2(pa.dot(u));
+        let theta_b = pb.dot(v).atan2(pb.dot(u));
+        let mut dtheta = theta_b - theta_a;
+        while dtheta < 1e-6 {
+            dtheta += 2.0 * PI;
+        }
+        while dtheta > 2.0 * PI {
+            dtheta -= 2.0 * PI;
+        }
+        (theta_a, theta_b, dtheta)
+    }
+
+    pub fn vector_get_num(&self, sk: &Sketch) -> Vector {
+        self.vector_get_exprs(sk).eval(&sk.param)
+    }
+
+    // A point to draw the vector from.
+    pub fn vector_get_ref_point(&self, sk: &Sketch) -> Vector {
+        if self.is_face() {
+            return self.face_get_point_num(sk);
+        }
+        match self.type_ {
+            EntityBaseType::LineSegment => {
+                let a = sk.get_entity(self.point[0]).point_get_num(sk);
+                let b = sk.get_entity(self.point[1]).point_get_num(sk);
+                (a + b) * 0.5
+            }
+            _ if self.is_normal() => sk.get_entity(self.point[0]).point_get_num(sk),
+            _ => panic!("Unexpected entity type for vector_get_ref_point(...)"),
+        }
+    }
+
+    pub fn endpoint_start(&self, sk: &Sketch) -> Vector {
+        match self.type_ {
+            EntityBaseType::LineSegment | EntityBaseType::Cubic => {
+                sk.get_entity(self.point[0]).point_get_num(sk)
+            }
+            EntityBaseType::ArcOfCircle => sk.get_entity(self.point[1]).point_get_num(sk),
+            _ => panic!("Unexpected entity type for endpoint_start(...)"),
+        }
+    }
+
+    pub fn endpoint_finish(&self, sk: &Sketch) -> Vector {
+        match self.type_ {
+            EntityBaseType::LineSegment => sk.get_entity(self.point[1]).point_get_num(sk),
+            EntityBaseType::Cubic => sk
+                .get_entity(self.point[3 + self.extra_points])
+                .point_get_num(sk),
+            EntityBaseType::ArcOfCircle => sk.get_entity(self.point[2]).point_get_num(sk),
+            _ => panic!("Unexpected entity type for endpoint_finish(...)"),
+        }
+    }
+
+    pub fn face_get_normal_num(&self, sk: &Sketch) -> Vector {
+        self.face_get_normal_exprs().eval(&sk.param)
+    }
+
+    pub fn face_get_point_num(&self, sk: &Sketch) -> Vector {
+        self.face_get_point_exprs(sk).eval(&sk.param)
+    }
+
+    // A cubic as Bezier segments, each given by its four control points.
+    // Like SolveSpace, the curve passes through every point but the control
+    // points at the ends, with the tangents in between chosen so the second
+    // derivative is continuous too; a periodic cubic passes through all of
+    // its points and closes up.
+    pub fn cubic_bezier_segments(&self, sk: &Sketch) -> Vec<[Vector; 4]> {
+        let periodic = match self.type_ {
+            EntityBaseType::Cubic => false,
+            EntityBaseType::CubicPeriodic => true,
+            _ => panic!("Unexpected entity type for cubic_bezier_segments(...)"),
+        };
+        let ep = self.extra_points;
+        let num = |i: usize| sk.get_entity(self.point[i]).point_get_num(sk);
+
+        // The points on the curve, and the ends' control points.
+        let (pt, ctrl_s, ctrl_f) = if periodic {
+            ((0..ep + 3).map(num).collect::<Vec<Vector>>(), None, None)
+        } else {
+            let mut pt = vec![num(0)];
+            pt.extend((2..ep + 2).map(num));
+            pt.push(num(ep + 3));
+            (pt, Some(num(1)), Some(num(ep + 2)))
+        };
+
+        // The unknowns are the offsets x[k] from each on-curve point to the
+        // control point after it, which is mirrored to the one before it.
+        // Equal second derivatives either side of point k give
+        //     x[k-1] + 4 x[k] + x[k+1] = pt[k+1] - pt[k-1],
+        // where an end's offset comes from its control point instead.
+        let m = pt.len();
+        let (first, n) = if periodic { (0, m) } else { (1, m - 2) };
+        let mut x = vec![Vector::new(0.0, 0.0, 0.0); m];
+        if n > 0 {
+            let mut a = vec![vec![0.0; n]; n];
+            let mut b = vec![Vector::new(0.0, 0.0, 0.0); n];
+            for i in 0..n {
+                let k = first + i;
+                let prev = (k + m - 1) % m;
+                let next = (k + 1) % m;
+                a[i][i] = 4.0;
+                b[i] = pt[next] - pt[prev];
+                if periodic || i > 0 {
+                    a[i][(i + n - 1) % n] += 1.0;
+                } else {
+                    b[i] = b[i] - (ctrl_s.unwrap() - pt[0]);
+                }
+                if periodic || i < n - 1 {
+                    a[i][(i + 1) % n] += 1.0;
+                } else {
+                    b[i] = b[i] - (pt[m - 1] - ctrl_f.unwrap());
+                }
+            }
+            let mut xs = [vec![0.0; n], vec![0.0; n], vec![0.0; n]];
+            let coords = [
+                b.iter().map(|v| v.x).collect::<Vec<f64>>(),
+                b.iter().map(|v| v.y).collect(),
+                b.iter().map(|v| v.z).collect(),
+            ];
+            for (xc, bc) in xs.iter_mut().zip(coords.iter()) {
+                System::solve_linear_system(xc, a.clone(), bc.clone());
+            }
+            for i in 0..n {
+                x[first + i] = Vector::new(xs[0][i], xs[1][i], xs[2][i]);
+            }
+        }
+
+        let segments = if periodic { m } else { m - 1 };
+        (0..segments)
+            .map(|i| {
+                let j = (i + 1) % m;
+                let p1 = match ctrl_s {
+                    Some(c) if i == 0 => c,
+                    _ => pt[i] + x[i],
+                };
+                let p2 = match ctrl_f {
+                    Some(c) if j == m - 1 => c,
+                    _ => pt[j] - x[j],
+                };
+                [pt[i], p1, p2, pt[j]]
+            })
+            .collect()
+    }
+
+    // The point a fraction t of the way along a line, circle, arc or cubic,
+    // for t from 0 to 1; a cubic's segments each get an equal share of t.
+    pub fn curve_point_at(&self, sk: &Sketch, t: f64) -> Vector {
+        match self.type_ {
+            EntityBaseType::LineSegment => {
+                let a = sk.get_entity(self.point[0]).point_get_num(sk);
+                let b = sk.get_entity(self.point[1]).point_get_num(sk);
+                a + (b - a) * t
+            }
+            EntityBaseType::Circle | EntityBaseType::ArcOfCircle => {
+                let q = self.normal_entity(sk).normal_get_num(sk);
+                let c = sk.get_entity(self.point[0]).point_get_num(sk);
+                let r = self.circle_get_radius_num(sk);
+                let theta = if self.type_ == EntityBaseType::Circle {
+                    2.0 * PI * t
+                } else {
+                    let (theta_a, _, dtheta) = self.arc_get_angles(sk);
+                    theta_a + dtheta * t
+                };
+                c + q.rotation_u() * (r * theta.cos()) + q.rotation_v() * (r * theta.sin())
+            }
+            EntityBaseType::Cubic | EntityBaseType::CubicPeriodic => {
+                let segs = self.cubic_bezier_segments(sk);
+                let st = t.clamp(0.0, 1.0) * segs.len() as f64;
+                let i = (st.floor() as usize).min(segs.len() - 1);
+                let u = st - i as f64;
+                let [p0, p1, p2, p3] = segs[i];
+                let w = 1.0 - u;
+                p0 * (w * w * w)
+                    + p1 * (3.0 * w * w * u)
+                    + p2 * (3.0 * w * u * u)
+                    + p3 * (u * u * u)
+            }
+            _ => panic!("Unexpected entity type for curve_point_at(...)"),
+        }
+    }
+
+    // The curve as Bezier pieces, for making loops out of; anything that
+    // isn't a curve adds nothing. Circles and arcs are rational quadratics
+    // of at most a quarter turn each, which are exact.
+    pub fn generate_bezier_curves(&self, sk: &Sketch, l: &mut Vec<SBezier>) {
+        let h = self.h.v;
+        match self.type_ {
+            EntityBaseType::LineSegment => {
+                let a = sk.get_entity(self.point[0]).point_get_num(sk);
+                let b = sk.get_entity(self.point[1]).point_get_num(sk);
+                l.push(SBezier::new(1, &[a, b], h));
+            }
+            EntityBaseType::Cubic | EntityBaseType::CubicPeriodic => {
+                for seg in self.cubic_bezier_segments(sk) {
+                    l.push(SBezier::new(3, &seg, h));
+                }
+            }
+            EntityBaseType::Circle | EntityBaseType::ArcOfCircle => {
+                let q = self.normal_entity(sk).normal_get_num(sk);
+                let (u, v) = (q.rotation_u(), q.rotation_v());
+                let c = sk.get_entity(self.point[0]).point_get_num(sk);
+                let r = self.circle_get_radius_num(sk);
+                let (theta_a, dtheta) = if self.type_ == EntityBaseType::Circle {
+                    (0.0, 2.0 * PI)
+                } else {
+                    let (theta_a, _, dtheta) = self.arc_get_angles(sk);
+                    (theta_a, dtheta)
+                };
+                let n = ((dtheta / (PI / 2.0)).ceil() as usize).max(1);
+                let step = dtheta / (n as f64);
+                let at = |theta: f64, r: f64| c + u * (r * theta.cos()) + v * (r * theta.sin());
+                let first = l.len();
+                for i in 0..n {
+                    let t0 = theta_a + step * (i as f64);
+                    let half = step / 2.0;
+                    let ctrl = [at(t0, r), at(t0 + half, r / half.cos()), at(t0 + step, r)];
+                    let mut b = SBezier::new(2, &ctrl, h);
+                    b.weight[1] = half.cos();
+                    l.push(b);
+                }
+                // An arc ends exactly on its endpoints, whatever its radii,
+                // so that it joins up with whatever else is there.
+                if self.type_ == EntityBaseType::ArcOfCircle {
+                    l[first].ctrl[0] = sk.get_entity(self.point[1]).point_get_num(sk);
+                    l[first + n - 1].ctrl[2] = sk.get_entity(self.point[2]).point_get_num(sk);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    pub fn generate_equations(&self, sk: &Sketch, l: &mut Vec<Equation>) {
+        match self.type_ {
+            EntityBaseType::NormalIn3D => {
+                let q = self.normal_get_exprs(sk);
+                l.push(Equation {
+                    tag: 0,
+                    h: self.h.equation(),
+                    e: q.magnitude() - Expr::from_constant(1.0),
+                });
+            }
+            EntityBaseType::ArcOfCircle => {
+                // Copied arcs already have their points fixed relative to
+                // each other, so the radii can't disagree.
+                if sk.get_entity(self.point[0]).type_ != EntityBaseType::PointIn2D {
+                    return;
+                }
+                // If the endpoints are constrained coincident to make a
+                // complete circle, equal radii would be redundant.
+                let closed = sk.constraint.values().any(|c| {
+                    c.group == self.group
+                        && c.type_ == ConstraintType::PointsCoincident
+                        && ((c.pt_a == self.point[1] && c.pt_b == self.point[2])
+                            || (c.pt_a == self.point[2] && c.pt_b == self.point[1]))
+                });
+                if closed {
+                    return;
+                }
+                let ra = Constraint::distance(sk, self.workplane, self.point[0], self.point[1]);
+                let rb = Constraint::distance(sk, self.workplane, self.point[0], self.point[2]);
+                l.push(Equation {
+                    tag: 0,
+                    h: self.h.equation(),
+                    e: ra - rb,
+                });
+            }
+            _ => {}
+        }
+    }
+}
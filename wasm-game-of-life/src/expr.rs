@@ -0,0 +1,778 @@
+use crate::dsc::{Quaternion, Vector};
+use crate::id_list::IdList;
+use crate::sketch::{HParam, Param};
+
+use std::f64::consts::PI;
+use std::fmt;
+use std::ops::Add;
+use std::ops::Div;
+use std::ops::Mul;
+use std::ops::Neg;
+use std::ops::Sub;
+
+#[derive(Clone)]
+pub enum Expr {
+    Param(HParam),
+    Constant(f64),
+
+    Plus(Box<Expr>, Box<Expr>),
+    Minus(Box<Expr>, Box<Expr>),
+    Times(Box<Expr>, Box<Expr>),
+    Div(Box<Expr>, Box<Expr>),
+
+    Negate(Box<Expr>),
+    Sqrt(Box<Expr>),
+    Square(Box<Expr>),
+    Sin(Box<Expr>),
+    Cos(Box<Expr>),
+    ASin(Box<Expr>),
+    ACos(Box<Expr>),
+}
+
+#[derive(Clone)]
+pub struct ExprVector {
+    pub x: Expr,
+    pub y: Expr,
+    pub z: Expr,
+}
+
+#[derive(Clone)]
+pub struct ExprQuaternion {
+    pub w: Expr,
+    pub vx: Expr,
+    pub vy: Expr,
+    pub vz: Expr,
+}
+
+impl Expr {
+    pub fn from_param(p: HParam) -> Self {
+        Expr::Param(p)
+    }
+
+    pub fn from_constant(v: f64) -> Self {
+        Expr::Constant(v)
+    }
+
+    pub fn negate(self) -> Self {
+        Expr::Negate(Box::new(self))
+    }
+
+    pub fn sqrt(self) -> Self {
+        Expr::Sqrt(Box::new(self))
+    }
+
+    pub fn square(self) -> Self {
+        Expr::Square(Box::new(self))
+    }
+
+    pub fn sin(self) -> Self {
+        Expr::Sin(Box::new(self))
+    }
+
+    pub fn cos(self) -> Self {
+        Expr::Cos(Box::new(self))
+    }
+
+    pub fn asin(self) -> Self {
+        Expr::ASin(Box::new(self))
+    }
+
+    pub fn acos(self) -> Self {
+        Expr::ACos(Box::new(self))
+    }
+
+    // Every param it refers to has to be in params; one that isn't means the
+    // equations were written for some other system, so it panics rather
+    // than solve for the wrong thing.
+    pub fn eval(&self, params: &IdList<HParam, Param>) -> f64 {
+        match self {
+            Expr::Param(p) => match params.get(p) {
+                Some(param) => param.val,
+                None => panic!("Expr::eval(...) of unknown param {}", p.v),
+            },
+            Expr::Constant(v) => *v,
+
+            Expr::Plus(a, b) => a.eval(params) + b.eval(params),
+            Expr::Minus(a, b) => a.eval(params) - b.eval(params),
+            Expr::Times(a, b) => a.eval(params) * b.eval(params),
+            Expr::Div(a, b) => a.eval(params) / b.eval(params),
+
+            Expr::Negate(a) => -a.eval(params),
+            Expr::Sqrt(a) => a.eval(params).sqrt(),
+            Expr::Square(a) => {
+                let v = a.eval(params);
+                v * v
+            }
+            Expr::Sin(a) => a.eval(params).sin(),
+            Expr::Cos(a) => a.eval(params).cos(),
+            Expr::ASin(a) => a.eval(params).asin(),
+            Expr::ACos(a) => a.eval(params).acos(),
+        }
+    }
+
+    pub fn partial_wrt(&self, p: HParam) -> Expr {
+        match self {
+            Expr::Param(h) => Expr::Constant(if *h == p { 1.0 } else { 0.0 }),
+            Expr::Constant(_) => Expr::Constant(0.0),
+
+            Expr::Plus(a, b) => a.partial_wrt(p) + b.partial_wrt(p),
+            Expr::Minus(a, b) => a.partial_wrt(p) - b.partial_wrt(p),
+            Expr::Times(a, b) => {
+                let da = a.partial_wrt(p);
+                let db = b.partial_wrt(p);
+                (**a).clone() * db + (**b).clone() * da
+            }
+            Expr::Div(a, b) => {
+                let da = a.partial_wrt(p);
+                let db = b.partial_wrt(p);
+                // (a/b)' = (a'*b - a*b')/(b^2)
+                (da * (**b).clone() - (**a).clone() * db) / (**b).clone().square()
+            }
+
+            Expr::Negate(a) => a.partial_wrt(p).negate(),
+            Expr::Sqrt(a) => Expr::Constant(0.5) / (**a).clone().sqrt() * a.partial_wrt(p),
+            Expr::Square(a) => Expr::Constant(2.0) * (**a).clone() * a.partial_wrt(p),
+            Expr::Sin(a) => (**a).clone().cos() * a.partial_wrt(p),
+            Expr::Cos(a) => (**a).clone().sin().negate() * a.partial_wrt(p),
+            Expr::ASin(a) => {
+                Expr::Constant(1.0) / (Expr::Constant(1.0) - (**a).clone().square()).sqrt()
+                    * a.partial_wrt(p)
+            }
+            Expr::ACos(a) => {
+                Expr::Constant(-1.0) / (Expr::Constant(1.0) - (**a).clone().square()).sqrt()
+                    * a.partial_wrt(p)
+            }
+        }
+    }
+
+    pub fn depends_on(&self, p: HParam) -> bool {
+        match self {
+            Expr::Param(h) => *h == p,
+            Expr::Constant(_) => false,
+
+            Expr::Plus(a, b) | Expr::Minus(a, b) | Expr::Times(a, b) | Expr::Div(a, b) => {
+                a.depends_on(p) || b.depends_on(p)
+            }
+
+            Expr::Negate(a)
+            | Expr::Sqrt(a)
+            | Expr::Square(a)
+            | Expr::Sin(a)
+            | Expr::Cos(a)
+            | Expr::ASin(a)
+            | Expr::ACos(a) => a.depends_on(p),
+        }
+    }
+
+    pub fn params(&self, out: &mut Vec<HParam>) {
+        match self {
+            Expr::Param(h) => {
+                if !out.contains(h) {
+                    out.push(*h);
+                }
+            }
+            Expr::Constant(_) => {}
+
+            Expr::Plus(a, b) | Expr::Minus(a, b) | Expr::Times(a, b) | Expr::Div(a, b) => {
+                a.params(out);
+                b.params(out);
+            }
+
+            Expr::Negate(a)
+            | Expr::Sqrt(a)
+            | Expr::Square(a)
+            | Expr::Sin(a)
+            | Expr::Cos(a)
+            | Expr::ASin(a)
+            | Expr::ACos(a) => a.params(out),
+        }
+    }
+
+    pub fn substitute(&mut self, old: HParam, new: HParam) {
+        match self {
+            Expr::Param(h) => {
+                if *h == old {
+                    *h = new;
+                }
+            }
+            Expr::Constant(_) => {}
+
+            Expr::Plus(a, b) | Expr::Minus(a, b) | Expr::Times(a, b) | Expr::Div(a, b) => {
+                a.substitute(old, new);
+                b.substitute(old, new);
+            }
+
+            Expr::Negate(a)
+            | Expr::Sqrt(a)
+            | Expr::Square(a)
+            | Expr::Sin(a)
+            | Expr::Cos(a)
+            | Expr::ASin(a)
+            | Expr::ACos(a) => a.substitute(old, new),
+        }
+    }
+
+    pub fn is_constant(&self, v: f64) -> bool {
+        match self {
+            Expr::Constant(c) => *c == v,
+            _ => false,
+        }
+    }
+
+    pub fn fold_constants(&self) -> Expr {
+        match self {
+            Expr::Param(h) => Expr::Param(*h),
+            Expr::Constant(v) => Expr::Constant(*v),
+
+            Expr::Plus(a, b) => {
+                let a = a.fold_constants();
+                let b = b.fold_constants();
+                match (&a, &b) {
+                    (Expr::Constant(va), Expr::Constant(vb)) => Expr::Constant(va + vb),
+                    _ if a.is_constant(0.0) => b,
+                    _ if b.is_constant(0.0) => a,
+                    _ => a + b,
+                }
+            }
+            Expr::Minus(a, b) => {
+                let a = a.fold_constants();
+                let b = b.fold_constants();
+                match (&a, &b) {
+                    (Expr::Constant(va), Expr::Constant(vb)) => Expr::Constant(va - vb),
+                    _ if a.is_constant(0.0) => b.negate(),
+                    _ if b.is_constant(0.0) => a,
+                    _ => a - b,
+                }
+            }
+            Expr::Times(a, b) => {
+                let a = a.fold_constants();
+                let b = b.fold_constants();
+                match (&a, &b) {
+                    (Expr::Constant(va), Expr::Constant(vb)) => Expr::Constant(va * vb),
+                    _ if a.is_constant(0.0) || b.is_constant(0.0) => Expr::Constant(0.0),
+                    _ if a.is_constant(1.0) => b,
+                    _ if b.is_constant(1.0) => a,
+                    _ => a * b,
+                }
+            }
+            Expr::Div(a, b) => {
+                let a = a.fold_constants();
+                let b = b.fold_constants();
+                match (&a, &b) {
+                    (Expr::Constant(va), Expr::Constant(vb)) => Expr::Constant(va / vb),
+                    _ if a.is_constant(0.0) => Expr::Constant(0.0),
+                    _ if b.is_constant(1.0) => a,
+                    _ => a / b,
+                }
+            }
+
+            Expr::Negate(a) => match a.fold_constants() {
+                Expr::Constant(v) => Expr::Constant(-v),
+                Expr::Negate(b) => *b,
+                a => a.negate(),
+            },
+            Expr::Sqrt(a) => match a.fold_constants() {
+                Expr::Constant(v) => Expr::Constant(v.sqrt()),
+                a => a.sqrt(),
+            },
+            Expr::Square(a) => match a.fold_constants() {
+                Expr::Constant(v) => Expr::Constant(v * v),
+                a => a.square(),
+            },
+            Expr::Sin(a) => match a.fold_constants() {
+                Expr::Constant(v) => Expr::Constant(v.sin()),
+                a => a.sin(),
+            },
+            Expr::Cos(a) => match a.fold_constants() {
+                Expr::Constant(v) => Expr::Constant(v.cos()),
+                a => a.cos(),
+            },
+            Expr::ASin(a) => match a.fold_constants() {
+                Expr::Constant(v) => Expr::Constant(v.asin()),
+                a => a.asin(),
+            },
+            Expr::ACos(a) => match a.fold_constants() {
+                Expr::Constant(v) => Expr::Constant(v.acos()),
+                a => a.acos(),
+            },
+        }
+    }
+}
+
+impl Add for Expr {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Expr::Plus(Box::new(self), Box::new(rhs))
+    }
+}
+
+impl Sub for Expr {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        Expr::Minus(Box::new(self), Box::new(rhs))
+    }
+}
+
+impl Mul for Expr {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        Expr::Times(Box::new(self), Box::new(rhs))
+    }
+}
+
+impl Div for Expr {
+    type Output = Self;
+
+    fn div(self, rhs: Self) -> Self {
+        Expr::Div(Box::new(self), Box::new(rhs))
+    }
+}
+
+impl Neg for Expr {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        self.negate()
+    }
+}
+
+impl ExprVector {
+    pub fn new(x: Expr, y: Expr, z: Expr) -> Self {
+        ExprVector { x, y, z }
+    }
+
+    pub fn from_params(x: HParam, y: HParam, z: HParam) -> Self {
+        ExprVector {
+            x: Expr::from_param(x),
+            y: Expr::from_param(y),
+            z: Expr::from_param(z),
+        }
+    }
+
+    pub fn from_vector(v: Vector) -> Self {
+        ExprVector {
+            x: Expr::from_constant(v.x),
+            y: Expr::from_constant(v.y),
+            z: Expr::from_constant(v.z),
+        }
+    }
+
+    pub fn dot(&self, b: &ExprVector) -> Expr {
+        self.x.clone() * b.x.clone() + self.y.clone() * b.y.clone() + self.z.clone() * b.z.clone()
+    }
+
+    pub fn cross(&self, b: &ExprVector) -> ExprVector {
+        ExprVector {
+            x: self.y.clone() * b.z.clone() - self.z.clone() * b.y.clone(),
+            y: self.z.clone() * b.x.clone() - self.x.clone() * b.z.clone(),
+            z: self.x.clone() * b.y.clone() - self.y.clone() * b.x.clone(),
+        }
+    }
+
+    pub fn scaled_by(&self, s: Expr) -> ExprVector {
+        ExprVector {
+            x: self.x.clone() * s.clone(),
+            y: self.y.clone() * s.clone(),
+            z: self.z.clone() * s,
+        }
+    }
+
+    pub fn magnitude(&self) -> Expr {
+        (self.x.clone().square() + self.y.clone().square() + self.z.clone().square()).sqrt()
+    }
+
+    pub fn with_magnitude(&self, s: Expr) -> ExprVector {
+        let m = self.magnitude();
+        self.scaled_by(s / m)
+    }
+
+    pub fn eval(&self, params: &IdList<HParam, Param>) -> Vector {
+        Vector::new(
+            self.x.eval(params),
+            self.y.eval(params),
+            self.z.eval(params),
+        )
+    }
+}
+
+impl Add for ExprVector {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        ExprVector {
+            x: self.x + rhs.x,
+            y: self.y + rhs.y,
+            z: self.z + rhs.z,
+        }
+    }
+}
+
+impl Sub for ExprVector {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        ExprVector {
+            x: self.x - rhs.x,
+            y: self.y - rhs.y,
+            z: self.z - rhs.z,
+        }
+    }
+}
+
+impl ExprQuaternion {
+    pub fn from_params(w: HParam, vx: HParam, vy: HParam, vz: HParam) -> Self {
+        ExprQuaternion {
+            w: Expr::from_param(w),
+            vx: Expr::from_param(vx),
+            vy: Expr::from_param(vy),
+            vz: Expr::from_param(vz),
+        }
+    }
+
+    pub fn from_quaternion(q: Quaternion) -> Self {
+        ExprQuaternion {
+            w: Expr::from_constant(q.w),
+            vx: Expr::from_constant(q.vx),
+            vy: Expr::from_constant(q.vy),
+            vz: Expr::from_constant(q.vz),
+        }
+    }
+
+    pub fn rotation_u(&self) -> ExprVector {
+        let two = || Expr::from_constant(2.0);
+        let (w, vx, vy, vz) = (&self.w, &self.vx, &self.vy, &self.vz);
+        ExprVector {
+            x: w.clone().square() + vx.clone().square() - vy.clone().square() - vz.clone().square(),
+            y: two() * w.clone() * vz.clone() + two() * vx.clone() * vy.clone(),
+            z: two() * vx.clone() * vz.clone() - two() * w.clone() * vy.clone(),
+        }
+    }
+
+    pub fn rotation_v(&self) -> ExprVector {
+        let two = || Expr::from_constant(2.0);
+        let (w, vx, vy, vz) = (&self.w, &self.vx, &self.vy, &self.vz);
+        ExprVector {
+            x: two() * vx.clone() * vy.clone() - two() * w.clone() * vz.clone(),
+            y: w.clone().square() - vx.clone().square() + vy.clone().square() - vz.clone().square(),
+            z: two() * w.clone() * vx.clone() + two() * vy.clone() * vz.clone(),
+        }
+    }
+
+    pub fn rotation_n(&self) -> ExprVector {
+        let two = || Expr::from_constant(2.0);
+        let (w, vx, vy, vz) = (&self.w, &self.vx, &self.vy, &self.vz);
+        ExprVector {
+            x: two() * w.clone() * vy.clone() + two() * vx.clone() * vz.clone(),
+            y: two() * vy.clone() * vz.clone() - two() * w.clone() * vx.clone(),
+            z: w.clone().square() - vx.clone().square() - vy.clone().square() + vz.clone().square(),
+        }
+    }
+
+    pub fn rotate(&self, p: &ExprVector) -> ExprVector {
+        self.rotation_u().scaled_by(p.x.clone())
+            + self.rotation_v().scaled_by(p.y.clone())
+            + self.rotation_n().scaled_by(p.z.clone())
+    }
+
+    pub fn times(&self, b: &ExprQuaternion) -> ExprQuaternion {
+        let sa = self.w.clone();
+        let sb = b.w.clone();
+        let va = ExprVector::new(self.vx.clone(), self.vy.clone(), self.vz.clone());
+        let vb = ExprVector::new(b.vx.clone(), b.vy.clone(), b.vz.clone());
+        let vr = vb.scaled_by(sa.clone()) + va.scaled_by(sb.clone()) + va.cross(&vb);
+        ExprQuaternion {
+            w: sa * sb - va.dot(&vb),
+            vx: vr.x,
+            vy: vr.y,
+            vz: vr.z,
+        }
+    }
+
+    pub fn magnitude(&self) -> Expr {
+        (self.w.clone().square()
+            + self.vx.clone().square()
+            + self.vy.clone().square()
+            + self.vz.clone().square())
+        .sqrt()
+    }
+
+    pub fn eval(&self, params: &IdList<HParam, Param>) -> Quaternion {
+        Quaternion::new(
+            self.w.eval(params),
+            self.vx.eval(params),
+            self.vy.eval(params),
+            self.vz.eval(params),
+        )
+    }
+}
+
+// What's wrong with the text of an expression, or with the variables it
+// refers to. Positions are byte offsets into the text.
+#[derive(Clone, PartialEq, Debug)]
+pub enum ExprError {
+    Syntax(usize, String),
+    UnknownVariable(String),
+    BadVariable(String),
+    Cycle(Vec<String>),
+    NotFinite,
+}
+
+impl fmt::Display for ExprError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExprError::Syntax(pos, msg) => write!(f, "{} at position {}", msg, pos),
+            ExprError::UnknownVariable(name) => write!(f, "unknown variable '{}'", name),
+            ExprError::BadVariable(name) => write!(f, "variable '{}' has an error", name),
+            ExprError::Cycle(names) => {
+                write!(f, "variables depend on each other: {}", names.join(" -> "))
+            }
+            ExprError::NotFinite => write!(f, "value is not a finite number"),
+        }
+    }
+}
+
+// Lengths are in mm and angles in degrees, same as the dimensions.
+const UNITS: [(&str, f64); 7] = [
+    ("mm", 1.0),
+    ("cm", 10.0),
+    ("m", 1000.0),
+    ("in", 25.4),
+    ("ft", 304.8),
+    ("deg", 1.0),
+    ("rad", 180.0 / PI),
+];
+
+const FUNCTIONS: [&str; 5] = ["sqrt", "sin", "cos", "asin", "acos"];
+
+#[derive(Clone, PartialEq, Debug)]
+enum Token {
+    Number(f64),
+    Name(String),
+    Op(char),
+    End,
+}
+
+struct ExprParser<'a> {
+    tokens: Vec<(usize, Token)>,
+    next: usize,
+    lookup: &'a mut dyn FnMut(&str) -> Result<Expr, ExprError>,
+}
+
+impl Expr {
+    // A name that can't be used for a variable, because it already means
+    // a unit, a function or pi.
+    pub fn is_reserved_name(name: &str) -> bool {
+        name == "pi" || UNITS.iter().any(|(u, _)| *u == name) || FUNCTIONS.contains(&name)
+    }
+
+    pub fn is_valid_name(name: &str) -> bool {
+        let mut cs = name.chars();
+        match cs.next() {
+            Some(c) if c.is_ascii_alphabetic() || c == '_' => {}
+            _ => return false,
+        }
+        cs.all(|c| c.is_ascii_alphanumeric() || c == '_') && !Expr::is_reserved_name(name)
+    }
+
+    // Parses something like "width/2 + 3 mm". Variables are looked up by
+    // name as they're found; a unit may follow any operand, and sin(..) and
+    // friends work in degrees, as SolveSpace does.
+    pub fn parse(
+        s: &str,
+        lookup: &mut dyn FnMut(&str) -> Result<Expr, ExprError>,
+    ) -> Result<Expr, ExprError> {
+        let mut p = ExprParser {
+            tokens: ExprParser::lex(s)?,
+            next: 0,
+            lookup,
+        };
+        let e = p.expr()?;
+        match p.peek() {
+            (_, Token::End) => Ok(e),
+            (pos, _) => Err(ExprError::Syntax(pos, "expected an operator".to_string())),
+        }
+    }
+
+    // Parses and evaluates, for text that can only refer to values.
+    pub fn parse_value(
+        s: &str,
+        lookup: &mut dyn FnMut(&str) -> Result<Expr, ExprError>,
+    ) -> Result<f64, ExprError> {
+        let v = Expr::parse(s, lookup)?.eval(&IdList::new());
+        if v.is_finite() {
+            Ok(v)
+        } else {
+            Err(ExprError::NotFinite)
+        }
+    }
+}
+
+impl<'a> ExprParser<'a> {
+    fn lex(s: &str) -> Result<Vec<(usize, Token)>, ExprError> {
+        let b = s.as_bytes();
+        let mut r = Vec::new();
+        let mut i = 0;
+        while i < b.len() {
+            let c = b[i] as char;
+            let start = i;
+            if c.is_ascii_whitespace() {
+                i += 1;
+            } else if c.is_ascii_digit() || c == '.' {
+                while i < b.len() && (b[i].is_ascii_digit() || b[i] == b'.') {
+                    i += 1;
+                }
+                // An exponent, but only if there's a number to it; otherwise
+                // the 'e' starts whatever name comes next.
+                if i < b.len() && (b[i] == b'e' || b[i] == b'E') {
+                    let mut j = i + 1;
+                    if j < b.len() && (b[j] == b'+' || b[j] == b'-') {
+                        j += 1;
+                    }
+                    if j < b.len() && b[j].is_ascii_digit() {
+                        i = j;
+                        while i < b.len() && b[i].is_ascii_digit() {
+                            i += 1;
+                        }
+                    }
+                }
+                match s[start..i].parse::<f64>() {
+                    Ok(v) => r.push((start, Token::Number(v))),
+                    Err(_) => return Err(ExprError::Syntax(start, "malformed number".to_string())),
+                }
+            } else if c.is_ascii_alphabetic() || c == '_' {
+                while i < b.len() && (b[i].is_ascii_alphanumeric() || b[i] == b'_') {
+                    i += 1;
+                }
+                r.push((start, Token::Name(s[start..i].to_string())));
+            } else if "+-*/()".contains(c) {
+                i += 1;
+                r.push((start, Token::Op(c)));
+            } else {
+                let c = s[start..].chars().next().unwrap();
+                return Err(ExprError::Syntax(start, format!("unexpected '{}'", c)));
+            }
+        }
+        r.push((s.len(), Token::End));
+        Ok(r)
+    }
+
+    fn peek(&self) -> (usize, Token) {
+        self.tokens[self.next].clone()
+    }
+
+    fn take(&mut self) -> (usize, Token) {
+        let t = self.peek();
+        if t.1 != Token::End {
+            self.next += 1;
+        }
+        t
+    }
+
+    fn expect(&mut self, op: char) -> Result<(), ExprError> {
+        match self.take() {
+            (_, Token::Op(c)) if c == op => Ok(()),
+            (pos, _) => Err(ExprError::Syntax(pos, format!("expected '{}'", op))),
+        }
+    }
+
+    fn expr(&mut self) -> Result<Expr, ExprError> {
+        let mut e = self.term()?;
+        loop {
+            match self.peek().1 {
+                Token::Op('+') => {
+                    self.take();
+                    e = e + self.term()?;
+                }
+                Token::Op('-') => {
+                    self.take();
+                    e = e - self.term()?;
+                }
+                _ => return Ok(e),
+            }
+        }
+    }
+
+    fn term(&mut self) -> Result<Expr, ExprError> {
+        let mut e = self.unary()?;
+        loop {
+            match self.peek().1 {
+                Token::Op('*') => {
+                    self.take();
+                    e = e * self.unary()?;
+                }
+                Token::Op('/') => {
+                    self.take();
+                    e = e / self.unary()?;
+                }
+                _ => return Ok(e),
+            }
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, ExprError> {
+        match self.peek().1 {
+            Token::Op('-') => {
+                self.take();
+                Ok(self.unary()?.negate())
+            }
+            Token::Op('+') => {
+                self.take();
+                self.unary()
+            }
+            _ => self.with_unit(),
+        }
+    }
+
+    fn with_unit(&mut self) -> Result<Expr, ExprError> {
+        let e = self.primary()?;
+        if let (_, Token::Name(name)) = self.peek() {
+            if let Some((_, scale)) = UNITS.iter().find(|(u, _)| *u == name) {
+                self.take();
+                return Ok(e * Expr::from_constant(*scale));
+            }
+        }
+        Ok(e)
+    }
+
+    fn primary(&mut self) -> Result<Expr, ExprError> {
+        match self.take() {
+            (_, Token::Number(v)) => Ok(Expr::from_constant(v)),
+            (_, Token::Op('(')) => {
+                let e = self.expr()?;
+                self.expect(')')?;
+                Ok(e)
+            }
+            (pos, Token::Name(name)) => {
+                if FUNCTIONS.contains(&name.as_str()) {
+                    self.expect('(')?;
+                    let a = self.expr()?;
+                    self.expect(')')?;
+                    let to_rad = Expr::from_constant(PI / 180.0);
+                    let to_deg = Expr::from_constant(180.0 / PI);
+                    return Ok(match name.as_str() {
+                        "sqrt" => a.sqrt(),
+                        "sin" => (a * to_rad).sin(),
+                        "cos" => (a * to_rad).cos(),
+                        "asin" => a.asin() * to_deg,
+                        _ => a.acos() * to_deg,
+                    });
+                }
+                if name == "pi" {
+                    return Ok(Expr::from_constant(PI));
+                }
+                if Expr::is_reserved_name(&name) {
+                    return Err(ExprError::Syntax(
+                        pos,
+                        format!("'{}' needs a value before it", name),
+                    ));
+                }
+                (self.lookup)(&name)
+            }
+            (pos, Token::End) => Err(ExprError::Syntax(pos, "expected a value".to_string())),
+            (pos, Token::Op(c)) => Err(ExprError::Syntax(pos, format!("unexpected '{}'", c))),
+        }
+    }
+}
@@ -0,0 +1,392 @@
+// A C ABI over the headless solver, laid out like SolveSpace's slvs.h so
+// that callers of libslvs can link against this instead. The header in
+// include/slvs.h is generated from this file by cbindgen.
+
+#![allow(non_camel_case_types, non_snake_case, clippy::too_many_arguments)]
+
+use crate::dsc::{Quaternion, Vector};
+use crate::sketch::{ConstraintType, EntityBaseType};
+use crate::slvs::{SlvsConstraint, SlvsEntity, SlvsParam, SlvsResult, SlvsSystem};
+
+use std::os::raw::c_int;
+use std::panic::{self, AssertUnwindSafe};
+use std::slice;
+
+pub type Slvs_hParam = u32;
+pub type Slvs_hEntity = u32;
+pub type Slvs_hConstraint = u32;
+pub type Slvs_hGroup = u32;
+
+pub const SLVS_FREE_IN_3D: Slvs_hEntity = 0;
+
+pub const SLVS_E_POINT_IN_3D: c_int = 50000;
+pub const SLVS_E_POINT_IN_2D: c_int = 50001;
+pub const SLVS_E_NORMAL_IN_3D: c_int = 60000;
+pub const SLVS_E_NORMAL_IN_2D: c_int = 60001;
+pub const SLVS_E_DISTANCE: c_int = 70000;
+pub const SLVS_E_WORKPLANE: c_int = 80000;
+pub const SLVS_E_LINE_SEGMENT: c_int = 80001;
+pub const SLVS_E_CUBIC: c_int = 80002;
+pub const SLVS_E_CIRCLE: c_int = 80003;
+pub const SLVS_E_ARC_OF_CIRCLE: c_int = 80004;
+
+pub const SLVS_C_POINTS_COINCIDENT: c_int = 100000;
+pub const SLVS_C_PT_PT_DISTANCE: c_int = 100001;
+pub const SLVS_C_PT_PLANE_DISTANCE: c_int = 100002;
+pub const SLVS_C_PT_LINE_DISTANCE: c_int = 100003;
+pub const SLVS_C_PT_FACE_DISTANCE: c_int = 100004;
+pub const SLVS_C_PT_IN_PLANE: c_int = 100005;
+pub const SLVS_C_PT_ON_LINE: c_int = 100006;
+pub const SLVS_C_PT_ON_FACE: c_int = 100007;
+pub const SLVS_C_EQUAL_LENGTH_LINES: c_int = 100008;
+pub const SLVS_C_LENGTH_RATIO: c_int = 100009;
+pub const SLVS_C_EQ_LEN_PT_LINE_D: c_int = 100010;
+pub const SLVS_C_EQ_PT_LN_DISTANCES: c_int = 100011;
+pub const SLVS_C_EQUAL_ANGLE: c_int = 100012;
+pub const SLVS_C_EQUAL_LINE_ARC_LEN: c_int = 100013;
+pub const SLVS_C_SYMMETRIC: c_int = 100014;
+pub const SLVS_C_SYMMETRIC_HORIZ: c_int = 100015;
+pub const SLVS_C_SYMMETRIC_VERT: c_int = 100016;
+pub const SLVS_C_SYMMETRIC_LINE: c_int = 100017;
+pub const SLVS_C_AT_MIDPOINT: c_int = 100018;
+pub const SLVS_C_HORIZONTAL: c_int = 100019;
+pub const SLVS_C_VERTICAL: c_int = 100020;
+pub const SLVS_C_DIAMETER: c_int = 100021;
+pub const SLVS_C_PT_ON_CIRCLE: c_int = 100022;
+pub const SLVS_C_SAME_ORIENTATION: c_int = 100023;
+pub const SLVS_C_ANGLE: c_int = 100024;
+pub const SLVS_C_PARALLEL: c_int = 100025;
+pub const SLVS_C_PERPENDICULAR: c_int = 100026;
+pub const SLVS_C_ARC_LINE_TANGENT: c_int = 100027;
+pub const SLVS_C_CUBIC_LINE_TANGENT: c_int = 100028;
+pub const SLVS_C_EQUAL_RADIUS: c_int = 100029;
+pub const SLVS_C_PROJ_PT_DISTANCE: c_int = 100030;
+pub const SLVS_C_WHERE_DRAGGED: c_int = 100031;
+pub const SLVS_C_CURVE_CURVE_TANGENT: c_int = 100032;
+pub const SLVS_C_LENGTH_DIFFERENCE: c_int = 100033;
+
+pub const SLVS_RESULT_OKAY: c_int = 0;
+pub const SLVS_RESULT_INCONSISTENT: c_int = 1;
+pub const SLVS_RESULT_DIDNT_CONVERGE: c_int = 2;
+pub const SLVS_RESULT_TOO_MANY_UNKNOWNS: c_int = 3;
+/// Not a result of solving: an entity or constraint had a type this solver
+/// doesn't know. Kept well clear of libslvs's own results.
+pub const SLVS_RESULT_UNKNOWN_TYPE: c_int = 100;
+/// Not a result of solving: the solver failed in a way it should have
+/// caught, and reported as one of the results above.
+pub const SLVS_RESULT_INTERNAL_ERROR: c_int = 101;
+/// Not a result of solving: something refers to a handle that isn't there,
+/// or to an entity of the wrong type.
+pub const SLVS_RESULT_BAD_INPUT: c_int = 102;
+
+#[repr(C)]
+pub struct Slvs_Param {
+    pub h: Slvs_hParam,
+    pub group: Slvs_hGroup,
+    pub val: f64,
+}
+
+#[repr(C)]
+pub struct Slvs_Entity {
+    pub h: Slvs_hEntity,
+    pub group: Slvs_hGroup,
+
+    pub r#type: c_int,
+
+    pub wrkpl: Slvs_hEntity,
+    pub point: [Slvs_hEntity; 4],
+    pub normal: Slvs_hEntity,
+    pub distance: Slvs_hEntity,
+
+    pub param: [Slvs_hParam; 4],
+}
+
+#[repr(C)]
+pub struct Slvs_Constraint {
+    pub h: Slvs_hConstraint,
+    pub group: Slvs_hGroup,
+
+    pub r#type: c_int,
+
+    pub wrkpl: Slvs_hEntity,
+
+    pub valA: f64,
+    pub ptA: Slvs_hEntity,
+    pub ptB: Slvs_hEntity,
+    pub entityA: Slvs_hEntity,
+    pub entityB: Slvs_hEntity,
+    pub entityC: Slvs_hEntity,
+    pub entityD: Slvs_hEntity,
+
+    pub other: c_int,
+    pub other2: c_int,
+}
+
+#[repr(C)]
+pub struct Slvs_System {
+    // Inputs, owned by the caller.
+    pub param: *mut Slvs_Param,
+    pub params: c_int,
+    pub entity: *mut Slvs_Entity,
+    pub entities: c_int,
+    pub constraint: *mut Slvs_Constraint,
+    pub constraints: c_int,
+
+    // If a param is listed here then the solver will try hardest to keep it
+    // where it is; zero for unused.
+    pub dragged: [Slvs_hParam; 4],
+
+    // Whether to find the constraints to delete when the system is
+    // inconsistent; that can be slow.
+    pub calculateFaileds: c_int,
+
+    // Outputs. The caller allocates failed with room for faileds entries;
+    // faileds is then set to the number found.
+    pub failed: *mut Slvs_hConstraint,
+    pub faileds: c_int,
+
+    pub dof: c_int,
+
+    pub result: c_int,
+}
+
+fn entity_type(t: c_int) -> Option<EntityBaseType> {
+    Some(match t {
+        SLVS_E_POINT_IN_3D => EntityBaseType::PointIn3D,
+        SLVS_E_POINT_IN_2D => EntityBaseType::PointIn2D,
+        SLVS_E_NORMAL_IN_3D => EntityBaseType::NormalIn3D,
+        SLVS_E_NORMAL_IN_2D => EntityBaseType::NormalIn2D,
+        SLVS_E_DISTANCE => EntityBaseType::Distance,
+        SLVS_E_WORKPLANE => EntityBaseType::Workplane,
+        SLVS_E_LINE_SEGMENT => EntityBaseType::LineSegment,
+        SLVS_E_CUBIC => EntityBaseType::Cubic,
+        SLVS_E_CIRCLE => EntityBaseType::Circle,
+        SLVS_E_ARC_OF_CIRCLE => EntityBaseType::ArcOfCircle,
+        _ => return None,
+    })
+}
+
+fn constraint_type(t: c_int) -> Option<ConstraintType> {
+    Some(match t {
+        SLVS_C_POINTS_COINCIDENT => ConstraintType::PointsCoincident,
+        SLVS_C_PT_PT_DISTANCE => ConstraintType::PtPtDistance,
+        SLVS_C_PT_PLANE_DISTANCE => ConstraintType::PtPlaneDistance,
+        SLVS_C_PT_LINE_DISTANCE => ConstraintType::PtLineDistance,
+        SLVS_C_PT_FACE_DISTANCE => ConstraintType::PtFaceDistance,
+        SLVS_C_PT_IN_PLANE => ConstraintType::PtInPlane,
+        SLVS_C_PT_ON_LINE => ConstraintType::PtOnLine,
+        SLVS_C_PT_ON_FACE => ConstraintType::PtOnFace,
+        SLVS_C_EQUAL_LENGTH_LINES => ConstraintType::EqualLengthLines,
+        SLVS_C_LENGTH_RATIO => ConstraintType::LengthRatio,
+        SLVS_C_EQ_LEN_PT_LINE_D => ConstraintType::EqLenPtLineD,
+        SLVS_C_EQ_PT_LN_DISTANCES => ConstraintType::EqPtLnDistances,
+        SLVS_C_EQUAL_ANGLE => ConstraintType::EqualAngle,
+        SLVS_C_SYMMETRIC => ConstraintType::Symmetric,
+        SLVS_C_SYMMETRIC_HORIZ => ConstraintType::SymmetricHoriz,
+        SLVS_C_SYMMETRIC_VERT => ConstraintType::SymmetricVert,
+        SLVS_C_SYMMETRIC_LINE => ConstraintType::SymmetricLine,
+        SLVS_C_AT_MIDPOINT => ConstraintType::AtMidpoint,
+        SLVS_C_HORIZONTAL => ConstraintType::Horizontal,
+        SLVS_C_VERTICAL => ConstraintType::Vertical,
+        SLVS_C_DIAMETER => ConstraintType::Diameter,
+        SLVS_C_PT_ON_CIRCLE => ConstraintType::PtOnCircle,
+        SLVS_C_SAME_ORIENTATION => ConstraintType::SameOrientation,
+        SLVS_C_ANGLE => ConstraintType::Angle,
+        SLVS_C_PARALLEL => ConstraintType::Parallel,
+        SLVS_C_PERPENDICULAR => ConstraintType::Perpendicular,
+        SLVS_C_ARC_LINE_TANGENT => ConstraintType::ArcLineTangent,
+        SLVS_C_CUBIC_LINE_TANGENT => ConstraintType::CubicLineTangent,
+        SLVS_C_EQUAL_RADIUS => ConstraintType::EqualRadius,
+        SLVS_C_PROJ_PT_DISTANCE => ConstraintType::ProjPtDistance,
+        SLVS_C_WHERE_DRAGGED => ConstraintType::WhereDragged,
+        SLVS_C_CURVE_CURVE_TANGENT => ConstraintType::CurveCurveTangent,
+        SLVS_C_LENGTH_DIFFERENCE => ConstraintType::LengthDifference,
+        // SLVS_C_EQUAL_LINE_ARC_LEN isn't implemented by this solver.
+        _ => return None,
+    })
+}
+
+unsafe fn as_slice<'a, T>(p: *const T, n: c_int) -> &'a [T] {
+    if p.is_null() || n <= 0 {
+        &[]
+    } else {
+        slice::from_raw_parts(p, n as usize)
+    }
+}
+
+/// Solve the given group, writing the new param values back into
+/// `ssys->param`. An entity or constraint of an unknown type makes this
+/// return without solving, with `ssys->result` set to
+/// `SLVS_RESULT_UNKNOWN_TYPE`, and one that refers to a handle that isn't
+/// there, or to an entity of the wrong type, sets it to
+/// `SLVS_RESULT_BAD_INPUT`; the params are left as they were. If the solver
+/// fails some other way, `ssys->result` is `SLVS_RESULT_INTERNAL_ERROR` and
+/// the params may be partly written. Any way, `ssys->dof` and
+/// `ssys->faileds` are 0.
+///
+/// # Safety
+///
+/// `ssys` must point to a valid `Slvs_System`, whose arrays are valid for
+/// the lengths given.
+#[no_mangle]
+pub unsafe extern "C" fn Slvs_Solve(ssys: *mut Slvs_System, shg: Slvs_hGroup) {
+    let ssys = &mut *ssys;
+    let result = panic::catch_unwind(AssertUnwindSafe(|| solve(ssys, shg)));
+    let failed_with = match result {
+        Ok(Ok(())) => return,
+        Ok(Err(result)) => result,
+        Err(_) => SLVS_RESULT_INTERNAL_ERROR,
+    };
+    ssys.faileds = 0;
+    ssys.dof = 0;
+    ssys.result = failed_with;
+}
+
+unsafe fn solve(ssys: &mut Slvs_System, shg: Slvs_hGroup) -> Result<(), c_int> {
+    let mut sys = SlvsSystem::new();
+
+    for p in as_slice(ssys.param, ssys.params) {
+        sys.param.push(SlvsParam::new(p.h, p.group, p.val));
+    }
+    for e in as_slice(ssys.entity, ssys.entities) {
+        let type_ = entity_type(e.r#type).ok_or(SLVS_RESULT_UNKNOWN_TYPE)?;
+        sys.entity.push(SlvsEntity {
+            h: e.h,
+            group: e.group,
+            type_,
+            wrkpl: e.wrkpl,
+            point: e.point,
+            normal: e.normal,
+            distance: e.distance,
+            param: e.param,
+        });
+    }
+    for c in as_slice(ssys.constraint, ssys.constraints) {
+        let type_ = constraint_type(c.r#type).ok_or(SLVS_RESULT_UNKNOWN_TYPE)?;
+        let mut sc = SlvsConstraint::new(c.h, c.group, type_, c.wrkpl);
+        sc.val_a = c.valA;
+        sc.pt_a = c.ptA;
+        sc.pt_b = c.ptB;
+        sc.entity_a = c.entityA;
+        sc.entity_b = c.entityB;
+        sc.entity_c = c.entityC;
+        sc.entity_d = c.entityD;
+        sc.other = c.other != 0;
+        sc.other2 = c.other2 != 0;
+        sys.constraint.push(sc);
+    }
+    sys.dragged = ssys.dragged.iter().cloned().filter(|h| *h != 0).collect();
+    sys.calculate_faileds = ssys.calculateFaileds != 0;
+
+    if sys.solve(shg) == SlvsResult::BadInput {
+        return Err(SLVS_RESULT_BAD_INPUT);
+    }
+
+    if !ssys.param.is_null() {
+        let param = slice::from_raw_parts_mut(ssys.param, ssys.params.max(0) as usize);
+        for (p, sp) in param.iter_mut().zip(sys.param.iter()) {
+            p.val = sp.val;
+        }
+    }
+    if !ssys.failed.is_null() {
+        let failed = slice::from_raw_parts_mut(ssys.failed, ssys.faileds.max(0) as usize);
+        for (f, h) in failed.iter_mut().zip(sys.failed.iter()) {
+            *f = *h;
+        }
+        ssys.faileds = sys.failed.len() as c_int;
+    }
+    ssys.dof = sys.dof;
+    ssys.result = match sys.result {
+        SlvsResult::Okay => SLVS_RESULT_OKAY,
+        SlvsResult::Inconsistent => SLVS_RESULT_INCONSISTENT,
+        SlvsResult::DidntConverge => SLVS_RESULT_DIDNT_CONVERGE,
+        SlvsResult::TooManyUnknowns => SLVS_RESULT_TOO_MANY_UNKNOWNS,
+        SlvsResult::BadInput => SLVS_RESULT_BAD_INPUT,
+    };
+    Ok(())
+}
+
+unsafe fn write_vector(v: Vector, x: *mut f64, y: *mut f64, z: *mut f64) {
+    *x = v.x;
+    *y = v.y;
+    *z = v.z;
+}
+
+/// The first basis vector of the workplane a quaternion describes.
+///
+/// # Safety
+///
+/// The output pointers must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn Slvs_QuaternionU(
+    qw: f64,
+    qx: f64,
+    qy: f64,
+    qz: f64,
+    x: *mut f64,
+    y: *mut f64,
+    z: *mut f64,
+) {
+    write_vector(Quaternion::new(qw, qx, qy, qz).rotation_u(), x, y, z);
+}
+
+/// The second basis vector of the workplane a quaternion describes.
+///
+/// # Safety
+///
+/// The output pointers must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn Slvs_QuaternionV(
+    qw: f64,
+    qx: f64,
+    qy: f64,
+    qz: f64,
+    x: *mut f64,
+    y: *mut f64,
+    z: *mut f64,
+) {
+    write_vector(Quaternion::new(qw, qx, qy, qz).rotation_v(), x, y, z);
+}
+
+/// The normal of the workplane a quaternion describes.
+///
+/// # Safety
+///
+/// The output pointers must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn Slvs_QuaternionN(
+    qw: f64,
+    qx: f64,
+    qy: f64,
+    qz: f64,
+    x: *mut f64,
+    y: *mut f64,
+    z: *mut f64,
+) {
+    write_vector(Quaternion::new(qw, qx, qy, qz).rotation_n(), x, y, z);
+}
+
+/// The unit quaternion for the workplane with basis vectors u and v.
+///
+/// # Safety
+///
+/// The output pointers must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn Slvs_MakeQuaternion(
+    ux: f64,
+    uy: f64,
+    uz: f64,
+    vx: f64,
+    vy: f64,
+    vz: f64,
+    qw: *mut f64,
+    qx: *mut f64,
+    qy: *mut f64,
+    qz: *mut f64,
+) {
+    let q = Quaternion::from_uv(Vector::new(ux, uy, uz), Vector::new(vx, vy, vz));
+    *qw = q.w;
+    *qx = q.vx;
+    *qy = q.vy;
+    *qz = q.vz;
+}
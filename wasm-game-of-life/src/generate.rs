@@ -0,0 +1,226 @@
+use crate::sketch::{HConstraint, HEntity, HGroup, HParam, Sketch};
+use crate::system::{SolveResult, System};
+
+impl Sketch {
+    pub fn generate_constraint_params(&mut self, hg: HGroup) {
+        let hcs: Vec<HConstraint> = self
+            .constraint
+            .values()
+            .filter(|c| c.group == hg)
+            .map(|c| c.h)
+            .collect();
+        for hc in hcs {
+            let mut c = self.constraint.take(&hc).unwrap();
+            if let Some(p) = c.generate_params(self) {
+                self.param.add(p);
+            }
+            self.constraint.add(c);
+        }
+    }
+
+    // and_find_bad lists what to remove when the group fails, and
+    // and_find_free marks the params that can still move.
+    pub fn solve_group(
+        &mut self,
+        hg: HGroup,
+        and_find_bad: bool,
+        and_find_free: bool,
+    ) -> SolveResult {
+        self.cancel.reset();
+        self.solve_one_group(hg, and_find_bad, and_find_free)
+    }
+
+    // The cancel token is reset once, when whatever called this started,
+    // so that a cancel while one group solves stops the ones after it too.
+    fn solve_one_group(
+        &mut self,
+        hg: HGroup,
+        and_find_bad: bool,
+        and_find_free: bool,
+    ) -> SolveResult {
+        self.update_expressions();
+        self.generate_constraint_params(hg);
+
+        let mut sys = System::new();
+        sys.timeout = self.solve_timeout;
+        sys.cancel = Some(self.cancel.clone());
+        sys.write_params_for_group(self, hg);
+        sys.write_equations_for_group(self, hg);
+
+        let g = match self.group.get_mut(&hg) {
+            Some(g) => g,
+            None => panic!("Sketch::solve_group(...) of unknown group {}", hg.v),
+        };
+        sys.relaxed = g.relax_constraints;
+        let how = sys.solve(
+            &mut g.solved,
+            g.allow_redundant,
+            and_find_bad,
+            and_find_free,
+        );
+        g.dof_check_ok = how == SolveResult::Okay && g.solved.dof == 0;
+
+        self.write_back_params(&sys);
+        self.update_reference_dimensions(hg);
+        how
+    }
+
+    // Moves the dragged params towards their targets and re-solves the
+    // group, keeping them as close to there as the constraints allow.
+    pub fn drag(&mut self, hg: HGroup, dragged: &[(HParam, f64)]) -> SolveResult {
+        self.cancel.reset();
+        self.generate_constraint_params(hg);
+
+        let mut sys = System::new();
+        sys.timeout = self.solve_timeout;
+        sys.cancel = Some(self.cancel.clone());
+        sys.write_params_for_group(self, hg);
+        sys.write_equations_for_group(self, hg);
+
+        let g = match self.group.get_mut(&hg) {
+            Some(g) => g,
+            None => panic!("Sketch::drag(...) of unknown group {}", hg.v),
+        };
+        sys.solve_dragged(dragged, &mut g.solved);
+        let how = g.solved.how;
+
+        self.write_back_params(&sys);
+        self.update_reference_dimensions(hg);
+        self.generate_loops(hg);
+        self.generate_shell_and_mesh(hg);
+
+        // Only what's after this group can have moved with it; dragging in
+        // the last sketch leaves nothing else to redo.
+        match self.next_group(hg) {
+            Some(hn) => {
+                self.mark_group_dirty(hn);
+                self.regenerate_dirty_groups();
+            }
+            None => self.record_traces(),
+        }
+        how
+    }
+
+    fn write_back_params(&mut self, sys: &System) {
+        for p in sys.param.values() {
+            if let Some(sp) = self.param.get_mut(&p.h) {
+                sp.val = p.val;
+                sp.free = p.free;
+            }
+        }
+    }
+
+    // Every group in order, each solved against what the ones before it
+    // left and then given its solid, combined with the solid so far. A
+    // suppressed group is still solved, as in SolveSpace; suppress only
+    // leaves its solid out of the model.
+    pub fn regenerate_all(&mut self) -> Vec<(HGroup, SolveResult)> {
+        self.mark_groups_dirty_from(i32::MIN);
+        self.regenerate_dirty()
+    }
+
+    // As SolveSpace's GenerateAll(Generate::DIRTY): the groups before the
+    // first dirty one keep their solved params and solids, and everything
+    // from there on is redone in order: a group copies what it needs from
+    // the groups before it, is solved, and then finds its loops and makes
+    // its solid. The requests in those groups make their entities first,
+    // and traced points then add wherever they ended up to their paths.
+    // Whatever fails is listed for removal, but finding free params is left
+    // to an explicit solve_group(..), since it costs a rank check per
+    // param. Only the groups that were redone are returned.
+    pub fn regenerate_dirty(&mut self) -> Vec<(HGroup, SolveResult)> {
+        self.cancel.reset();
+        self.regenerate_dirty_groups()
+    }
+
+    // As regenerate_dirty(), but as part of something that has already
+    // reset the cancel token.
+    pub(crate) fn regenerate_dirty_groups(&mut self) -> Vec<(HGroup, SolveResult)> {
+        let mut order: Vec<(i32, HGroup)> = self.group.values().map(|g| (g.order, g.h)).collect();
+        order.sort();
+        let first = order
+            .iter()
+            .position(|(_, hg)| !self.get_group(*hg).clean)
+            .unwrap_or(order.len());
+        if let Some((from_order, _)) = order.get(first) {
+            self.generate_requests(*from_order);
+        }
+        let mut r = Vec::new();
+        for (_, hg) in order.into_iter().skip(first) {
+            self.generate_group(hg);
+            let how = self.solve_one_group(hg, true, false);
+            self.generate_loops(hg);
+            self.generate_shell_and_mesh(hg);
+            self.group.get_mut(&hg).unwrap().clean = true;
+            r.push((hg, how));
+        }
+        self.record_traces();
+        r
+    }
+
+    // As SolveSpace's MarkGroupDirty: a group builds on every group before
+    // it, so whatever changes it changes everything after it too. There's
+    // nothing to mark for a group that isn't in the sketch.
+    pub fn mark_group_dirty(&mut self, hg: HGroup) {
+        if let Some(g) = self.group.get(&hg) {
+            self.mark_groups_dirty_from(g.order);
+        }
+    }
+
+    pub fn mark_groups_dirty_from(&mut self, order: i32) {
+        for g in self.group.values_mut() {
+            if g.order >= order {
+                g.clean = false;
+            }
+        }
+    }
+
+    // Reference dimensions don't drive the geometry, they follow it; so
+    // re-measure them whenever it might have moved. Only a dimension that's
+    // a reference itself takes the measurement as its value too.
+    pub fn update_reference_dimensions(&mut self, hg: HGroup) {
+        let all_dims_reference = self.group.get(&hg).is_some_and(|g| g.all_dims_reference);
+        let measured: Vec<(HConstraint, Option<f64>)> = self
+            .constraint
+            .values()
+            .filter(|c| c.group == hg && c.is_dimension())
+            .map(|c| {
+                let is_reference = c.reference || all_dims_reference;
+                (c.h, if is_reference { c.measure(self) } else { None })
+            })
+            .collect();
+        for (hc, val) in measured {
+            let c = self.constraint.get_mut(&hc).unwrap();
+            c.measured = val;
+            if let (true, Some(val)) = (c.reference, val) {
+                c.val_a = val;
+            }
+        }
+    }
+
+    pub fn reference_dimensions(&self, hg: HGroup) -> Vec<(HConstraint, f64)> {
+        let all_dims_reference = self.group.get(&hg).is_some_and(|g| g.all_dims_reference);
+        let mut r: Vec<(HConstraint, f64)> = self
+            .constraint
+            .values()
+            .filter(|c| c.group == hg && c.is_dimension())
+            .filter(|c| c.reference || all_dims_reference)
+            .map(|c| (c.h, c.measured.unwrap_or(c.val_a)))
+            .collect();
+        r.sort_by_key(|(h, _)| *h);
+        r
+    }
+
+    // The entities of a group that can still move, as found by the last
+    // solve_group(..) that was asked to find free params.
+    pub fn free_entities(&self, hg: HGroup) -> Vec<HEntity> {
+        let mut r: Vec<HEntity> = self
+            .entity
+            .values()
+            .filter(|e| e.group == hg && e.is_free(self))
+            .map(|e| e.h)
+            .collect();
+        r.sort();
+        r
+    }
+}
@@ -0,0 +1,400 @@
+use crate::dsc::{Quaternion, RgbaColor, Vector};
+use crate::expr::ExprVector;
+use crate::polygon::{SEdge, SMesh, SOutlineList, SPolygon};
+use crate::sketch::{
+    EntityBase, EntityBaseType, EntityId, EntityKey, EntityList, EntityMap, Equation, Group,
+    GroupCombineAs, GroupPolyError, GroupPredef, GroupRemap, GroupSolved, GroupSubtype, GroupType,
+    HEntity, HGroup, HParam, Param, PolyError, Sketch, SketchError, FREE_IN_3D, NO_ENTITY,
+    NO_PARAM,
+};
+use crate::surface::{SBezierLoopSet, SBezierLoopSetSet, SShell};
+use crate::utils::PlatformPath;
+
+impl GroupType {
+    pub fn from_u32(v: u32) -> Option<Self> {
+        let types = [
+            GroupType::Drawing3D,
+            GroupType::DrawingWorkplane,
+            GroupType::Extrude,
+            GroupType::Lathe,
+            GroupType::Revolve,
+            GroupType::Helix,
+            GroupType::Rotate,
+            GroupType::Translate,
+            GroupType::Linked,
+        ];
+        types.iter().cloned().find(|t| *t as u32 == v)
+    }
+}
+
+impl Group {
+    // A group with nothing in it yet; add_group(..) gives it a handle and
+    // puts it at the end of the history.
+    pub fn new(type_: GroupType) -> Self {
+        Group {
+            tag: 0,
+            h: HGroup { v: 0 },
+            type_,
+            order: 0,
+            op_a: HGroup { v: 0 },
+            op_b: HGroup { v: 0 },
+            visible: true,
+            suppress: false,
+            relax_constraints: false,
+            allow_redundant: false,
+            all_dims_reference: false,
+            scale: 1.0,
+            clean: false,
+            dof_check_ok: false,
+            active_workplane: FREE_IN_3D,
+            val_a: 0.0,
+            val_b: 0.0,
+            val_c: 0.0,
+            color: RgbaColor {
+                red: 100,
+                green: 100,
+                blue: 100,
+                alpha: 255,
+            },
+            solved: GroupSolved::default(),
+            subtype: GroupSubtype::OneSided,
+            skip_first: false,
+            predef: GroupPredef {
+                q: Quaternion::new(1.0, 0.0, 0.0, 0.0),
+                origin: NO_ENTITY,
+                entity_b: NO_ENTITY,
+                entity_c: NO_ENTITY,
+                swap_uv: false,
+                negate_u: false,
+                negate_v: false,
+            },
+            poly_loops: SPolygon::new(),
+            bezier_loops: SBezierLoopSetSet::new(),
+            bezier_opens: SBezierLoopSet::new(),
+            poly_error: GroupPolyError {
+                how: PolyError::Good,
+                not_closed_at: SEdge {
+                    tag: 0,
+                    aux_a: 0,
+                    aux_b: 0,
+                },
+                error_point_at: Vector::new(0.0, 0.0, 0.0),
+            },
+            boolean_failed: false,
+            this_shell: SShell::new(),
+            running_shell: SShell::new(),
+            this_mesh: SMesh::new(),
+            running_mesh: SMesh::new(),
+            display_dirty: true,
+            display_mesh: SMesh::new(),
+            display_outlines: SOutlineList::new(),
+            mesh_combine: GroupCombineAs::Union,
+            force_to_mesh: false,
+            remap: EntityMap::new(),
+            link_file: PlatformPath { raw: String::new() },
+            imp_mesh: SMesh::new(),
+            imp_shell: SShell::new(),
+            imp_entity: EntityList::new(),
+            name: String::new(),
+        }
+    }
+
+    // Whether the running solid is only a mesh, because a boolean cut it
+    // into the solid before and shells can't be combined that way.
+    pub fn is_mesh_only(&self) -> bool {
+        self.running_shell.is_empty() && !self.running_mesh.is_empty()
+    }
+
+    // How many times the translation an extrusion's bottom and top are
+    // moved from the sketch; a two-sided one goes as far back as forward.
+    pub fn extrude_range(&self) -> (i32, i32) {
+        match self.subtype {
+            GroupSubtype::TwoSided => (-1, 1),
+            _ => (0, 1),
+        }
+    }
+
+    // The workplane an extrusion's sketch is drawn in, if it has one.
+    pub fn extrude_workplane(&self, sk: &Sketch) -> Option<HEntity> {
+        let src = sk.group.get(&self.op_a)?;
+        if src.type_ == GroupType::DrawingWorkplane && sk.entity.contains_key(&src.active_workplane)
+        {
+            Some(src.active_workplane)
+        } else {
+            None
+        }
+    }
+
+    pub fn extrude_translation(&self, sk: &Sketch) -> Vector {
+        ExprVector::from_params(self.h.param(0), self.h.param(1), self.h.param(2)).eval(&sk.param)
+    }
+
+    // As SolveSpace's Group::GenerateEquations: an extrusion out of a
+    // workplane goes straight out of it, so only its depth is left free.
+    pub fn generate_equations(&self, sk: &Sketch, l: &mut Vec<Equation>) {
+        if self.type_ != GroupType::Extrude {
+            return;
+        }
+        let wrkpl = match self.extrude_workplane(sk) {
+            Some(h) => h,
+            None => return,
+        };
+        let n = sk.get_entity(wrkpl).normal_entity(sk);
+        let t = ExprVector::from_params(self.h.param(0), self.h.param(1), self.h.param(2));
+        l.push(Equation {
+            tag: 0,
+            h: self.h.equation(0),
+            e: n.normal_exprs_u(sk).dot(&t),
+        });
+        l.push(Equation {
+            tag: 0,
+            h: self.h.equation(1),
+            e: n.normal_exprs_v(sk).dot(&t),
+        });
+    }
+}
+
+// The entity that stands for in, copied for the given reason; the same
+// input and reason always give the same handle, so constraints on it
+// survive regenerating.
+fn remap(map: &mut EntityMap, hg: HGroup, input: HEntity, copy_number: GroupRemap) -> HEntity {
+    let next = EntityId {
+        v: map.len() as u32,
+    };
+    let key = EntityKey {
+        input,
+        copy_number: copy_number as i32,
+    };
+    hg.entity(map.entry(key).or_insert(next).v)
+}
+
+impl Sketch {
+    // Adds the group under a new handle, after every group already there,
+    // so it's made from them next time the sketch regenerates. An extrusion
+    // needs a group to extrude.
+    pub fn add_group(&mut self, mut g: Group) -> Result<HGroup, SketchError> {
+        if g.op_a.v != 0 || g.type_ == GroupType::Extrude {
+            self.find_group(g.op_a)?;
+        }
+        if g.active_workplane != FREE_IN_3D
+            && self.find_entity(g.active_workplane)?.type_ != EntityBaseType::Workplane
+        {
+            return Err(SketchError::NotAWorkplane(g.active_workplane.v));
+        }
+        g.order = self.group.values().map(|g| g.order + 1).max().unwrap_or(0);
+        g.clean = false;
+        self.group
+            .add_and_assign_id(g)
+            .ok_or(SketchError::OutOfHandles)
+    }
+
+    // As SolveSpace's Group::Generate: the entities and params that a group
+    // makes from the groups before it, made again from where those are now.
+    // Groups that are drawn in make theirs from requests instead.
+    pub fn generate_group(&mut self, hg: HGroup) {
+        let g = self.get_group(hg);
+        if g.type_ != GroupType::Extrude {
+            return;
+        }
+
+        let stale: Vec<HEntity> = self
+            .entity
+            .values()
+            .filter(|e| e.group == hg && !e.h.is_from_request())
+            .map(|e| e.h)
+            .collect();
+        for h in stale {
+            self.entity.take(&h);
+        }
+
+        // Until a constraint says how deep, one unit straight out.
+        let g = self.get_group(hg);
+        let n = match g.extrude_workplane(self) {
+            Some(wrkpl) => self.get_entity(wrkpl).normal_entity(self).normal_n(self),
+            None => Vector::new(0.0, 0.0, 1.0),
+        };
+        for (i, val) in [n.x, n.y, n.z].iter().enumerate() {
+            let h = hg.param(i as u32);
+            if !self.param.contains_key(&h) {
+                self.param.add(Param {
+                    tag: 0,
+                    h,
+                    val: *val,
+                    known: false,
+                    free: false,
+                    substd: NO_PARAM,
+                });
+            }
+        }
+
+        let g = self.get_group(hg);
+        let (ai, af) = g.extrude_range();
+        // With skip_first a one-sided extrusion doesn't copy the sketch where
+        // it already is, and builds on the sketch's own entities instead.
+        let skip_bottom = g.skip_first && ai == 0;
+        let trans = [hg.param(0), hg.param(1), hg.param(2)];
+        let face_normal = self
+            .group
+            .get(&g.op_a)
+            .map(|src| src.poly_loops.normal)
+            .filter(|n| n.mag_squared() > 0.0)
+            .unwrap_or(n);
+
+        let mut src: Vec<&EntityBase> =
+            self.entity.values().filter(|e| e.group == g.op_a).collect();
+        src.sort_by_key(|e| e.h);
+
+        let mut map = g.remap.clone();
+        let mut made = Vec::new();
+        let mut pt = None;
+        for e in src {
+            if e.is_point() && pt.is_none() {
+                pt = Some(e.h);
+            }
+            if !skip_bottom {
+                made.extend(self.copy_entity(&mut map, hg, e, ai, GroupRemap::Bottom));
+            }
+            made.extend(self.copy_entity(&mut map, hg, e, af, GroupRemap::Top));
+            made.extend(self.make_extrusion_lines(&mut map, hg, e, skip_bottom, trans));
+        }
+
+        // The top and bottom faces, through any point of the sketch.
+        if let Some(pt) = pt {
+            let bottom = if skip_bottom {
+                pt
+            } else {
+                remap(&mut map, hg, pt, GroupRemap::Bottom)
+            };
+            let top = remap(&mut map, hg, pt, GroupRemap::Top);
+            let faces = [
+                (GroupRemap::Top, top, face_normal),
+                (GroupRemap::Bottom, bottom, -face_normal),
+            ];
+            for &(how, pt, n) in faces.iter() {
+                let h = remap(&mut map, hg, NO_ENTITY, how);
+                let mut en = EntityBase::new(h, EntityBaseType::FaceNormalPt);
+                en.group = hg;
+                en.point[0] = pt;
+                en.num_normal = Quaternion::new(0.0, n.x, n.y, n.z);
+                made.push(en);
+            }
+        }
+
+        for en in made {
+            self.entity.add(en);
+        }
+        self.group.get_mut(&hg).unwrap().remap = map;
+    }
+
+    // As SolveSpace's Group::CopyEntity, for an N_TRANS copy: points move
+    // with the group's translation, times the number of times applied,
+    // and everything else is fixed where it is or built on copied points.
+    // Workplanes, faces and the like aren't copied.
+    fn copy_entity(
+        &self,
+        map: &mut EntityMap,
+        hg: HGroup,
+        e: &EntityBase,
+        times_applied: i32,
+        how: GroupRemap,
+    ) -> Option<EntityBase> {
+        let copy_as = |map: &mut EntityMap, h: HEntity| {
+            if h == NO_ENTITY {
+                NO_ENTITY
+            } else {
+                remap(map, hg, h, how)
+            }
+        };
+        let type_ = if e.is_point() {
+            EntityBaseType::PointNTrans
+        } else if e.is_normal() {
+            EntityBaseType::NormalNCopy
+        } else if e.is_distance() {
+            EntityBaseType::DistanceNCopy
+        } else {
+            match e.type_ {
+                EntityBaseType::LineSegment
+                | EntityBaseType::Cubic
+                | EntityBaseType::CubicPeriodic
+                | EntityBaseType::Circle
+                | EntityBaseType::ArcOfCircle => e.type_,
+                _ => return None,
+            }
+        };
+
+        let mut en = EntityBase::new(copy_as(map, e.h), type_);
+        en.group = hg;
+        en.workplane = FREE_IN_3D;
+        en.construction = e.construction;
+        match type_ {
+            EntityBaseType::PointNTrans => {
+                en.num_point = e.point_get_num(self);
+                en.param[0] = hg.param(0);
+                en.param[1] = hg.param(1);
+                en.param[2] = hg.param(2);
+                en.times_applied = times_applied;
+            }
+            EntityBaseType::NormalNCopy => {
+                en.num_normal = e.normal_get_num(self);
+                en.point[0] = copy_as(map, e.point[0]);
+            }
+            EntityBaseType::DistanceNCopy => en.num_distance = e.distance_get_num(self),
+            _ => {
+                for (to, from) in en.point.iter_mut().zip(e.point.iter()) {
+                    *to = copy_as(map, *from);
+                }
+                en.extra_points = e.extra_points;
+                en.normal = copy_as(map, e.normal);
+                en.distance = copy_as(map, e.distance);
+            }
+        }
+        Some(en)
+    }
+
+    // As SolveSpace's Group::MakeExtrusionLines: each point of the sketch
+    // sweeps out a line from bottom to top, and each line segment a flat
+    // face, through one end and containing both the line and the
+    // translation.
+    fn make_extrusion_lines(
+        &self,
+        map: &mut EntityMap,
+        hg: HGroup,
+        e: &EntityBase,
+        skip_bottom: bool,
+        trans: [HParam; 3],
+    ) -> Option<EntityBase> {
+        if e.is_point() {
+            let mut en = EntityBase::new(
+                remap(map, hg, e.h, GroupRemap::PtToLine),
+                EntityBaseType::LineSegment,
+            );
+            en.group = hg;
+            en.construction = e.construction;
+            en.point[0] = remap(map, hg, e.h, GroupRemap::Top);
+            en.point[1] = if skip_bottom {
+                e.h
+            } else {
+                remap(map, hg, e.h, GroupRemap::Bottom)
+            };
+            Some(en)
+        } else if e.type_ == EntityBaseType::LineSegment {
+            let a = self.get_entity(e.point[0]).point_get_num(self);
+            let b = self.get_entity(e.point[1]).point_get_num(self);
+            let ab = b - a;
+            let mut en = EntityBase::new(
+                remap(map, hg, e.h, GroupRemap::LineToFace),
+                EntityBaseType::FaceXProd,
+            );
+            en.group = hg;
+            en.construction = e.construction;
+            en.num_point = a;
+            en.num_normal = Quaternion::new(0.0, ab.x, ab.y, ab.z);
+            en.param[0] = trans[0];
+            en.param[1] = trans[1];
+            en.param[2] = trans[2];
+            Some(en)
+        } else {
+            None
+        }
+    }
+}
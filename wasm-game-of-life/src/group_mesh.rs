@@ -0,0 +1,303 @@
+use crate::dsc::{Vector, LENGTH_EPS};
+use crate::polygon::{SEdge, SMesh, SPolygon};
+use crate::sketch::{
+    EntityKey, Group, GroupCombineAs, GroupPolyError, GroupRemap, GroupType, HEntity, HGroup,
+    PolyError, Sketch, NO_ENTITY,
+};
+use crate::surface::{SBezier, SBezierLoop, SBezierLoopSet, SBezierLoopSetSet, SShell};
+
+// How far a chord may stray from the curve it stands in for, when curves
+// are broken into straight pieces.
+const CHORD_TOL: f64 = 0.01;
+
+impl Sketch {
+    // The group just before this one in order, whose solid it builds on.
+    pub fn previous_group(&self, hg: HGroup) -> Option<HGroup> {
+        let order = self.get_group(hg).order;
+        self.group
+            .values()
+            .filter(|g| g.order < order)
+            .max_by_key(|g| g.order)
+            .map(|g| g.h)
+    }
+
+    pub fn next_group(&self, hg: HGroup) -> Option<HGroup> {
+        let order = self.get_group(hg).order;
+        self.group
+            .values()
+            .filter(|g| g.order > order)
+            .min_by_key(|g| g.order)
+            .map(|g| g.h)
+    }
+
+    // The solid this group makes by itself, before it's combined with
+    // anything, as faces and as triangles.
+    fn generate_this_shell(&self, hg: HGroup) -> (SShell, SMesh) {
+        let g = self.get_group(hg);
+        let shell = match g.type_ {
+            GroupType::Linked => g.imp_shell.clone(),
+            GroupType::Extrude => self.generate_extrusion(g),
+            // A sketch has no solid of its own, and the other kinds of
+            // group don't make one yet.
+            _ => SShell::new(),
+        };
+        let mut mesh = SMesh::new();
+        if g.type_ == GroupType::Linked && shell.is_empty() {
+            // The file had only a mesh to give.
+            mesh = g.imp_mesh.clone();
+        } else {
+            shell.triangulate_into(&mut mesh);
+        }
+        (shell, mesh)
+    }
+
+    // As SolveSpace's Group::GenerateLoops: the curves drawn in a workplane
+    // group, joined into closed loops for a later group to make a solid
+    // from. Construction entities don't count. If some curve doesn't join
+    // up, poly_error says where, and there are no loops.
+    pub fn generate_loops(&mut self, hg: HGroup) {
+        let g = self.get_group(hg);
+        let mut bezier_loops = SBezierLoopSetSet::new();
+        let mut poly_loops = SPolygon::new();
+        let mut poly_error = GroupPolyError {
+            how: PolyError::Good,
+            not_closed_at: SEdge {
+                tag: 0,
+                aux_a: 0,
+                aux_b: 0,
+            },
+            error_point_at: Vector::new(0.0, 0.0, 0.0),
+        };
+
+        let wrkpl = self.entity.get(&g.active_workplane);
+        if let (GroupType::DrawingWorkplane, Some(wrkpl)) = (g.type_, wrkpl) {
+            let (point, _, _, normal) = wrkpl.workplane_get_csys(self);
+
+            let mut curves: Vec<SBezier> = Vec::new();
+            let mut entities: Vec<_> = self
+                .entity
+                .values()
+                .filter(|e| e.group == hg && !e.construction)
+                .collect();
+            entities.sort_by_key(|e| e.h);
+            for e in entities {
+                e.generate_bezier_curves(self, &mut curves);
+            }
+
+            let mut loops: Vec<SBezierLoop> = Vec::new();
+            while !curves.is_empty() {
+                let (sbl, closed) = SBezierLoop::from_curves(&mut curves);
+                if !closed {
+                    let b = &sbl.l[sbl.l.len() - 1];
+                    poly_error.how = PolyError::NotClosed;
+                    poly_error.not_closed_at.aux_a = b.entity as i32;
+                    poly_error.error_point_at = b.finish();
+                    loops.clear();
+                    break;
+                }
+                loops.push(sbl);
+            }
+
+            bezier_loops =
+                SBezierLoopSetSet::find_outer_faces_from(loops, normal, point, CHORD_TOL);
+            for sbls in bezier_loops.l.iter() {
+                sbls.make_pwl_into(&mut poly_loops, CHORD_TOL);
+            }
+            poly_loops.normal = normal;
+        }
+
+        let g = self.group.get_mut(&hg).unwrap();
+        g.bezier_loops = bezier_loops;
+        g.poly_loops = poly_loops;
+        g.bezier_opens = SBezierLoopSet::new();
+        g.poly_error = poly_error;
+    }
+
+    // The solid an extrusion sweeps out of its sketch's loops: each region
+    // moved to the bottom and top, and a side face for every chord of every
+    // curve in between. Faces get the handles of the entities that stand
+    // for them, where there are any.
+    fn generate_extrusion(&self, g: &Group) -> SShell {
+        let mut shell = SShell::new();
+        let src = match self.group.get(&g.op_a) {
+            Some(src) => src,
+            None => return shell,
+        };
+        let t = g.extrude_translation(self);
+        if t.mag_squared() < LENGTH_EPS * LENGTH_EPS {
+            return shell;
+        }
+        let (ai, af) = g.extrude_range();
+        let (bottom, top) = (t * (ai as f64), t * (af as f64));
+        let face = |input, how: GroupRemap| {
+            let key = EntityKey {
+                input,
+                copy_number: how as i32,
+            };
+            g.remap.get(&key).map_or(0, |id| g.h.entity(id.v).v)
+        };
+
+        for sbls in src.bezier_loops.l.iter() {
+            // The loops go anticlockwise round the outside about the
+            // sketch's normal, which is the way the top has to face.
+            let mut sbls = sbls.clone();
+            let mut n = sbls.normal;
+            if t.dot(n) < 0.0 {
+                n = -n;
+                for sbl in sbls.l.iter_mut() {
+                    sbl.reverse();
+                }
+            }
+            sbls.normal = n;
+
+            let mut pwl = SPolygon::new();
+            sbls.make_pwl_into(&mut pwl, CHORD_TOL);
+            let moved = |by: Vector, reverse: bool| {
+                let mut sp = pwl.clone();
+                for c in sp.l.iter_mut() {
+                    for p in c.l.iter_mut() {
+                        *p = *p + by;
+                    }
+                    if reverse {
+                        c.reverse();
+                    }
+                }
+                sp.normal = if reverse { -n } else { n };
+                sp
+            };
+            shell.add_surface(face(NO_ENTITY, GroupRemap::Top), g.color, moved(top, false));
+            shell.add_surface(
+                face(NO_ENTITY, GroupRemap::Bottom),
+                g.color,
+                moved(bottom, true),
+            );
+
+            for sbl in sbls.l.iter() {
+                for b in sbl.l.iter() {
+                    let mut l = vec![b.start()];
+                    b.make_pwl_into(&mut l, CHORD_TOL);
+                    let hf = if b.deg == 1 {
+                        face(HEntity { v: b.entity }, GroupRemap::LineToFace)
+                    } else {
+                        0
+                    };
+                    for w in l.windows(2) {
+                        let (p, q) = (w[0], w[1]);
+                        let mut sp = SPolygon::new();
+                        sp.add_empty_contour();
+                        for v in [p + bottom, q + bottom, q + top, p + top].iter() {
+                            sp.add_point(*v);
+                        }
+                        sp.normal = (q - p).cross(n).with_magnitude(1.0);
+                        shell.add_surface(hf, g.color, sp);
+                    }
+                }
+            }
+        }
+        shell
+    }
+
+    // As SolveSpace's Group::GenerateShellAndMesh: makes this group's own
+    // solid, then combines it with the running solid of the group before
+    // to give this group's running solid. A suppressed group, or one with
+    // no solid, just passes on what came before.
+    pub fn generate_shell_and_mesh(&mut self, hg: HGroup) {
+        let (prev_shell, prev_mesh) = match self.previous_group(hg) {
+            Some(hp) => {
+                let prev = self.get_group(hp);
+                (prev.running_shell.clone(), prev.running_mesh.clone())
+            }
+            None => (SShell::new(), SMesh::new()),
+        };
+
+        let (this_shell, this_mesh) = self.generate_this_shell(hg);
+
+        let g = match self.group.get_mut(&hg) {
+            Some(g) => g,
+            None => panic!(
+                "Sketch::generate_shell_and_mesh(...) of unknown group {}",
+                hg.v
+            ),
+        };
+        let (running_shell, running_mesh, boolean_failed) = if g.suppress || this_mesh.is_empty() {
+            (prev_shell, prev_mesh, false)
+        } else {
+            combine(
+                g.mesh_combine,
+                &prev_shell,
+                &prev_mesh,
+                &this_shell,
+                &this_mesh,
+            )
+        };
+        g.this_shell = this_shell;
+        g.this_mesh = this_mesh;
+        g.running_shell = running_shell;
+        g.running_mesh = running_mesh;
+        g.boolean_failed = boolean_failed;
+        g.display_dirty = true;
+    }
+
+    // What's drawn for the group: the solid so far, and its edges. Only
+    // remade if the group has been regenerated since last time.
+    pub fn generate_display_items(&mut self, hg: HGroup) {
+        let g = match self.group.get_mut(&hg) {
+            Some(g) => g,
+            None => panic!(
+                "Sketch::generate_display_items(...) of unknown group {}",
+                hg.v
+            ),
+        };
+        if !g.display_dirty {
+            return;
+        }
+        g.display_mesh = g.running_mesh.clone();
+        g.display_outlines.clear();
+        g.running_mesh.make_outlines_into(&mut g.display_outlines);
+        g.display_dirty = false;
+    }
+}
+
+// There are no booleans on shells, only on meshes, as in SolveSpace with
+// force_to_mesh set. So the running shell is only kept while it's still the
+// whole solid, that is until two solids are cut into each other; from then
+// on there's just the mesh. Says too whether a boolean failed to give a
+// closed solid.
+fn combine(
+    how: GroupCombineAs,
+    prev_shell: &SShell,
+    prev_mesh: &SMesh,
+    this_shell: &SShell,
+    this_mesh: &SMesh,
+) -> (SShell, SMesh, bool) {
+    if prev_mesh.is_empty() {
+        return match how {
+            GroupCombineAs::Union | GroupCombineAs::Assemble => {
+                (this_shell.clone(), this_mesh.clone(), false)
+            }
+            GroupCombineAs::Difference | GroupCombineAs::Intersection => {
+                (SShell::new(), SMesh::new(), false)
+            }
+        };
+    }
+    let mesh = match how {
+        GroupCombineAs::Assemble => {
+            let shell = if prev_shell.is_empty() || this_shell.is_empty() {
+                SShell::new()
+            } else {
+                SShell::make_from_assembly_of(prev_shell, this_shell)
+            };
+            return (
+                shell,
+                SMesh::make_from_assembly_of(prev_mesh, this_mesh),
+                false,
+            );
+        }
+        GroupCombineAs::Union => SMesh::make_from_union_of(prev_mesh, this_mesh),
+        GroupCombineAs::Difference => SMesh::make_from_difference_of(prev_mesh, this_mesh),
+        GroupCombineAs::Intersection => SMesh::make_from_intersection_of(prev_mesh, this_mesh),
+    };
+    // Nothing left of an intersection or difference is still a solid.
+    let failed = !mesh.is_empty() && !mesh.is_closed();
+    (SShell::new(), mesh, failed)
+}
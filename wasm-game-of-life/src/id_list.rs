@@ -0,0 +1,300 @@
+use crate::sketch::{
+    Constraint, EntityBase, EntityId, Group, HConstraint, HEntity, HEquation, HGroup, HParam,
+    HRequest, HStyle, Param, Request,
+};
+
+use std::marker::PhantomData;
+use std::ops::Index;
+
+pub trait Handle: Copy + Ord {
+    fn from_v(v: u32) -> Self;
+    fn v(&self) -> u32;
+}
+
+// Anything kept in an IdList: it knows its own handle, and has a tag for
+// marking things to remove.
+pub trait IdListItem {
+    type H: Handle;
+    fn h(&self) -> Self::H;
+    fn set_h(&mut self, h: Self::H);
+    fn tag(&self) -> i32;
+    fn set_tag(&mut self, tag: i32);
+}
+
+macro_rules! handle {
+    ($($h:ident),*) => {$(
+        impl Handle for $h {
+            fn from_v(v: u32) -> Self {
+                $h { v }
+            }
+
+            fn v(&self) -> u32 {
+                self.v
+            }
+        }
+    )*};
+}
+
+handle!(
+    HGroup,
+    HRequest,
+    HParam,
+    HStyle,
+    HEntity,
+    EntityId,
+    HConstraint,
+    HEquation
+);
+
+macro_rules! id_list_item {
+    ($($t:ident: $h:ident),*) => {$(
+        impl IdListItem for $t {
+            type H = $h;
+
+            fn h(&self) -> $h {
+                self.h
+            }
+
+            fn set_h(&mut self, h: $h) {
+                self.h = h;
+            }
+
+            fn tag(&self) -> i32 {
+                self.tag
+            }
+
+            fn set_tag(&mut self, tag: i32) {
+                self.tag = tag;
+            }
+        }
+    )*};
+}
+
+id_list_item!(
+    Group: HGroup,
+    Request: HRequest,
+    Constraint: HConstraint,
+    EntityBase: HEntity,
+    Param: HParam
+);
+
+// As in SolveSpace, the items are kept sorted by handle, so lookups are a
+// binary search and iterating goes in handle order. Unlike there, a fresh
+// handle is never one that's been used before: the list remembers the
+// highest it has seen, and which handles have been removed, so something
+// still holding a deleted handle finds it stale rather than finding
+// whatever was added after, and nothing can be added under a removed
+// handle again. Something that's about to be made again under the same
+// handle, like a group's entities on regenerating, is take(..)n out
+// instead, which leaves it free to come back.
+//
+// The removed handles are kept as runs of consecutive values, since things
+// are mostly deleted along with their neighbours, like a request's entities
+// and params; so they take room for each gap between runs, not for each
+// handle. Fresh handles stop at the limit, and once they're used up
+// there are no more, rather than one given out twice.
+pub struct IdList<H: Handle, T: IdListItem<H = H>> {
+    elem: Vec<T>,
+    removed: Vec<(u32, u32)>,
+    next: u32,
+    limit: u32,
+    _h: PhantomData<H>,
+}
+
+impl<H: Handle, T: IdListItem<H = H>> IdList<H, T> {
+    pub fn new() -> Self {
+        IdList::with_limit(u32::MAX)
+    }
+
+    // A list whose fresh handles are all below limit, for handles that
+    // others are derived from and so have fewer bits to themselves.
+    pub fn with_limit(limit: u32) -> Self {
+        IdList {
+            elem: Vec::new(),
+            removed: Vec::new(),
+            next: 1,
+            limit,
+            _h: PhantomData,
+        }
+    }
+
+    fn index_of(&self, h: &H) -> Result<usize, usize> {
+        self.elem.binary_search_by(|t| t.h().cmp(h))
+    }
+
+    // Where the run holding v is, or would go.
+    fn run_of(&self, v: u32) -> usize {
+        self.removed.partition_point(|&(_, last)| last < v)
+    }
+
+    fn mark_removed(&mut self, h: &H) {
+        let v = h.v();
+        let i = self.run_of(v);
+        if self.removed.get(i).is_some_and(|&(first, _)| first <= v) {
+            return;
+        }
+        // Neither of these overflows, since the run before ends below v
+        // and the run after starts above it.
+        let after_prev = i > 0 && self.removed[i - 1].1 + 1 == v;
+        let before_next = self
+            .removed
+            .get(i)
+            .is_some_and(|&(first, _)| v + 1 == first);
+        match (after_prev, before_next) {
+            (true, true) => {
+                self.removed[i - 1].1 = self.removed[i].1;
+                self.removed.remove(i);
+            }
+            (true, false) => self.removed[i - 1].1 = v,
+            (false, true) => self.removed[i].0 = v,
+            (false, false) => self.removed.insert(i, (v, v)),
+        }
+    }
+
+    // A handle that's never been in this list, if there are any left.
+    pub fn fresh_handle(&self) -> Option<H> {
+        if self.next < self.limit {
+            Some(H::from_v(self.next))
+        } else {
+            None
+        }
+    }
+
+    // Adds t under its own handle, replacing anything already there.
+    pub fn add(&mut self, t: T) {
+        let h = t.h();
+        if self.is_stale(&h) {
+            panic!("IdList::add(...) of removed handle {}", h.v());
+        }
+        // Saturating is enough: u32::MAX is never below the limit.
+        self.next = self.next.max(h.v().saturating_add(1));
+        match self.index_of(&h) {
+            Ok(i) => self.elem[i] = t,
+            Err(i) => self.elem.insert(i, t),
+        }
+    }
+
+    pub fn add_and_assign_id(&mut self, mut t: T) -> Option<H> {
+        let h = self.fresh_handle()?;
+        t.set_h(h);
+        self.add(t);
+        Some(h)
+    }
+
+    pub fn get(&self, h: &H) -> Option<&T> {
+        self.index_of(h).ok().map(|i| &self.elem[i])
+    }
+
+    pub fn get_mut(&mut self, h: &H) -> Option<&mut T> {
+        match self.index_of(h) {
+            Ok(i) => Some(&mut self.elem[i]),
+            Err(_) => None,
+        }
+    }
+
+    pub fn contains_key(&self, h: &H) -> bool {
+        self.index_of(h).is_ok()
+    }
+
+    // Whether h was in the list once, but has since been removed.
+    pub fn is_stale(&self, h: &H) -> bool {
+        let v = h.v();
+        self.removed
+            .get(self.run_of(v))
+            .is_some_and(|&(first, _)| first <= v)
+    }
+
+    pub fn remove(&mut self, h: &H) -> Option<T> {
+        let i = self.index_of(h).ok()?;
+        self.mark_removed(h);
+        Some(self.elem.remove(i))
+    }
+
+    // Removes the item under h without making h stale, for something that
+    // goes back in, or is made again, under the same handle.
+    pub fn take(&mut self, h: &H) -> Option<T> {
+        let i = self.index_of(h).ok()?;
+        Some(self.elem.remove(i))
+    }
+
+    pub fn clear_tags(&mut self) {
+        for t in self.elem.iter_mut() {
+            t.set_tag(0);
+        }
+    }
+
+    pub fn tag(&mut self, h: &H, tag: i32) {
+        if let Some(t) = self.get_mut(h) {
+            t.set_tag(tag);
+        }
+    }
+
+    // Sweeps out everything with a non-zero tag, returning what went in
+    // handle order.
+    pub fn remove_tagged(&mut self) -> Vec<T> {
+        let (gone, kept) = std::mem::take(&mut self.elem)
+            .into_iter()
+            .partition(|t| t.tag() != 0);
+        self.elem = kept;
+        for t in gone.iter() {
+            self.mark_removed(&t.h());
+        }
+        gone
+    }
+
+    // Empties the list. Handles already given out stay used, but nothing is
+    // stale any more: whatever referred to them went with everything else.
+    pub fn clear(&mut self) {
+        self.elem.clear();
+        self.removed.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.elem.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.elem.is_empty()
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = H> + '_ {
+        self.elem.iter().map(|t| t.h())
+    }
+
+    pub fn values(&self) -> std::slice::Iter<'_, T> {
+        self.elem.iter()
+    }
+
+    pub fn values_mut(&mut self) -> std::slice::IterMut<'_, T> {
+        self.elem.iter_mut()
+    }
+}
+
+impl<H: Handle, T: IdListItem<H = H>> Default for IdList<H, T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<H: Handle, T: IdListItem<H = H> + Clone> Clone for IdList<H, T> {
+    fn clone(&self) -> Self {
+        IdList {
+            elem: self.elem.clone(),
+            removed: self.removed.clone(),
+            next: self.next,
+            limit: self.limit,
+            _h: PhantomData,
+        }
+    }
+}
+
+impl<H: Handle, T: IdListItem<H = H>> Index<&H> for IdList<H, T> {
+    type Output = T;
+
+    fn index(&self, h: &H) -> &T {
+        match self.get(h) {
+            Some(t) => t,
+            None => panic!("IdList::index(...) of unknown handle {}", h.v()),
+        }
+    }
+}
@@ -1,79 +1,38 @@
+#[cfg(feature = "app")]
 mod app;
+mod bsp;
+#[cfg(feature = "app")]
 mod component_designer;
-mod dsc;
-mod polygon;
-mod sketch;
-mod system;
+mod configuration;
+mod constraint;
+pub mod delete;
+pub mod dsc;
+mod entity;
+pub mod expr;
+pub mod ffi;
+mod generate;
+mod group;
+mod group_mesh;
+pub mod id_list;
+pub mod polygon;
+mod request;
+pub mod sketch;
+pub mod slvs;
+mod sparse;
+pub mod step_dimension;
+pub mod surface;
+pub mod system;
+mod trace;
 mod utils;
+mod variable;
+#[cfg(feature = "app")]
+mod wasm;
 
-use app::{App, AppView};
-
-use js_sys::Function;
-use sodium_rust::Listener;
-use std::sync::Arc;
-use std::sync::Mutex;
-use wasm_bindgen::prelude::*;
+#[cfg(feature = "app")]
+pub use wasm::*;
 
 // When the `wee_alloc` feature is enabled, use `wee_alloc` as the global
 // allocator.
 #[cfg(feature = "wee_alloc")]
 #[global_allocator]
 static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
-
-#[wasm_bindgen]
-pub fn greet() -> String {
-    "Hello, wasm-game-of-life!".into()
-}
-
-pub struct UnsafeSendSync<A> {
-    pub value: A,
-}
-
-unsafe impl<A> Send for UnsafeSendSync<A> {}
-unsafe impl<A> Sync for UnsafeSendSync<A> {}
-
-#[wasm_bindgen]
-pub fn new_app() -> *mut App {
-    Box::into_raw(Box::new(App::new()))
-}
-
-#[wasm_bindgen]
-pub fn drop_app(app: *mut App) {
-    unsafe {
-        Box::from_raw(app);
-    };
-}
-
-#[wasm_bindgen]
-pub fn app_c_current_view_listen(app: *mut App, callback: Function) -> *mut Listener {
-    let app = unsafe { &mut *app };
-    let callback = UnsafeSendSync { value: callback };
-    let listener = app.c_current_view.listen_weak(move |app_view: &AppView| {
-        match app_view {
-            AppView::LogInOrRegister => {
-                callback
-                    .value
-                    .call1(&JsValue::UNDEFINED, &JsValue::from_f64(1f64));
-            }
-            AppView::ComponentDesigner(_) => {
-                callback
-                    .value
-                    .call1(&JsValue::UNDEFINED, &JsValue::from_f64(2f64));
-            }
-        };
-    });
-    Box::into_raw(Box::new(listener))
-}
-
-#[wasm_bindgen]
-pub fn app_log_in(app: *mut App) {
-    let app = unsafe { &mut *app };
-    app.log_in();
-}
-
-#[wasm_bindgen]
-pub fn drop_listener(listener: *mut Listener) {
-    unsafe {
-        Box::from_raw(listener);
-    };
-}
@@ -1,25 +1,476 @@
+use crate::dsc::{RgbaColor, Vector, LENGTH_EPS};
+
+#[derive(Copy, Clone, PartialEq)]
 enum EarType {
     Unknown,
     NotEar,
     Ear,
 }
 
-enum BspClass {
+#[derive(Copy, Clone, PartialEq)]
+pub enum BspClass {
     Pos,
     Neg,
     Coplaner,
 }
 
-enum EdgeKind {
-    NakedOrSelfInter,
-    SelfInter,
-    Turning,
-    Emphasized,
-    Sharp,
+pub struct SEdge {
+    pub tag: i32,
+    pub aux_a: i32,
+    pub aux_b: i32,
 }
 
-pub struct SEdge {
+// A closed loop of points; the last one joins back to the first.
+#[derive(Clone)]
+pub struct SContour {
     pub tag: i32,
-    pub auxA: i32,
-    pub auxB: i32,
+    pub l: Vec<Vector>,
+}
+
+// Planar loops about a normal: outer loops go anticlockwise and holes
+// clockwise, seen from in front.
+#[derive(Clone, Default)]
+pub struct SPolygon {
+    pub l: Vec<SContour>,
+    pub normal: Vector,
+}
+
+// Which face a triangle is part of, as the handle of that face's entity,
+// and what colour it's drawn.
+#[derive(Copy, Clone)]
+pub struct STriMeta {
+    pub face: u32,
+    pub color: RgbaColor,
+}
+
+// Wound anticlockwise about its outward normal.
+#[derive(Copy, Clone)]
+pub struct STriangle {
+    pub tag: i32,
+    pub meta: STriMeta,
+    pub a: Vector,
+    pub b: Vector,
+    pub c: Vector,
+}
+
+#[derive(Clone, Default)]
+pub struct SMesh {
+    pub l: Vec<STriangle>,
+}
+
+// An edge worth drawing, with the normals of the faces either side; the
+// same for a naked edge.
+#[derive(Copy, Clone)]
+pub struct SOutline {
+    pub tag: i32,
+    pub a: Vector,
+    pub b: Vector,
+    pub nl: Vector,
+    pub nr: Vector,
+}
+
+#[derive(Clone, Default)]
+pub struct SOutlineList {
+    pub l: Vec<SOutline>,
+}
+
+impl SContour {
+    // Twice the area enclosed, positive if it goes anticlockwise about n.
+    pub fn signed_area_2x(&self, n: Vector) -> f64 {
+        let mut a = Vector::new(0.0, 0.0, 0.0);
+        for i in 0..self.l.len() {
+            a = a + self.l[i].cross(self.l[(i + 1) % self.l.len()]);
+        }
+        a.dot(n)
+    }
+
+    pub fn reverse(&mut self) {
+        self.l.reverse();
+    }
+
+    // Whether p is inside, looking along n.
+    pub fn contains_point_projd_to_normal(&self, n: Vector, p: Vector) -> bool {
+        let (u, v) = (n.normal(0), n.normal(1));
+        let (x, y) = (p.dot(u), p.dot(v));
+        let mut inside = false;
+        for i in 0..self.l.len() {
+            let (a, b) = (self.l[i], self.l[(i + 1) % self.l.len()]);
+            let (ax, ay, bx, by) = (a.dot(u), a.dot(v), b.dot(u), b.dot(v));
+            if (ay > y) != (by > y) && ax + (y - ay) * (bx - ax) / (by - ay) > x {
+                inside = !inside;
+            }
+        }
+        inside
+    }
+}
+
+impl SPolygon {
+    pub fn new() -> Self {
+        SPolygon {
+            l: Vec::new(),
+            normal: Vector::new(0.0, 0.0, 0.0),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.l.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.l.iter().all(|c| c.l.len() < 3)
+    }
+
+    pub fn add_empty_contour(&mut self) {
+        self.l.push(SContour {
+            tag: 0,
+            l: Vec::new(),
+        });
+    }
+
+    pub fn add_point(&mut self, p: Vector) {
+        match self.l.last_mut() {
+            Some(c) => c.l.push(p),
+            None => panic!("SPolygon::add_point(...) with no contour"),
+        }
+    }
+
+    // Breaks the polygon into triangles, holes and all: each hole is joined
+    // to its outer loop by a bridge so that what's left is one loop, which
+    // is then clipped an ear at a time.
+    pub fn triangulate_into(&self, m: &mut SMesh, meta: STriMeta) {
+        let n = self.normal;
+        if n.mag_squared() < LENGTH_EPS * LENGTH_EPS {
+            return;
+        }
+        let (u, v) = (n.normal(0), n.normal(1));
+
+        let mut pts: Vec<(Vector, f64, f64)> = Vec::new();
+        let mut outer: Vec<(f64, Vec<usize>)> = Vec::new();
+        let mut holes: Vec<Vec<usize>> = Vec::new();
+        for c in self.l.iter().filter(|c| c.l.len() >= 3) {
+            let area = c.signed_area_2x(n);
+            if area.abs() < LENGTH_EPS * LENGTH_EPS {
+                continue;
+            }
+            let start = pts.len();
+            pts.extend(c.l.iter().map(|p| (*p, p.dot(u), p.dot(v))));
+            let idx: Vec<usize> = (start..pts.len()).collect();
+            if area > 0.0 {
+                outer.push((area, idx));
+            } else {
+                holes.push(idx);
+            }
+        }
+
+        // Each hole goes with the smallest outer loop around it.
+        let mut assigned: Vec<Vec<Vec<usize>>> = vec![Vec::new(); outer.len()];
+        for hole in holes {
+            let (x, y) = (pts[hole[0]].1, pts[hole[0]].2);
+            let around = outer
+                .iter()
+                .enumerate()
+                .filter(|(_, (_, o))| contains_2d(&pts, o, x, y))
+                .min_by(|a, b| (a.1).0.partial_cmp(&(b.1).0).unwrap());
+            if let Some((i, _)) = around {
+                assigned[i].push(hole);
+            }
+        }
+
+        for ((_, o), hs) in outer.into_iter().zip(assigned) {
+            let merged = bridge_holes(&pts, o, hs);
+            clip_ears(&pts, merged, m, meta);
+        }
+    }
+}
+
+fn cross_2d(pts: &[(Vector, f64, f64)], a: usize, b: usize, c: usize) -> f64 {
+    let (ax, ay) = (pts[a].1, pts[a].2);
+    (pts[b].1 - ax) * (pts[c].2 - ay) - (pts[b].2 - ay) * (pts[c].1 - ax)
+}
+
+fn contains_2d(pts: &[(Vector, f64, f64)], l: &[usize], x: f64, y: f64) -> bool {
+    let mut inside = false;
+    for i in 0..l.len() {
+        let (a, b) = (&pts[l[i]], &pts[l[(i + 1) % l.len()]]);
+        if (a.2 > y) != (b.2 > y) && x < a.1 + (y - a.2) * (b.1 - a.1) / (b.2 - a.2) {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+// Whether segments pq and rs cross somewhere other than their ends.
+fn segments_cross(p: (f64, f64), q: (f64, f64), r: (f64, f64), s: (f64, f64)) -> bool {
+    let side = |a: (f64, f64), b: (f64, f64), c: (f64, f64)| {
+        (b.0 - a.0) * (c.1 - a.1) - (b.1 - a.1) * (c.0 - a.0)
+    };
+    let eps = LENGTH_EPS * LENGTH_EPS;
+    let (d1, d2) = (side(r, s, p), side(r, s, q));
+    let (d3, d4) = (side(p, q, r), side(p, q, s));
+    ((d1 > eps && d2 < -eps) || (d1 < -eps && d2 > eps))
+        && ((d3 > eps && d4 < -eps) || (d3 < -eps && d4 > eps))
+}
+
+// Joins the holes into the outer loop, the one reaching furthest in +u
+// first, each by a bridge from that furthest point to the nearest point of
+// the loop so far that it can see.
+fn bridge_holes(
+    pts: &[(Vector, f64, f64)],
+    mut l: Vec<usize>,
+    mut holes: Vec<Vec<usize>>,
+) -> Vec<usize> {
+    let max_u = |h: &Vec<usize>| {
+        h.iter()
+            .cloned()
+            .max_by(|a, b| pts[*a].1.partial_cmp(&pts[*b].1).unwrap())
+            .unwrap()
+    };
+    holes.sort_by(|a, b| pts[max_u(b)].1.partial_cmp(&pts[max_u(a)].1).unwrap());
+
+    for (k, hole) in holes.iter().enumerate() {
+        let hm = max_u(hole);
+        let m = hole.iter().position(|i| *i == hm).unwrap();
+        let p = (pts[hm].1, pts[hm].2);
+
+        let visible = |j: usize| {
+            let q = (pts[l[j]].1, pts[l[j]].2);
+            let edges = (0..l.len()).map(|i| (l[i], l[(i + 1) % l.len()])).chain(
+                holes[k..]
+                    .iter()
+                    .flat_map(|h| (0..h.len()).map(move |i| (h[i], h[(i + 1) % h.len()]))),
+            );
+            for (a, b) in edges {
+                let (ra, rb) = ((pts[a].1, pts[a].2), (pts[b].1, pts[b].2));
+                if segments_cross(p, q, ra, rb) {
+                    return false;
+                }
+            }
+            true
+        };
+        let dist = |j: usize| (pts[l[j]].1 - p.0).powi(2) + (pts[l[j]].2 - p.1).powi(2);
+        let mut order: Vec<usize> = (0..l.len()).collect();
+        order.sort_by(|a, b| dist(*a).partial_cmp(&dist(*b)).unwrap());
+        let j = match order.into_iter().find(|j| visible(*j)) {
+            Some(j) => j,
+            None => continue,
+        };
+
+        let mut merged = l[..=j].to_vec();
+        merged.extend(hole[m..].iter());
+        merged.extend(hole[..=m].iter());
+        merged.extend(l[j..].iter());
+        l = merged;
+    }
+    l
+}
+
+fn clip_ears(pts: &[(Vector, f64, f64)], mut l: Vec<usize>, m: &mut SMesh, meta: STriMeta) {
+    let eps = LENGTH_EPS * LENGTH_EPS;
+    let same = |a: usize, b: usize| pts[a].0.equals_with_def_tol(pts[b].0);
+    let is_ear = |l: &[usize], i: usize| {
+        let n = l.len();
+        let (a, b, c) = (l[(i + n - 1) % n], l[i], l[(i + 1) % n]);
+        if cross_2d(pts, a, b, c) <= eps {
+            return false;
+        }
+        // Nothing else may be in it, counting its edges; the far ends of
+        // a bridge are the same points again, and those don't count.
+        !l.iter().any(|&p| {
+            !same(p, a)
+                && !same(p, b)
+                && !same(p, c)
+                && cross_2d(pts, a, b, p) >= -eps
+                && cross_2d(pts, b, c, p) >= -eps
+                && cross_2d(pts, c, a, p) >= -eps
+        })
+    };
+
+    let mut ear = vec![EarType::Unknown; l.len()];
+    while l.len() > 3 {
+        let n = l.len();
+        let mut found = None;
+        for (i, e) in ear.iter_mut().enumerate() {
+            if *e == EarType::Unknown {
+                *e = if is_ear(&l, i) {
+                    EarType::Ear
+                } else {
+                    EarType::NotEar
+                };
+            }
+            if *e == EarType::Ear {
+                found = Some(i);
+                break;
+            }
+        }
+        // No ear at all means the loop is degenerate somewhere, so drop
+        // whichever point is closest to being in line with its neighbours.
+        let i = match found {
+            Some(i) => {
+                let (a, b, c) = (l[(i + n - 1) % n], l[i], l[(i + 1) % n]);
+                m.add_triangle(meta, pts[a].0, pts[b].0, pts[c].0);
+                i
+            }
+            None => (0..n)
+                .min_by(|x, y| {
+                    let area = |i: usize| cross_2d(pts, l[(i + n - 1) % n], l[i], l[(i + 1) % n]);
+                    area(*x).abs().partial_cmp(&area(*y).abs()).unwrap()
+                })
+                .unwrap(),
+        };
+        l.remove(i);
+        ear.remove(i);
+        let n = l.len();
+        ear[(i + n - 1) % n] = EarType::Unknown;
+        ear[i % n] = EarType::Unknown;
+    }
+    if cross_2d(pts, l[0], l[1], l[2]) > eps {
+        m.add_triangle(meta, pts[l[0]].0, pts[l[1]].0, pts[l[2]].0);
+    }
+}
+
+impl STriangle {
+    pub fn normal(&self) -> Vector {
+        (self.b - self.a).cross(self.c - self.b)
+    }
+
+    pub fn area(&self) -> f64 {
+        self.normal().magnitude() / 2.0
+    }
+
+    pub fn flip(&mut self) {
+        std::mem::swap(&mut self.a, &mut self.b);
+    }
+}
+
+impl SMesh {
+    pub fn new() -> Self {
+        SMesh { l: Vec::new() }
+    }
+
+    pub fn clear(&mut self) {
+        self.l.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.l.is_empty()
+    }
+
+    // Leaves out triangles with no area, which have no normal either.
+    pub fn add_triangle(&mut self, meta: STriMeta, a: Vector, b: Vector, c: Vector) {
+        let t = STriangle {
+            tag: 0,
+            meta,
+            a,
+            b,
+            c,
+        };
+        if t.area() > LENGTH_EPS * LENGTH_EPS {
+            self.l.push(t);
+        }
+    }
+
+    // Both meshes as they are, overlapping or not.
+    pub fn make_from_assembly_of(a: &SMesh, b: &SMesh) -> SMesh {
+        let mut m = a.clone();
+        m.l.extend(b.l.iter().cloned());
+        m
+    }
+
+    // The volume enclosed, which only means anything for a closed mesh.
+    pub fn calculate_volume(&self) -> f64 {
+        self.l.iter().map(|t| t.a.dot(t.b.cross(t.c)) / 6.0).sum()
+    }
+
+    // Whether every edge has a face on its other side, so the mesh encloses
+    // a solid.
+    pub fn is_closed(&self) -> bool {
+        let mut sol = SOutlineList::new();
+        self.make_outlines_into(&mut sol);
+        // Where there's no face on the other side, both normals are the
+        // one face's own.
+        sol.l.iter().all(|o| !o.nl.equals_with_def_tol(o.nr))
+    }
+
+    // Every edge where the surface isn't smooth: where two faces meet at
+    // an angle, and where there's no face on the other side at all. An edge
+    // may be split differently on its two sides, so what's on the other
+    // side is worked out along its length.
+    pub fn make_outlines_into(&self, sol: &mut SOutlineList) {
+        let smooth = |a: Vector, b: Vector| a.direction_cosine_with(b) > 1.0 - 1e-6;
+        for (ti, t) in self.l.iter().enumerate() {
+            let nt = t.normal();
+            for (a, b) in [(t.a, t.b), (t.b, t.c), (t.c, t.a)].iter().cloned() {
+                let d = b - a;
+                let len2 = d.mag_squared();
+                let along = |p: Vector| (p - a).dot(d) / len2;
+
+                // Where along ab the other side is covered, and by what.
+                let mut cover: Vec<(f64, f64, Vector)> = Vec::new();
+                for (oi, o) in self.l.iter().enumerate() {
+                    if oi == ti {
+                        continue;
+                    }
+                    for (c, e) in [(o.a, o.b), (o.b, o.c), (o.c, o.a)].iter().cloned() {
+                        if (e - c).dot(d) >= 0.0
+                            || c.distance_to_line(a, d) > LENGTH_EPS
+                            || e.distance_to_line(a, d) > LENGTH_EPS
+                        {
+                            continue;
+                        }
+                        let (s0, s1) = (along(e).max(0.0), along(c).min(1.0));
+                        if s1 > s0 {
+                            cover.push((s0, s1, o.normal()));
+                        }
+                    }
+                }
+
+                let mut cuts: Vec<f64> = vec![0.0, 1.0];
+                for (s0, s1, _) in cover.iter() {
+                    cuts.push(*s0);
+                    cuts.push(*s1);
+                }
+                cuts.sort_by(|x, y| x.partial_cmp(y).unwrap());
+                cuts.dedup_by(|x, y| (*x - *y).abs() < 1e-9);
+                for w in cuts.windows(2) {
+                    let mid = (w[0] + w[1]) / 2.0;
+                    let other = cover.iter().find(|(s0, s1, _)| *s0 <= mid && mid <= *s1);
+                    let nr = match other {
+                        Some((_, _, n)) if smooth(nt, *n) => continue,
+                        // Drawn once, from the side it goes forwards on.
+                        Some(_) if !forwards(d) => continue,
+                        Some((_, _, n)) => *n,
+                        None => nt,
+                    };
+                    sol.l.push(SOutline {
+                        tag: 0,
+                        a: a + d * w[0],
+                        b: a + d * w[1],
+                        nl: nt.with_magnitude(1.0),
+                        nr: nr.with_magnitude(1.0),
+                    });
+                }
+            }
+        }
+    }
+}
+
+// Whether d points the positive way along the first axis it has any of,
+// so that of an edge and its reverse just one is forwards.
+fn forwards(d: Vector) -> bool {
+    for x in [d.x, d.y, d.z].iter() {
+        if x.abs() > LENGTH_EPS {
+            return *x > 0.0;
+        }
+    }
+    false
+}
+
+impl SOutlineList {
+    pub fn new() -> Self {
+        SOutlineList { l: Vec::new() }
+    }
+
+    pub fn clear(&mut self) {
+        self.l.clear();
+    }
 }
@@ -0,0 +1,268 @@
+use crate::dsc::Vector;
+use crate::id_list::IdList;
+use crate::sketch::{
+    EntityBase, EntityBaseType, HEntity, HGroup, HParam, HRequest, HStyle, Param, Request,
+    RequestType, Sketch, SketchError, FREE_IN_3D, MAX_POINTS_IN_ENTITY, NO_PARAM,
+};
+
+// What each type of request makes, as in SolveSpace's EntReqTable: the
+// entity itself (if any), how many points, and whether it has a normal or a
+// distance. Points that depend on extra_points get those added on.
+struct RequestInfo {
+    entity: Option<EntityBaseType>,
+    points: usize,
+    extra: bool,
+    normal: bool,
+    distance: bool,
+}
+
+fn request_info(type_: RequestType) -> RequestInfo {
+    let (entity, points, extra, normal, distance) = match type_ {
+        RequestType::Workplane => (Some(EntityBaseType::Workplane), 1, false, true, false),
+        RequestType::DatumPoint => (None, 1, false, false, false),
+        RequestType::DatumNormal => (None, 1, false, true, false),
+        RequestType::LineSegment => (Some(EntityBaseType::LineSegment), 2, false, false, false),
+        RequestType::Cubic => (Some(EntityBaseType::Cubic), 4, true, false, false),
+        RequestType::CubicPeriodic => (Some(EntityBaseType::CubicPeriodic), 3, true, false, false),
+        RequestType::Circle => (Some(EntityBaseType::Circle), 1, false, true, true),
+        RequestType::ArcOfCircle => (Some(EntityBaseType::ArcOfCircle), 3, false, true, false),
+        RequestType::TtfText => (Some(EntityBaseType::TtfText), 4, false, true, false),
+        RequestType::Image => (Some(EntityBaseType::Image), 4, false, true, false),
+    };
+    RequestInfo {
+        entity,
+        points,
+        extra,
+        normal,
+        distance,
+    }
+}
+
+// Params keep their values across regenerations; only new ones start at 0.
+fn add_param(param: &mut IdList<HParam, Param>, h: HParam) -> HParam {
+    if !param.contains_key(&h) {
+        param.add(Param {
+            tag: 0,
+            h,
+            val: 0.0,
+            known: false,
+            free: false,
+            substd: NO_PARAM,
+        });
+    }
+    h
+}
+
+impl RequestType {
+    pub fn from_u32(v: u32) -> Option<Self> {
+        let types = [
+            RequestType::Workplane,
+            RequestType::DatumPoint,
+            RequestType::DatumNormal,
+            RequestType::LineSegment,
+            RequestType::Cubic,
+            RequestType::CubicPeriodic,
+            RequestType::Circle,
+            RequestType::ArcOfCircle,
+            RequestType::TtfText,
+            RequestType::Image,
+        ];
+        types.iter().cloned().find(|t| *t as u32 == v)
+    }
+}
+
+impl Request {
+    pub fn new(type_: RequestType, group: HGroup, workplane: HEntity) -> Self {
+        Request {
+            tag: 0,
+            h: HRequest { v: 0 },
+            type_,
+            extra_points: 0,
+            workplane,
+            group,
+            style: HStyle { v: 0 },
+            construction: false,
+            str_: String::new(),
+            font: String::new(),
+            file: String::new(),
+            aspect_ratio: 0.0,
+        }
+    }
+
+    // How many extra_points this type of request can take, before its
+    // points wouldn't fit in one entity; 0 if it doesn't use them.
+    pub fn max_extra_points(&self) -> usize {
+        let info = request_info(self.type_);
+        if info.extra {
+            MAX_POINTS_IN_ENTITY - info.points
+        } else {
+            0
+        }
+    }
+
+    // The entities and params this request stands for. Their handles are
+    // derived from the request's, so generating again gives the same ones:
+    // the entity itself is 0, points from 1 (from 0 if there's no entity),
+    // the normal 32 and the distance 64; each point's params start at
+    // 16 + 3i, the normal's at 32 and the distance's at 64.
+    pub fn generate(
+        &self,
+        entity: &mut IdList<HEntity, EntityBase>,
+        param: &mut IdList<HParam, Param>,
+    ) {
+        let info = request_info(self.type_);
+        let points = info.points + if info.extra { self.extra_points } else { 0 };
+        if points > MAX_POINTS_IN_ENTITY {
+            panic!(
+                "Request::generate(...) of request {} with {} points",
+                self.h.v, points
+            );
+        }
+        let first_point = if info.entity.is_some() { 1 } else { 0 };
+
+        let mut e = EntityBase::new(
+            self.h.entity(0),
+            info.entity.unwrap_or(EntityBaseType::PointIn3D),
+        );
+        e.group = self.group;
+        e.workplane = self.workplane;
+        e.construction = self.construction;
+        e.extra_points = if info.extra { self.extra_points } else { 0 };
+
+        for i in 0..points {
+            let type_ = if self.workplane == FREE_IN_3D {
+                EntityBaseType::PointIn3D
+            } else {
+                EntityBaseType::PointIn2D
+            };
+            let mut p = EntityBase::new(self.h.entity((i + first_point) as u32), type_);
+            p.group = self.group;
+            p.workplane = self.workplane;
+            p.construction = self.construction;
+            let coords = if type_ == EntityBaseType::PointIn3D {
+                3
+            } else {
+                2
+            };
+            for k in 0..coords {
+                p.param[k] = add_param(param, self.h.param((16 + 3 * i + k) as u32));
+            }
+            e.point[i] = p.h;
+            entity.add(p);
+        }
+
+        if info.normal {
+            let mut n = EntityBase::new(self.h.entity(32), EntityBaseType::NormalIn3D);
+            n.group = self.group;
+            n.workplane = self.workplane;
+            if self.workplane == FREE_IN_3D {
+                // A new normal starts out as the identity rotation.
+                let fresh = !param.contains_key(&self.h.param(32));
+                for k in 0..4 {
+                    n.param[k] = add_param(param, self.h.param(32 + k as u32));
+                }
+                if fresh {
+                    param.get_mut(&n.param[0]).unwrap().val = 1.0;
+                }
+            } else {
+                // Just the workplane's normal, so no params of its own.
+                n.type_ = EntityBaseType::NormalIn2D;
+            }
+            // Only says where the normal is drawn.
+            n.point[0] = e.point[0];
+            e.normal = n.h;
+            entity.add(n);
+        }
+
+        if info.distance {
+            let mut d = EntityBase::new(self.h.entity(64), EntityBaseType::Distance);
+            d.group = self.group;
+            d.workplane = self.workplane;
+            d.param[0] = add_param(param, self.h.param(64));
+            e.distance = d.h;
+            entity.add(d);
+        }
+
+        if info.entity.is_some() {
+            entity.add(e);
+        }
+    }
+}
+
+impl Sketch {
+    // Adds the request under a new handle and generates what it makes.
+    pub fn add_request(&mut self, r: Request) -> Result<HRequest, SketchError> {
+        if request_info(r.type_).extra && r.extra_points > r.max_extra_points() {
+            return Err(SketchError::TooManyPoints(r.max_extra_points()));
+        }
+        self.find_group(r.group)?;
+        if r.workplane != FREE_IN_3D
+            && self.find_entity(r.workplane)?.type_ != EntityBaseType::Workplane
+        {
+            return Err(SketchError::NotAWorkplane(r.workplane.v));
+        }
+        let hg = r.group;
+        let hr = self
+            .request
+            .add_and_assign_id(r)
+            .ok_or(SketchError::OutOfHandles)?;
+        self.generate_request(hr);
+        self.mark_group_dirty(hg);
+        Ok(hr)
+    }
+
+    pub fn generate_request(&mut self, hr: HRequest) {
+        let r = match self.request.get(&hr) {
+            Some(r) => r,
+            None => panic!("Sketch::generate_request(...) of unknown request {}", hr.v),
+        };
+        r.generate(&mut self.entity, &mut self.param);
+    }
+
+    // Only the requests in groups from that order on; the ones before keep
+    // the entities they made.
+    pub fn generate_requests(&mut self, from_order: i32) {
+        let hrs: Vec<HRequest> = self
+            .request
+            .values()
+            .filter(|r| {
+                self.group
+                    .get(&r.group)
+                    .is_none_or(|g| g.order >= from_order)
+            })
+            .map(|r| r.h)
+            .collect();
+        for hr in hrs {
+            self.generate_request(hr);
+        }
+    }
+
+    // Moves a point by setting its params; one in a workplane goes to
+    // wherever p projects onto that workplane.
+    pub fn point_force_to(&mut self, h: HEntity, p: Vector) {
+        let e = self.get_entity(h);
+        let (hps, vals) = match e.type_ {
+            EntityBaseType::PointIn3D => (e.param, vec![p.x, p.y, p.z]),
+            EntityBaseType::PointIn2D => {
+                let wp = self.get_entity(e.workplane);
+                let n = wp.normal_entity(self);
+                let o = wp.workplane_get_offset_exprs(self).eval(&self.param);
+                let u = n.normal_exprs_u(self).eval(&self.param);
+                let v = n.normal_exprs_v(self).eval(&self.param);
+                (e.param, vec![(p - o).dot(u), (p - o).dot(v)])
+            }
+            _ => panic!(
+                "Sketch::point_force_to(...) of entity {}, not a free point",
+                h.v
+            ),
+        };
+        let hg = e.group;
+        for (hp, val) in hps.iter().zip(vals) {
+            match self.param.get_mut(hp) {
+                Some(param) => param.val = val,
+                None => panic!("Sketch::point_force_to(...) of unknown param {}", hp.v),
+            }
+        }
+        self.mark_group_dirty(hg);
+    }
+}
@@ -1,5 +1,12 @@
-use crate::dsc::{Quaternion, RgbaColor};
+use crate::dsc::{Quaternion, RgbaColor, Vector};
+use crate::expr::{Expr, ExprError};
+use crate::id_list::IdList;
+use crate::polygon::{SEdge, SMesh, SOutlineList, SPolygon};
+use crate::surface::{SBezierLoopSet, SBezierLoopSetSet, SShell};
+use crate::system::{CancelToken, SolveResult};
+use crate::utils::PlatformPath;
 use std::collections::HashMap;
+use std::fmt;
 
 #[derive(Copy, Clone)]
 pub enum PolyError {
@@ -22,8 +29,6 @@ pub enum StripplePattern {
     Zigzag = 8,
 }
 
-const STRIPPLE_PATTERN_LAST: StripplePattern = StripplePattern::Zigzag;
-
 #[derive(Copy, Clone, Hash, Ord, PartialOrd, Eq, PartialEq)]
 pub struct HGroup {
     pub v: u32,
@@ -49,6 +54,139 @@ pub struct EntityId {
     pub v: u32,
 }
 
+pub const NO_PARAM: HParam = HParam { v: 0 };
+
+#[derive(Copy, Clone, Hash, Ord, PartialOrd, Eq, PartialEq)]
+pub struct HConstraint {
+    pub v: u32,
+}
+
+#[derive(Copy, Clone, Hash, Ord, PartialOrd, Eq, PartialEq)]
+pub struct HEquation {
+    pub v: u32,
+}
+
+// A derived handle keeps the handle it's derived from above bit 16, under
+// the two tag bits, so that's 14 bits for a constraint, for a request's
+// params, since they share bit 30 with a constraint's, or for the request
+// or group of an entity that has an equation; 15 for a request's or a
+// group's entities, and a group's params and equations.
+impl HConstraint {
+    pub fn equation(&self, i: u32) -> HEquation {
+        debug_assert!(
+            self.v < 1 << 14 && i < 1 << 16,
+            "HConstraint::equation(...) of out of range {}.{}",
+            self.v,
+            i
+        );
+        HEquation {
+            v: (self.v << 16) | i,
+        }
+    }
+
+    pub fn param(&self, i: u32) -> HParam {
+        debug_assert!(
+            self.v < 1 << 14 && i < 1 << 16,
+            "HConstraint::param(...) of out of range {}.{}",
+            self.v,
+            i
+        );
+        HParam {
+            v: 0x40000000 | (self.v << 16) | i,
+        }
+    }
+}
+
+impl HGroup {
+    pub fn entity(&self, i: u32) -> HEntity {
+        debug_assert!(
+            self.v < 1 << 15 && i < 1 << 16,
+            "HGroup::entity(...) of out of range {}.{}",
+            self.v,
+            i
+        );
+        HEntity {
+            v: 0x80000000 | (self.v << 16) | i,
+        }
+    }
+
+    pub fn param(&self, i: u32) -> HParam {
+        debug_assert!(
+            self.v < 1 << 15 && i < 1 << 16,
+            "HGroup::param(...) of out of range {}.{}",
+            self.v,
+            i
+        );
+        HParam {
+            v: 0x80000000 | (self.v << 16) | i,
+        }
+    }
+
+    pub fn equation(&self, i: u32) -> HEquation {
+        debug_assert!(
+            self.v < 1 << 15 && i < 1 << 16,
+            "HGroup::equation(...) of out of range {}.{}",
+            self.v,
+            i
+        );
+        HEquation {
+            v: 0x80000000 | (self.v << 16) | i,
+        }
+    }
+}
+
+impl HRequest {
+    pub fn entity(&self, i: u32) -> HEntity {
+        debug_assert!(
+            self.v < 1 << 15 && i < 1 << 16,
+            "HRequest::entity(...) of out of range {}.{}",
+            self.v,
+            i
+        );
+        HEntity {
+            v: (self.v << 16) | i,
+        }
+    }
+
+    pub fn param(&self, i: u32) -> HParam {
+        debug_assert!(
+            self.v < 1 << 14 && i < 1 << 16,
+            "HRequest::param(...) of out of range {}.{}",
+            self.v,
+            i
+        );
+        HParam {
+            v: (self.v << 16) | i,
+        }
+    }
+}
+
+impl HParam {
+    pub fn request(&self) -> HRequest {
+        HRequest { v: self.v >> 16 }
+    }
+
+    pub fn is_from_group(&self) -> bool {
+        self.v & 0x80000000 != 0
+    }
+
+    pub fn group(&self) -> HGroup {
+        HGroup {
+            v: (self.v >> 16) & 0x7fff,
+        }
+    }
+}
+
+impl HEquation {
+    pub fn is_from_constraint(&self) -> bool {
+        self.v & 0xc0000000 == 0
+    }
+
+    pub fn constraint(&self) -> HConstraint {
+        HConstraint { v: self.v >> 16 }
+    }
+}
+
 #[derive(Copy, Clone, Hash, PartialOrd, PartialEq, Ord, Eq)]
 pub struct EntityKey {
     pub input: HEntity,
@@ -56,6 +194,7 @@ pub struct EntityKey {
 }
 
 pub type EntityMap = HashMap<EntityKey, EntityId>;
+pub type EntityList = IdList<HEntity, EntityBase>;
 
 pub enum GroupCopyAs {
     Numeric,
@@ -65,6 +204,7 @@ pub enum GroupCopyAs {
     NRotAxisTrans,
 }
 
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
 pub enum GroupType {
     Drawing3D = 5000,
     DrawingWorkplane = 5001,
@@ -83,8 +223,27 @@ pub struct GroupSolved {
     pub find_to_fix_timeout: i32,
     pub timeout: bool,
     pub remove: Vec<HConstraint>,
+    pub residual: Vec<(HConstraint, f64)>,
+}
+
+// How long, in ms, to spend looking for constraints to remove from a group
+// that's over-constrained, as SolveSpace's timeoutRedundantConstr.
+pub const FIND_TO_FIX_TIMEOUT: i32 = 1000;
+
+impl Default for GroupSolved {
+    fn default() -> Self {
+        GroupSolved {
+            how: SolveResult::Okay,
+            dof: 0,
+            find_to_fix_timeout: FIND_TO_FIX_TIMEOUT,
+            timeout: false,
+            remove: Vec::new(),
+            residual: Vec::new(),
+        }
+    }
 }
 
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
 pub enum GroupSubtype {
     WorkplaneByPointOrtho = 6000,
     WorkplaneByLineSegments = 6001,
@@ -105,9 +264,10 @@ pub struct GroupPredef {
 pub struct GroupPolyError {
     pub how: PolyError,
     pub not_closed_at: SEdge,
-    error_point_at: Vector,
+    pub error_point_at: Vector,
 }
 
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
 pub enum GroupCombineAs {
     Union = 0,
     Difference = 1,
@@ -115,6 +275,7 @@ pub enum GroupCombineAs {
     Intersection = 3,
 }
 
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
 pub enum GroupRemap {
     Last = 1000,
     Top = 1001,
@@ -138,7 +299,7 @@ pub struct Group {
     pub op_b: HGroup,
     pub visible: bool,
     pub suppress: bool,
-    pub relaxConstraints: bool,
+    pub relax_constraints: bool,
     pub allow_redundant: bool,
     pub all_dims_reference: bool,
     pub scale: f64,
@@ -162,8 +323,12 @@ pub struct Group {
     pub bezier_opens: SBezierLoopSet,
     pub poly_error: GroupPolyError,
 
+    // The Union, Difference or Intersection with the solid before didn't
+    // give a closed solid, so running_mesh is whatever it did give.
     pub boolean_failed: bool,
 
+    // Only kept until two solids are cut into each other; from then on the
+    // running solid is just running_mesh, see Group::is_mesh_only().
     pub this_shell: SShell,
     pub running_shell: SShell,
 
@@ -193,6 +358,116 @@ pub struct HEntity {
     pub v: u32,
 }
 
+pub const NO_ENTITY: HEntity = HEntity { v: 0 };
+pub const FREE_IN_3D: HEntity = HEntity { v: 0 };
+
+impl HEntity {
+    pub fn equation(&self) -> HEquation {
+        debug_assert!(
+            self.v & 0x40000000 == 0,
+            "HEntity::equation(...) of out of range {}",
+            self.v
+        );
+        HEquation {
+            v: 0x40000000 | self.v,
+        }
+    }
+
+    pub fn is_from_request(&self) -> bool {
+        self.v & 0x80000000 == 0
+    }
+
+    pub fn request(&self) -> HRequest {
+        HRequest { v: self.v >> 16 }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum RequestType {
+    Workplane = 100,
+    DatumPoint = 101,
+    DatumNormal = 102,
+    LineSegment = 200,
+    Cubic = 300,
+    CubicPeriodic = 301,
+    Circle = 400,
+    ArcOfCircle = 500,
+    TtfText = 600,
+    Image = 700,
+}
+
+// Something the user drew, which becomes the entities and params of it;
+// a line segment is the line plus its two points.
+pub struct Request {
+    pub tag: i32,
+    pub h: HRequest,
+    pub type_: RequestType,
+    pub extra_points: usize,
+
+    pub workplane: HEntity,
+    pub group: HGroup,
+    pub style: HStyle,
+
+    pub construction: bool,
+    pub str_: String,
+    pub font: String,
+    pub file: String,
+    pub aspect_ratio: f64,
+}
+
+// Why an edit couldn't be made; the sketch is left as it was.
+#[derive(Clone, PartialEq, Debug)]
+pub enum SketchError {
+    TooManyPoints(usize),
+    UnknownGroup(u32),
+    UnknownEntity(u32),
+    UnknownConstraint(u32),
+    NotAPoint(u32),
+    NotAWorkplane(u32),
+    WrongEntityType(u32),
+    NotInWorkplane,
+    OutOfHandles,
+    NotDraggable(u32),
+    NotInGroup(u32),
+    NoPath(u32),
+    NotADimension(u32),
+    IsReference(u32),
+}
+
+impl fmt::Display for SketchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SketchError::TooManyPoints(max) => {
+                write!(f, "at most {} extra points for this request", max)
+            }
+            SketchError::UnknownGroup(h) => write!(f, "no group {}", h),
+            SketchError::UnknownEntity(h) => write!(f, "no entity {}", h),
+            SketchError::UnknownConstraint(h) => write!(f, "no constraint {}", h),
+            SketchError::NotAPoint(h) => write!(f, "entity {} is not a point", h),
+            SketchError::NotAWorkplane(h) => write!(f, "entity {} is not a workplane", h),
+            SketchError::WrongEntityType(h) => {
+                write!(f, "entity {} is not what the constraint takes there", h)
+            }
+            SketchError::NotInWorkplane => write!(f, "the constraint needs a workplane"),
+            SketchError::OutOfHandles => write!(f, "no handles left to add it under"),
+            SketchError::NotDraggable(h) => {
+                write!(f, "point {} is made by its group, so can't be dragged", h)
+            }
+            SketchError::NotInGroup(h) => write!(f, "entity {} is in some other group", h),
+            SketchError::NoPath(h) => write!(f, "point {} hasn't traced enough to make a curve", h),
+            SketchError::NotADimension(h) => write!(f, "constraint {} is not a dimension", h),
+            SketchError::IsReference(h) => {
+                write!(
+                    f,
+                    "dimension {} is a reference, so doesn't drive anything",
+                    h
+                )
+            }
+        }
+    }
+}
+
+#[derive(Clone)]
 pub struct Param {
     pub tag: i32,
     pub h: HParam,
@@ -202,8 +477,10 @@ pub struct Param {
     pub substd: HParam,
 }
 
-const MAX_POINTS_IN_ENTITY: usize = 12;
+pub const MAX_POINTS_IN_ENTITY: usize = 12;
+pub const MAX_PARAMS_IN_ENTITY: usize = 8;
 
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
 pub enum EntityBaseType {
     PointIn3D = 2000,
     PointIn2D = 2001,
@@ -222,25 +499,259 @@ pub enum EntityBaseType {
     Distance = 4000,
     DistanceNCopy = 4001,
 
-    FACE_NORMAL_PT = 5000,
-    FACE_XPROD = 5001,
-    FACE_N_ROT_TRANS = 5002,
-    FACE_N_TRANS = 5003,
-    FACE_N_ROT_AA = 5004,
-    FACE_ROT_NORMAL_PT = 5005,
-    FACE_N_ROT_AXIS_TRANS = 5006,
-
-    WORKPLANE = 10000,
-    LINE_SEGMENT = 11000,
-    CUBIC = 12000,
-    CUBIC_PERIODIC = 12001,
-    CIRCLE = 13000,
-    ARC_OF_CIRCLE = 14000,
-    TTF_TEXT = 15000,
-    IMAGE = 16000,
+    FaceNormalPt = 5000,
+    FaceXProd = 5001,
+    FaceNRotTrans = 5002,
+    FaceNTrans = 5003,
+    FaceNRotAA = 5004,
+    FaceRotNormalPt = 5005,
+    FaceNRotAxisTrans = 5006,
+
+    Workplane = 10000,
+    LineSegment = 11000,
+    Cubic = 12000,
+    CubicPeriodic = 12001,
+    Circle = 13000,
+    ArcOfCircle = 14000,
+    TtfText = 15000,
+    Image = 16000,
 }
 
 pub struct EntityBase {
     pub tag: i32,
     pub h: HEntity,
+    pub type_: EntityBaseType,
+    pub group: HGroup,
+    pub workplane: HEntity,
+
+    pub point: [HEntity; MAX_POINTS_IN_ENTITY],
+    pub extra_points: usize,
+    pub normal: HEntity,
+    pub distance: HEntity,
+    pub param: [HParam; MAX_PARAMS_IN_ENTITY],
+
+    pub num_point: Vector,
+    pub num_normal: Quaternion,
+    pub num_distance: f64,
+    pub times_applied: i32,
+
+    pub construction: bool,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum ConstraintType {
+    PointsCoincident = 20,
+    PtPtDistance = 30,
+    PtPlaneDistance = 31,
+    PtLineDistance = 32,
+    PtFaceDistance = 33,
+    ProjPtDistance = 34,
+    PtInPlane = 41,
+    PtOnLine = 42,
+    PtOnFace = 43,
+    EqualLengthLines = 50,
+    LengthRatio = 51,
+    EqLenPtLineD = 52,
+    EqPtLnDistances = 53,
+    EqualAngle = 54,
+    LengthDifference = 56,
+    Symmetric = 60,
+    SymmetricHoriz = 61,
+    SymmetricVert = 62,
+    SymmetricLine = 63,
+    AtMidpoint = 70,
+    Horizontal = 80,
+    Vertical = 81,
+    Diameter = 90,
+    PtOnCircle = 100,
+    SameOrientation = 110,
+    Angle = 120,
+    Parallel = 121,
+    Perpendicular = 122,
+    ArcLineTangent = 123,
+    CubicLineTangent = 124,
+    CurveCurveTangent = 125,
+    EqualRadius = 130,
+    WhereDragged = 200,
+    Comment = 1000,
+}
+
+pub struct Constraint {
+    pub tag: i32,
+    pub h: HConstraint,
+    pub type_: ConstraintType,
+    pub group: HGroup,
+    pub workplane: HEntity,
+
+    pub val_a: f64,
+    pub val_p: HParam,
+    pub pt_a: HEntity,
+    pub pt_b: HEntity,
+    pub entity_a: HEntity,
+    pub entity_b: HEntity,
+    pub entity_c: HEntity,
+    pub entity_d: HEntity,
+    pub other: bool,
+    pub other2: bool,
+    pub reference: bool,
+
+    // If set, val_a is worked out from this on every regenerate, and
+    // expr_error says why it couldn't be.
+    pub expr: Option<String>,
+    pub expr_error: Option<ExprError>,
+
+    // What it measured at the last solve, if it was a reference then. A
+    // driving dimension of an all_dims_reference group keeps its val_a, so
+    // it still drives once the flag is cleared.
+    pub measured: Option<f64>,
+
+    pub comment: String,
+}
+
+#[derive(Clone)]
+pub struct Equation {
+    pub tag: i32,
+    pub h: HEquation,
+    pub e: Expr,
+}
+
+// A named value that dimensions (and other variables) can refer to.
+pub struct Variable {
+    pub name: String,
+    pub expr: String,
+    pub val: f64,
+    pub error: Option<ExprError>,
+}
+
+// One row of a design table: what it takes to turn the sketch into one
+// member of a family of parts, say the M4 bracket rather than the M3. The
+// variables are given as expression text, and the dimensions as they were
+// given, a plain value or an expression.
+#[derive(Clone)]
+pub struct Configuration {
+    pub name: String,
+    pub dimension: HashMap<HConstraint, DimensionValue>,
+    pub variable: HashMap<String, String>,
+    pub suppress: HashMap<HGroup, bool>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum DimensionValue {
+    Value(f64),
+    Expr(String),
+}
+
+pub struct Sketch {
+    pub group: IdList<HGroup, Group>,
+    pub request: IdList<HRequest, Request>,
+    pub constraint: IdList<HConstraint, Constraint>,
+    pub entity: IdList<HEntity, EntityBase>,
+    pub param: IdList<HParam, Param>,
+    pub variable: HashMap<String, Variable>,
+    pub configuration: Vec<Configuration>,
+    pub active_configuration: Option<String>,
+
+    // Points whose positions are recorded as the sketch moves, and the
+    // paths they've followed so far.
+    pub traced: Vec<HEntity>,
+    pub trace_path: HashMap<HEntity, Vec<Vector>>,
+
+    // Limits on solve_group(..), so a bad sketch can't hang the caller. The
+    // token is reset as each solve, drag, regenerate or step_dimension(..)
+    // starts, so it can only stop one that's running, from another thread
+    // holding a clone of it.
+    pub solve_timeout: Option<f64>,
+    pub cancel: CancelToken,
+}
+
+impl Default for Sketch {
+    fn default() -> Self {
+        Sketch::new()
+    }
+}
+
+impl Sketch {
+    pub fn new() -> Sketch {
+        Sketch {
+            // Everything else is derived from these, so they get 14 bits.
+            group: IdList::with_limit(1 << 14),
+            request: IdList::with_limit(1 << 14),
+            constraint: IdList::with_limit(1 << 14),
+            entity: IdList::new(),
+            param: IdList::new(),
+            variable: HashMap::new(),
+            configuration: Vec::new(),
+            active_configuration: None,
+            traced: Vec::new(),
+            trace_path: HashMap::new(),
+            solve_timeout: None,
+            cancel: CancelToken::new(),
+        }
+    }
+
+    pub fn get_group(&self, h: HGroup) -> &Group {
+        match self.group.get(&h) {
+            Some(g) => g,
+            None if self.group.is_stale(&h) => {
+                panic!("Sketch::get_group(...) of deleted group {}", h.v)
+            }
+            None => panic!("Sketch::get_group(...) of unknown group {}", h.v),
+        }
+    }
+
+    pub fn get_request(&self, h: HRequest) -> &Request {
+        match self.request.get(&h) {
+            Some(r) => r,
+            None if self.request.is_stale(&h) => {
+                panic!("Sketch::get_request(...) of deleted request {}", h.v)
+            }
+            None => panic!("Sketch::get_request(...) of unknown request {}", h.v),
+        }
+    }
+
+    pub fn get_constraint(&self, h: HConstraint) -> &Constraint {
+        match self.constraint.get(&h) {
+            Some(c) => c,
+            None if self.constraint.is_stale(&h) => {
+                panic!("Sketch::get_constraint(...) of deleted constraint {}", h.v)
+            }
+            None => panic!("Sketch::get_constraint(...) of unknown constraint {}", h.v),
+        }
+    }
+
+    pub fn get_entity(&self, h: HEntity) -> &EntityBase {
+        match self.entity.get(&h) {
+            Some(e) => e,
+            None if self.entity.is_stale(&h) => {
+                panic!("Sketch::get_entity(...) of deleted entity {}", h.v)
+            }
+            None => panic!("Sketch::get_entity(...) of unknown entity {}", h.v),
+        }
+    }
+
+    pub fn get_param(&self, h: HParam) -> &Param {
+        match self.param.get(&h) {
+            Some(p) => p,
+            None if self.param.is_stale(&h) => {
+                panic!("Sketch::get_param(...) of deleted param {}", h.v)
+            }
+            None => panic!("Sketch::get_param(...) of unknown param {}", h.v),
+        }
+    }
+
+    // As get_group(..) and the rest, for handles from outside the sketch,
+    // such as the UI's, that might not be in it.
+    pub fn find_group(&self, h: HGroup) -> Result<&Group, SketchError> {
+        self.group.get(&h).ok_or(SketchError::UnknownGroup(h.v))
+    }
+
+    pub fn find_entity(&self, h: HEntity) -> Result<&EntityBase, SketchError> {
+        self.entity.get(&h).ok_or(SketchError::UnknownEntity(h.v))
+    }
+
+    pub fn find_constraint(&self, h: HConstraint) -> Result<&Constraint, SketchError> {
+        self.constraint
+            .get(&h)
+            .ok_or(SketchError::UnknownConstraint(h.v))
+    }
 }
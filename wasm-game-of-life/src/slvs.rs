@@ -0,0 +1,341 @@
+// The solver on its own, without the app: describe params, entities and
+// constraints in groups, much as libslvs does, then solve one group at a
+// time and read back the params, the DOF and what failed.
+
+use crate::sketch::{
+    Constraint, ConstraintType, EntityBase, EntityBaseType, GroupSolved, HConstraint, HEntity,
+    HGroup, HParam, Param, Sketch, NO_PARAM,
+};
+use crate::system::{SolveResult, System};
+
+pub const SLVS_FREE_IN_3D: u32 = 0;
+
+const SLVS_FIND_TO_FIX_TIMEOUT: i32 = 5000;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum SlvsResult {
+    Okay = 0,
+    Inconsistent = 1,
+    DidntConverge = 2,
+    TooManyUnknowns = 3,
+    // Not a result of solving: something refers to a handle that isn't
+    // there, or to an entity of the wrong type, so nothing was solved.
+    BadInput = 4,
+}
+
+#[derive(Clone, Debug)]
+pub struct SlvsParam {
+    pub h: u32,
+    pub group: u32,
+    pub val: f64,
+}
+
+#[derive(Clone, Debug)]
+pub struct SlvsEntity {
+    pub h: u32,
+    pub group: u32,
+    pub type_: EntityBaseType,
+    pub wrkpl: u32,
+    pub point: [u32; 4],
+    pub normal: u32,
+    pub distance: u32,
+    pub param: [u32; 4],
+}
+
+#[derive(Clone, Debug)]
+pub struct SlvsConstraint {
+    pub h: u32,
+    pub group: u32,
+    pub type_: ConstraintType,
+    pub wrkpl: u32,
+    pub val_a: f64,
+    pub pt_a: u32,
+    pub pt_b: u32,
+    pub entity_a: u32,
+    pub entity_b: u32,
+    pub entity_c: u32,
+    pub entity_d: u32,
+    pub other: bool,
+    pub other2: bool,
+}
+
+pub struct SlvsSystem {
+    pub param: Vec<SlvsParam>,
+    pub entity: Vec<SlvsEntity>,
+    pub constraint: Vec<SlvsConstraint>,
+
+    // Up to four params to keep as close as possible to where they are.
+    pub dragged: Vec<u32>,
+
+    pub calculate_faileds: bool,
+
+    pub failed: Vec<u32>,
+    pub dof: i32,
+    pub result: SlvsResult,
+}
+
+impl SlvsParam {
+    pub fn new(h: u32, group: u32, val: f64) -> Self {
+        SlvsParam { h, group, val }
+    }
+}
+
+impl SlvsEntity {
+    fn new(h: u32, group: u32, type_: EntityBaseType, wrkpl: u32) -> Self {
+        SlvsEntity {
+            h,
+            group,
+            type_,
+            wrkpl,
+            point: [0; 4],
+            normal: 0,
+            distance: 0,
+            param: [0; 4],
+        }
+    }
+
+    pub fn point_2d(h: u32, group: u32, wrkpl: u32, u: u32, v: u32) -> Self {
+        let mut e = SlvsEntity::new(h, group, EntityBaseType::PointIn2D, wrkpl);
+        e.param[0] = u;
+        e.param[1] = v;
+        e
+    }
+
+    pub fn point_3d(h: u32, group: u32, x: u32, y: u32, z: u32) -> Self {
+        let mut e = SlvsEntity::new(h, group, EntityBaseType::PointIn3D, SLVS_FREE_IN_3D);
+        e.param[0] = x;
+        e.param[1] = y;
+        e.param[2] = z;
+        e
+    }
+
+    pub fn normal_3d(h: u32, group: u32, qw: u32, qx: u32, qy: u32, qz: u32) -> Self {
+        let mut e = SlvsEntity::new(h, group, EntityBaseType::NormalIn3D, SLVS_FREE_IN_3D);
+        e.param = [qw, qx, qy, qz];
+        e
+    }
+
+    pub fn normal_2d(h: u32, group: u32, wrkpl: u32) -> Self {
+        SlvsEntity::new(h, group, EntityBaseType::NormalIn2D, wrkpl)
+    }
+
+    pub fn distance(h: u32, group: u32, wrkpl: u32, d: u32) -> Self {
+        let mut e = SlvsEntity::new(h, group, EntityBaseType::Distance, wrkpl);
+        e.param[0] = d;
+        e
+    }
+
+    pub fn line_segment(h: u32, group: u32, wrkpl: u32, pt_a: u32, pt_b: u32) -> Self {
+        let mut e = SlvsEntity::new(h, group, EntityBaseType::LineSegment, wrkpl);
+        e.point[0] = pt_a;
+        e.point[1] = pt_b;
+        e
+    }
+
+    pub fn cubic(h: u32, group: u32, wrkpl: u32, pt: [u32; 4]) -> Self {
+        let mut e = SlvsEntity::new(h, group, EntityBaseType::Cubic, wrkpl);
+        e.point = pt;
+        e
+    }
+
+    pub fn arc_of_circle(
+        h: u32,
+        group: u32,
+        wrkpl: u32,
+        normal: u32,
+        center: u32,
+        start: u32,
+        end: u32,
+    ) -> Self {
+        let mut e = SlvsEntity::new(h, group, EntityBaseType::ArcOfCircle, wrkpl);
+        e.normal = normal;
+        e.point[0] = center;
+        e.point[1] = start;
+        e.point[2] = end;
+        e
+    }
+
+    pub fn circle(h: u32, group: u32, wrkpl: u32, center: u32, normal: u32, radius: u32) -> Self {
+        let mut e = SlvsEntity::new(h, group, EntityBaseType::Circle, wrkpl);
+        e.point[0] = center;
+        e.normal = normal;
+        e.distance = radius;
+        e
+    }
+
+    pub fn workplane(h: u32, group: u32, origin: u32, normal: u32) -> Self {
+        let mut e = SlvsEntity::new(h, group, EntityBaseType::Workplane, SLVS_FREE_IN_3D);
+        e.point[0] = origin;
+        e.normal = normal;
+        e
+    }
+}
+
+// Whether the entity is one libslvs has, and everything it's made from is
+// there and of the right type. Only the entities are in sk so far.
+fn entity_ok(se: &SlvsEntity, sk: &Sketch) -> bool {
+    let is = |h: u32, ok: fn(&EntityBase) -> bool| sk.entity.get(&HEntity { v: h }).is_some_and(ok);
+    let params = |n: usize| {
+        se.param[..n]
+            .iter()
+            .all(|h| sk.param.contains_key(&HParam { v: *h }))
+    };
+    let points = |n: usize| se.point[..n].iter().all(|h| is(*h, EntityBase::is_point));
+    let in_workplane = is(se.wrkpl, EntityBase::is_workplane);
+    let wrkpl_ok = se.wrkpl == SLVS_FREE_IN_3D || in_workplane;
+
+    wrkpl_ok
+        && match se.type_ {
+            EntityBaseType::PointIn3D => params(3),
+            EntityBaseType::PointIn2D => in_workplane && params(2),
+            EntityBaseType::NormalIn3D => params(4),
+            EntityBaseType::NormalIn2D => in_workplane,
+            EntityBaseType::Distance => params(1),
+            EntityBaseType::LineSegment => points(2),
+            EntityBaseType::Cubic => points(4),
+            EntityBaseType::ArcOfCircle => {
+                in_workplane && points(3) && is(se.normal, EntityBase::is_normal)
+            }
+            EntityBaseType::Circle => {
+                points(1)
+                    && is(se.normal, EntityBase::is_normal)
+                    && is(se.distance, EntityBase::is_distance)
+            }
+            EntityBaseType::Workplane => points(1) && is(se.normal, EntityBase::is_normal),
+            _ => false,
+        }
+}
+
+impl SlvsConstraint {
+    pub fn new(h: u32, group: u32, type_: ConstraintType, wrkpl: u32) -> Self {
+        SlvsConstraint {
+            h,
+            group,
+            type_,
+            wrkpl,
+            val_a: 0.0,
+            pt_a: 0,
+            pt_b: 0,
+            entity_a: 0,
+            entity_b: 0,
+            entity_c: 0,
+            entity_d: 0,
+            other: false,
+            other2: false,
+        }
+    }
+}
+
+impl Default for SlvsSystem {
+    fn default() -> Self {
+        SlvsSystem::new()
+    }
+}
+
+impl SlvsSystem {
+    pub fn new() -> Self {
+        SlvsSystem {
+            param: Vec::new(),
+            entity: Vec::new(),
+            constraint: Vec::new(),
+            dragged: Vec::new(),
+            calculate_faileds: false,
+            failed: Vec::new(),
+            dof: 0,
+            result: SlvsResult::Okay,
+        }
+    }
+
+    // Params are unknown if they're in the group being solved, and fixed
+    // otherwise; the solved values are written back in place. Input the
+    // solver can't make sense of is BadInput, and leaves the params alone.
+    pub fn solve(&mut self, hg: u32) -> SlvsResult {
+        self.failed.clear();
+        self.dof = 0;
+        self.result = SlvsResult::BadInput;
+
+        let shg = HGroup { v: hg };
+        let mut sk = Sketch::new();
+        let mut sys = System::new();
+
+        for sp in self.param.iter() {
+            let p = Param {
+                tag: 0,
+                h: HParam { v: sp.h },
+                val: sp.val,
+                known: sp.group != hg,
+                free: false,
+                substd: NO_PARAM,
+            };
+            sk.param.add(p.clone());
+            sys.add_param(p);
+        }
+
+        for se in self.entity.iter() {
+            let mut e = EntityBase::new(HEntity { v: se.h }, se.type_);
+            e.group = HGroup { v: se.group };
+            e.workplane = HEntity { v: se.wrkpl };
+            for i in 0..4 {
+                e.point[i] = HEntity { v: se.point[i] };
+                e.param[i] = HParam { v: se.param[i] };
+            }
+            e.normal = HEntity { v: se.normal };
+            e.distance = HEntity { v: se.distance };
+            sk.entity.add(e);
+        }
+        if !self.entity.iter().all(|se| entity_ok(se, &sk)) {
+            return self.result;
+        }
+
+        for sc in self.constraint.iter() {
+            let mut c = Constraint::new(HConstraint { v: sc.h }, sc.type_, HGroup { v: sc.group });
+            c.workplane = HEntity { v: sc.wrkpl };
+            c.val_a = sc.val_a;
+            c.pt_a = HEntity { v: sc.pt_a };
+            c.pt_b = HEntity { v: sc.pt_b };
+            c.entity_a = HEntity { v: sc.entity_a };
+            c.entity_b = HEntity { v: sc.entity_b };
+            c.entity_c = HEntity { v: sc.entity_c };
+            c.entity_d = HEntity { v: sc.entity_d };
+            c.other = sc.other;
+            c.other2 = sc.other2;
+            if c.check(&sk).is_err() {
+                return self.result;
+            }
+            if let Some(mut p) = c.generate_params(&sk) {
+                p.known = c.group != shg;
+                sk.param.add(p.clone());
+                sys.add_param(p);
+            }
+            sk.constraint.add(c);
+        }
+
+        sys.write_equations_for_group(&sk, shg);
+        sys.dragged = self.dragged.iter().map(|h| HParam { v: *h }).collect();
+
+        let mut solved = GroupSolved {
+            how: SolveResult::Okay,
+            dof: 0,
+            find_to_fix_timeout: SLVS_FIND_TO_FIX_TIMEOUT,
+            timeout: false,
+            remove: Vec::new(),
+            residual: Vec::new(),
+        };
+        let how = sys.solve(&mut solved, false, self.calculate_faileds, false);
+
+        for sp in self.param.iter_mut() {
+            sp.val = sys.param[&HParam { v: sp.h }].val;
+        }
+        self.dof = solved.dof;
+        self.failed = solved.remove.iter().map(|h| h.v).collect();
+        self.result = match how {
+            SolveResult::Okay => SlvsResult::Okay,
+            SolveResult::DidntConverge => SlvsResult::DidntConverge,
+            SolveResult::RedundantOkay | SolveResult::RedundantDidntConverge => {
+                SlvsResult::Inconsistent
+            }
+            SolveResult::TooManyUnknowns => SlvsResult::TooManyUnknowns,
+        };
+        self.result
+    }
+}
@@ -0,0 +1,268 @@
+use std::collections::BTreeMap;
+use std::collections::VecDeque;
+
+const PIVOT_RELATIVE_TOLERANCE: f64 = 1e-12;
+
+// A row-major sparse matrix; each row is kept sorted by column.
+#[derive(Clone)]
+pub struct SparseMatrix {
+    pub rows: usize,
+    pub cols: usize,
+    pub row: Vec<Vec<(usize, f64)>>,
+}
+
+// The LDL^T factorisation of a symmetric positive semi-definite matrix,
+// usually permuted to reduce fill. Pivots below the tolerance (redundant rows) are
+// dropped, so the number of pivots kept is the rank.
+pub struct SparseLdlt {
+    perm: Vec<usize>,
+    l: Vec<BTreeMap<usize, f64>>,
+    d: Vec<f64>,
+    // Of all the pivots, the fewest times over or under the tolerance one
+    // was, kept or not.
+    closest: f64,
+}
+
+impl SparseMatrix {
+    pub fn new(rows: usize, cols: usize) -> Self {
+        SparseMatrix {
+            rows,
+            cols,
+            row: vec![Vec::new(); rows],
+        }
+    }
+
+    pub fn push(&mut self, r: usize, c: usize, v: f64) {
+        if v != 0.0 {
+            self.row[r].push((c, v));
+        }
+    }
+
+    pub fn clear_column(&mut self, c: usize) {
+        for row in self.row.iter_mut() {
+            row.retain(|(rc, _)| *rc != c);
+        }
+    }
+
+    pub fn scale_columns(&mut self, scale: &[f64]) {
+        for row in self.row.iter_mut() {
+            for (c, v) in row.iter_mut() {
+                *v *= scale[*c];
+            }
+        }
+    }
+
+    pub fn mul_transpose(&self, z: &[f64]) -> Vec<f64> {
+        let mut x = vec![0.0; self.cols];
+        for (r, row) in self.row.iter().enumerate() {
+            for (c, v) in row {
+                x[*c] += v * z[r];
+            }
+        }
+        x
+    }
+
+    pub fn transpose(&self) -> SparseMatrix {
+        let mut t = SparseMatrix::new(self.cols, self.rows);
+        for (r, row) in self.row.iter().enumerate() {
+            for (c, v) in row {
+                t.row[*c].push((r, *v));
+            }
+        }
+        t
+    }
+
+    pub fn to_dense(&self) -> Vec<Vec<f64>> {
+        let mut a = vec![vec![0.0; self.cols]; self.rows];
+        for (r, row) in self.row.iter().enumerate() {
+            for (c, v) in row {
+                a[r][*c] = *v;
+            }
+        }
+        a
+    }
+
+    // Lower triangle of A A^T, by row.
+    pub fn aat_lower(&self) -> Vec<BTreeMap<usize, f64>> {
+        let mut col: Vec<Vec<(usize, f64)>> = vec![Vec::new(); self.cols];
+        for (r, row) in self.row.iter().enumerate() {
+            for (c, v) in row {
+                col[*c].push((r, *v));
+            }
+        }
+
+        let mut aat = vec![BTreeMap::new(); self.rows];
+        for entries in col.iter() {
+            for (i, (ri, vi)) in entries.iter().enumerate() {
+                for (rj, vj) in entries[..=i].iter() {
+                    *aat[*ri].entry(*rj).or_insert(0.0) += vi * vj;
+                }
+            }
+        }
+        aat
+    }
+}
+
+// Reverse Cuthill-McKee ordering of a symmetric matrix given by its lower
+// triangle; keeps the fill of the factorisation inside a narrow profile.
+pub fn reverse_cuthill_mckee(lower: &[BTreeMap<usize, f64>]) -> Vec<usize> {
+    let n = lower.len();
+    let mut adj: Vec<Vec<usize>> = vec![Vec::new(); n];
+    for (i, row) in lower.iter().enumerate() {
+        for j in row.keys() {
+            if *j != i {
+                adj[i].push(*j);
+                adj[*j].push(i);
+            }
+        }
+    }
+    let degree: Vec<usize> = adj.iter().map(|a| a.len()).collect();
+    for a in adj.iter_mut() {
+        a.sort_by_key(|j| degree[*j]);
+    }
+
+    let mut order = Vec::with_capacity(n);
+    let mut visited = vec![false; n];
+    let mut start: Vec<usize> = (0..n).collect();
+    start.sort_by_key(|i| degree[*i]);
+    for s in start {
+        if visited[s] {
+            continue;
+        }
+        visited[s] = true;
+        let mut queue = VecDeque::new();
+        queue.push_back(s);
+        while let Some(i) = queue.pop_front() {
+            order.push(i);
+            for j in adj[i].iter() {
+                if !visited[*j] {
+                    visited[*j] = true;
+                    queue.push_back(*j);
+                }
+            }
+        }
+    }
+    order.reverse();
+    order
+}
+
+impl SparseLdlt {
+    pub fn factor(lower: &[BTreeMap<usize, f64>], tolerance: f64) -> Self {
+        Self::factor_permuted(lower, tolerance, &reverse_cuthill_mckee(lower))
+    }
+
+    // In an order found beforehand, for a run of matrices that have entries
+    // in no more places than the one it was found for.
+    pub fn factor_permuted(lower: &[BTreeMap<usize, f64>], tolerance: f64, perm: &[usize]) -> Self {
+        let n = lower.len();
+        let perm = perm.to_vec();
+        let mut inv = vec![0; n];
+        for (k, i) in perm.iter().enumerate() {
+            inv[*i] = k;
+        }
+
+        // Permute into m, lower triangle by row, plus the rows that have an
+        // entry in each column below the diagonal.
+        let mut m: Vec<BTreeMap<usize, f64>> = vec![BTreeMap::new(); n];
+        let mut below: Vec<Vec<usize>> = vec![Vec::new(); n];
+        for (i, row) in lower.iter().enumerate() {
+            for (j, v) in row {
+                let (pi, pj) = (inv[i], inv[*j]);
+                let (r, c) = if pi >= pj { (pi, pj) } else { (pj, pi) };
+                let e = m[r].entry(c).or_insert(0.0);
+                if *e == 0.0 && r != c {
+                    below[c].push(r);
+                }
+                *e += v;
+            }
+        }
+        let diag: Vec<f64> = (0..n)
+            .map(|k| m[k].get(&k).cloned().unwrap_or(0.0).abs())
+            .collect();
+
+        let mut d = vec![0.0; n];
+        let mut closest = f64::INFINITY;
+        for k in 0..n {
+            let dk = m[k].get(&k).cloned().unwrap_or(0.0);
+            if tolerance > 0.0 && dk.abs() > 0.0 {
+                closest = closest.min((dk.abs() / tolerance).max(tolerance / dk.abs()));
+            }
+            let mut rows = std::mem::take(&mut below[k]);
+            rows.sort();
+            rows.dedup();
+
+            // A NaN pivot (say a distance between coincident points) counts
+            // as zero, as it would in the Gram-Schmidt rank test.
+            if dk.is_nan() || dk.abs() <= tolerance.max(PIVOT_RELATIVE_TOLERANCE * diag[k]) {
+                for i in rows.iter() {
+                    m[*i].remove(&k);
+                }
+                continue;
+            }
+            d[k] = dk;
+
+            let col: Vec<(usize, f64)> = rows
+                .iter()
+                .map(|i| (*i, m[*i].get(&k).cloned().unwrap_or(0.0)))
+                .collect();
+            for (a, (i, vi)) in col.iter().enumerate() {
+                for (j, vj) in col[..=a].iter() {
+                    let e = m[*i].entry(*j).or_insert(0.0);
+                    if *e == 0.0 && i != j {
+                        below[*j].push(*i);
+                    }
+                    *e -= vi * vj / dk;
+                }
+            }
+            for (i, vi) in col {
+                m[i].insert(k, vi / dk);
+            }
+        }
+
+        for (k, row) in m.iter_mut().enumerate() {
+            row.remove(&k);
+        }
+        SparseLdlt {
+            perm,
+            l: m,
+            d,
+            closest,
+        }
+    }
+
+    pub fn rank(&self) -> usize {
+        self.d.iter().filter(|d| **d != 0.0).count()
+    }
+
+    pub fn closest_to_tolerance(&self) -> f64 {
+        self.closest
+    }
+
+    pub fn solve(&self, b: &[f64]) -> Vec<f64> {
+        let n = self.d.len();
+        let mut y: Vec<f64> = self.perm.iter().map(|i| b[*i]).collect();
+
+        for i in 0..n {
+            let mut s = y[i];
+            for (k, l) in self.l[i].iter() {
+                s -= l * y[*k];
+            }
+            y[i] = s;
+        }
+        for (yi, d) in y.iter_mut().zip(self.d.iter()) {
+            *yi = if *d == 0.0 { 0.0 } else { *yi / d };
+        }
+        for i in (0..n).rev() {
+            let yi = y[i];
+            for (k, l) in self.l[i].iter() {
+                y[*k] -= l * yi;
+            }
+        }
+
+        let mut x = vec![0.0; n];
+        for (k, i) in self.perm.iter().enumerate() {
+            x[*i] = y[k];
+        }
+        x
+    }
+}
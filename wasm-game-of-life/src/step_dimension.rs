@@ -0,0 +1,117 @@
+use crate::dsc::Vector;
+use crate::sketch::{HConstraint, HEntity, HParam, Sketch, SketchError};
+use crate::system::SolveResult;
+
+// The sketch as it was solved at one step of a step_dimension(..).
+pub struct StepFrame {
+    pub val: f64,
+    pub how: SolveResult,
+    pub param: Vec<(HParam, f64)>,
+    pub traced: Vec<(HEntity, Vector)>,
+}
+
+fn solved(how: SolveResult) -> bool {
+    matches!(how, SolveResult::Okay | SolveResult::RedundantOkay)
+}
+
+impl Sketch {
+    // Sweeps a dimension from a to b in the given number of steps,
+    // regenerating at each one, as SolveSpace's step dimension does. That
+    // stops at the first step that won't solve, so the last frame may be a
+    // failure. A dimension driven by an expression gets it back afterwards.
+    // Only a driving dimension can be stepped; a reference one, or any in
+    // an all_dims_reference group, would just be measured over again.
+    pub fn step_dimension(
+        &mut self,
+        hc: HConstraint,
+        a: f64,
+        b: f64,
+        steps: u32,
+    ) -> Result<Vec<StepFrame>, SketchError> {
+        let c = self.find_constraint(hc)?;
+        if !c.is_dimension() {
+            return Err(SketchError::NotADimension(hc.v));
+        }
+        let hg = c.group;
+        if c.reference || self.find_group(hg)?.all_dims_reference {
+            return Err(SketchError::IsReference(hc.v));
+        }
+        let expr = self.constraint.get_mut(&hc).unwrap().expr.take();
+        let steps = steps.max(1);
+        // One reset for the whole sweep, so a cancel stops it.
+        self.cancel.reset();
+
+        let mut frames = Vec::new();
+        for i in 0..=steps {
+            let val = a + (b - a) * (i as f64) / (steps as f64);
+            self.constraint.get_mut(&hc).unwrap().val_a = val;
+            self.mark_group_dirty(hg);
+            // A failure if any group failed, else redundant if any was, as
+            // a group that allows it solves as RedundantOkay every time.
+            let hows: Vec<SolveResult> = self
+                .regenerate_dirty_groups()
+                .into_iter()
+                .map(|(_, how)| how)
+                .collect();
+            let how = hows
+                .iter()
+                .find(|how| !solved(**how))
+                .or_else(|| hows.iter().find(|how| **how != SolveResult::Okay))
+                .cloned()
+                .unwrap_or(SolveResult::Okay);
+            frames.push(self.step_frame(val, how));
+            if !solved(how) {
+                break;
+            }
+        }
+
+        self.constraint.get_mut(&hc).unwrap().expr = expr;
+        Ok(frames)
+    }
+
+    fn step_frame(&self, val: f64, how: SolveResult) -> StepFrame {
+        let mut param: Vec<(HParam, f64)> = self.param.values().map(|p| (p.h, p.val)).collect();
+        param.sort_by_key(|(h, _)| *h);
+        let traced = self
+            .traced
+            .iter()
+            .map(|h| (*h, self.get_entity(*h).point_get_num(self)))
+            .collect();
+        StepFrame {
+            val,
+            how,
+            param,
+            traced,
+        }
+    }
+}
+
+// One row per frame: the dimension's value, then every param, then each
+// traced point's x, y and z.
+pub fn step_frames_csv(frames: &[StepFrame]) -> String {
+    let mut r = String::new();
+    let first = match frames.first() {
+        Some(f) => f,
+        None => return r,
+    };
+    r.push_str("step,value");
+    for (h, _) in first.param.iter() {
+        r.push_str(&format!(",p{:08x}", h.v));
+    }
+    for (h, _) in first.traced.iter() {
+        r.push_str(&format!(",e{:08x}.x,e{:08x}.y,e{:08x}.z", h.v, h.v, h.v));
+    }
+    r.push('\n');
+
+    for (i, f) in frames.iter().enumerate() {
+        r.push_str(&format!("{},{}", i, f.val));
+        for (_, v) in f.param.iter() {
+            r.push_str(&format!(",{}", v));
+        }
+        for (_, p) in f.traced.iter() {
+            r.push_str(&format!(",{},{},{}", p.x, p.y, p.z));
+        }
+        r.push('\n');
+    }
+    r
+}
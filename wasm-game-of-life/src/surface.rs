@@ -0,0 +1,316 @@
+use crate::dsc::{RgbaColor, Vector, LENGTH_EPS};
+use crate::polygon::{SContour, SMesh, SPolygon, STriMeta};
+
+// A rational Bezier curve of degree 1 to 3, as in SolveSpace; entity is
+// the handle of the sketch entity it was made from.
+#[derive(Copy, Clone)]
+pub struct SBezier {
+    pub tag: i32,
+    pub entity: u32,
+    pub deg: usize,
+    pub ctrl: [Vector; 4],
+    pub weight: [f64; 4],
+}
+
+#[derive(Clone)]
+pub struct SBezierLoop {
+    pub tag: i32,
+    pub l: Vec<SBezier>,
+}
+
+// The loops of one closed region and its holes, all in a plane.
+#[derive(Clone, Default)]
+pub struct SBezierLoopSet {
+    pub l: Vec<SBezierLoop>,
+    pub normal: Vector,
+    pub point: Vector,
+}
+
+#[derive(Clone, Default)]
+pub struct SBezierLoopSetSet {
+    pub l: Vec<SBezierLoopSet>,
+}
+
+// One flat face of a shell, trimmed to the polygon, whose normal points out
+// of the solid. face is the handle of the entity that stands for it.
+#[derive(Clone)]
+pub struct SSurface {
+    pub h: u32,
+    pub face: u32,
+    pub color: RgbaColor,
+    pub trim: SPolygon,
+}
+
+// A solid as the faces bounding it. Curved edges are broken into chords
+// when the faces are made, so every face here is flat.
+#[derive(Clone, Default)]
+pub struct SShell {
+    pub surface: Vec<SSurface>,
+}
+
+impl SBezier {
+    pub fn new(deg: usize, ctrl: &[Vector], entity: u32) -> Self {
+        let mut b = SBezier {
+            tag: 0,
+            entity,
+            deg,
+            ctrl: [Vector::new(0.0, 0.0, 0.0); 4],
+            weight: [1.0; 4],
+        };
+        b.ctrl[..=deg].copy_from_slice(&ctrl[..=deg]);
+        b
+    }
+
+    pub fn start(&self) -> Vector {
+        self.ctrl[0]
+    }
+
+    pub fn finish(&self) -> Vector {
+        self.ctrl[self.deg]
+    }
+
+    pub fn reverse(&mut self) {
+        self.ctrl[..=self.deg].reverse();
+        self.weight[..=self.deg].reverse();
+    }
+
+    pub fn point_at(&self, t: f64) -> Vector {
+        let bernstein = |k: usize| {
+            let c = match (self.deg, k) {
+                (3, 1) | (3, 2) => 3.0,
+                (2, 1) => 2.0,
+                _ => 1.0,
+            };
+            c * t.powi(k as i32) * (1.0 - t).powi((self.deg - k) as i32)
+        };
+        let mut p = Vector::new(0.0, 0.0, 0.0);
+        let mut w = 0.0;
+        for k in 0..=self.deg {
+            let b = bernstein(k) * self.weight[k];
+            p = p + self.ctrl[k] * b;
+            w += b;
+        }
+        p * (1.0 / w)
+    }
+
+    // Points along the curve, not including its start, close enough that
+    // the chords between them stay within chord_tol of it.
+    pub fn make_pwl_into(&self, l: &mut Vec<Vector>, chord_tol: f64) {
+        self.make_pwl_between(l, 0.0, 1.0, chord_tol, 0);
+    }
+
+    fn make_pwl_between(&self, l: &mut Vec<Vector>, ta: f64, tb: f64, chord_tol: f64, depth: u32) {
+        let (pa, pb) = (self.point_at(ta), self.point_at(tb));
+        let tm = (ta + tb) / 2.0;
+        let pm = self.point_at(tm);
+        let off = pm.distance_to_line(pa, pb - pa);
+        // A line is one chord. Anything else is split at least once, since
+        // a closed curve starts and finishes at the same point.
+        let short = (pb - pa).mag_squared() < LENGTH_EPS * LENGTH_EPS;
+        if self.deg == 1 || (depth > 0 && (short || off < chord_tol || depth >= 10)) {
+            l.push(pb);
+        } else {
+            self.make_pwl_between(l, ta, tm, chord_tol, depth + 1);
+            self.make_pwl_between(l, tm, tb, chord_tol, depth + 1);
+        }
+    }
+}
+
+impl SBezierLoop {
+    // Takes curves from l that join end to end, starting from the first,
+    // until they come back round; curves are turned around as needed. If
+    // they never do, the loop is returned anyway, along with false.
+    pub fn from_curves(l: &mut Vec<SBezier>) -> (SBezierLoop, bool) {
+        let mut sbl = SBezierLoop {
+            tag: 0,
+            l: Vec::new(),
+        };
+        if l.is_empty() {
+            return (sbl, false);
+        }
+        sbl.l.push(l.remove(0));
+        loop {
+            let (start, finish) = (sbl.l[0].start(), sbl.l[sbl.l.len() - 1].finish());
+            if start.equals_with_def_tol(finish) {
+                return (sbl, true);
+            }
+            let next = l.iter().position(|b| {
+                b.start().equals_with_def_tol(finish) || b.finish().equals_with_def_tol(finish)
+            });
+            match next {
+                Some(i) => {
+                    let mut b = l.remove(i);
+                    if !b.start().equals_with_def_tol(finish) {
+                        b.reverse();
+                    }
+                    sbl.l.push(b);
+                }
+                None => return (sbl, false),
+            }
+        }
+    }
+
+    pub fn reverse(&mut self) {
+        self.l.reverse();
+        for b in self.l.iter_mut() {
+            b.reverse();
+        }
+    }
+
+    pub fn make_pwl_contour(&self, chord_tol: f64) -> SContour {
+        let mut l = Vec::new();
+        self.make_pwl_into(&mut l, chord_tol);
+        SContour { tag: 0, l }
+    }
+
+    pub fn make_pwl_into(&self, l: &mut Vec<Vector>, chord_tol: f64) {
+        if let Some(first) = self.l.first() {
+            l.push(first.start());
+        }
+        for b in self.l.iter() {
+            b.make_pwl_into(l, chord_tol);
+        }
+        // The last point is the first again.
+        l.pop();
+    }
+}
+
+impl SBezierLoopSet {
+    pub fn new() -> Self {
+        SBezierLoopSet {
+            l: Vec::new(),
+            normal: Vector::new(0.0, 0.0, 0.0),
+            point: Vector::new(0.0, 0.0, 0.0),
+        }
+    }
+
+    pub fn make_pwl_into(&self, sp: &mut SPolygon, chord_tol: f64) {
+        for sbl in self.l.iter() {
+            sp.add_empty_contour();
+            let mut l = Vec::new();
+            sbl.make_pwl_into(&mut l, chord_tol);
+            for p in l {
+                sp.add_point(p);
+            }
+        }
+        sp.normal = self.normal;
+    }
+}
+
+impl SBezierLoopSetSet {
+    pub fn new() -> Self {
+        SBezierLoopSetSet { l: Vec::new() }
+    }
+
+    // Sorts closed loops in a plane into regions, as SolveSpace's
+    // FindOuterFacesFrom: a loop inside an even number of others bounds a
+    // region and is made to go anticlockwise about the normal, and one
+    // inside an odd number is a hole, clockwise, in the smallest region
+    // around it.
+    pub fn find_outer_faces_from(
+        loops: Vec<SBezierLoop>,
+        normal: Vector,
+        point: Vector,
+        chord_tol: f64,
+    ) -> SBezierLoopSetSet {
+        let pwl: Vec<SContour> = loops
+            .iter()
+            .map(|l| l.make_pwl_contour(chord_tol))
+            .collect();
+        let inside = |i: usize, j: usize| {
+            i != j
+                && !pwl[i].l.is_empty()
+                && pwl[j].contains_point_projd_to_normal(normal, pwl[i].l[0])
+        };
+        let depth: Vec<usize> = (0..loops.len())
+            .map(|i| (0..loops.len()).filter(|j| inside(i, *j)).count())
+            .collect();
+        let hole = |i: usize| depth[i] % 2 == 1;
+
+        let mut r = SBezierLoopSetSet::new();
+        let mut set_of = vec![0; loops.len()];
+        for (i, sbl) in loops.iter().enumerate() {
+            if !hole(i) {
+                let mut sbl = sbl.clone();
+                if pwl[i].signed_area_2x(normal) < 0.0 {
+                    sbl.reverse();
+                }
+                set_of[i] = r.l.len();
+                r.l.push(SBezierLoopSet {
+                    l: vec![sbl],
+                    normal,
+                    point,
+                });
+            }
+        }
+        for (i, sbl) in loops.iter().enumerate() {
+            if !hole(i) {
+                continue;
+            }
+            let around = (0..loops.len())
+                .filter(|j| depth[*j] == depth[i] - 1 && inside(i, *j))
+                .min_by(|a, b| {
+                    let area = |k: usize| pwl[k].signed_area_2x(normal).abs();
+                    area(*a).partial_cmp(&area(*b)).unwrap()
+                });
+            if let Some(j) = around {
+                let mut sbl = sbl.clone();
+                if pwl[i].signed_area_2x(normal) > 0.0 {
+                    sbl.reverse();
+                }
+                r.l[set_of[j]].l.push(sbl);
+            }
+        }
+        r
+    }
+
+    pub fn clear(&mut self) {
+        self.l.clear();
+    }
+}
+
+impl SShell {
+    pub fn new() -> Self {
+        SShell {
+            surface: Vec::new(),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.surface.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.surface.is_empty()
+    }
+
+    // Adds a face with the next free handle.
+    pub fn add_surface(&mut self, face: u32, color: RgbaColor, trim: SPolygon) {
+        let h = self.surface.iter().map(|s| s.h).max().unwrap_or(0) + 1;
+        self.surface.push(SSurface {
+            h,
+            face,
+            color,
+            trim,
+        });
+    }
+
+    // Both shells' faces together, overlapping or not.
+    pub fn make_from_assembly_of(a: &SShell, b: &SShell) -> SShell {
+        let mut r = a.clone();
+        for s in b.surface.iter() {
+            r.add_surface(s.face, s.color, s.trim.clone());
+        }
+        r
+    }
+
+    pub fn triangulate_into(&self, m: &mut SMesh) {
+        for s in self.surface.iter() {
+            let meta = STriMeta {
+                face: s.face,
+                color: s.color,
+            };
+            s.trim.triangulate_into(m, meta);
+        }
+    }
+}
@@ -1,10 +1,908 @@
-use crate::sketch::{HEntity, HParam};
+use crate::dsc::LENGTH_EPS;
+use crate::expr::Expr;
+use crate::id_list::IdList;
+use crate::sketch::{
+    Constraint, EntityBase, Equation, GroupSolved, HConstraint, HGroup, HParam, Param, Sketch,
+    NO_PARAM,
+};
+use crate::sparse::{reverse_cuthill_mckee, SparseLdlt, SparseMatrix};
+use crate::utils::get_milliseconds;
 
-use std::collections::hash_map::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 
-const SYSTEM_MAX_UNKNOWNS: usize = 1024;
+pub const SYSTEM_MAX_UNKNOWNS: usize = 1024;
+pub const SYSTEM_MAX_ITERATIONS: usize = 50;
+const SYSTEM_MAX_STEP_HALVINGS: usize = 8;
+
+pub const VAR_SUBSTITUTED: i32 = 10000;
+pub const EQ_SUBSTITUTED: i32 = 20000;
+
+// Dragged params are scaled down in the least squares step, so moving them
+// costs more than moving anything else and they stay near the cursor.
+const DRAGGED_PARAM_SCALE: f64 = 1.0 / 20.0;
+
+const CONVERGE_TOLERANCE: f64 = LENGTH_EPS / 1e2;
+const RANK_MAG_TOLERANCE: f64 = 1e-4;
+// How many times over or under RANK_MAG_TOLERANCE a pivot has to be for
+// the rank to not depend on the order it was found in.
+const RANK_AMBIGUITY: f64 = 100.0;
+
+// Levenberg-Marquardt damping for the relaxed solve; it's raised while
+// steps fail to reduce the residual, and we're at the best fit once it
+// can't be raised any further.
+const RELAX_INITIAL_DAMPING: f64 = 1e-3;
+const RELAX_MAX_DAMPING: f64 = 1e12;
+const RELAX_GAIN_TOLERANCE: f64 = 1e-10;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum SolveResult {
+    Okay = 0,
+    DidntConverge = 10,
+    RedundantOkay = 11,
+    RedundantDidntConverge = 12,
+    TooManyUnknowns = 20,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum SolverBackend {
+    Dense,
+    Sparse,
+}
+
+// Lets a solve be abandoned from outside, e.g. from the UI or from the
+// thread that started it; it's checked between iterations, so the solve
+// stops with whatever it had so far.
+#[derive(Clone, Default)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancelToken {
+    pub fn new() -> Self {
+        CancelToken::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn reset(&self) {
+        self.cancelled.store(false, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+struct SystemMatrix {
+    param: Vec<HParam>,
+    eq: Vec<usize>,
+
+    a_sym: Vec<Vec<(usize, Expr)>>,
+    a_num: SparseMatrix,
+    // The order A A^T is factored in for the sparse rank tests, found
+    // once by calculate_rank(); the free param tests after it only take
+    // entries away, so it keeps the fill down for them too.
+    rank_order: Vec<usize>,
+
+    b_num: Vec<f64>,
+    x: Vec<f64>,
+}
 
 pub struct System {
-    /*
-entity: HashMap<HEntity, Entity>,
-param: HashMap<HParam, Param>,*/}
+    pub param: IdList<HParam, Param>,
+    pub eq: Vec<Equation>,
+    pub dragged: Vec<HParam>,
+    pub backend: SolverBackend,
+    pub relaxed: bool,
+
+    // The budget for one solve, in milliseconds and Newton iterations.
+    pub timeout: Option<f64>,
+    pub max_iterations: usize,
+    pub cancel: Option<CancelToken>,
+    deadline: f64,
+
+    // The unknowns and equations as the last solve found them, before it
+    // marked what it solved known and substituted params away; solving
+    // again, as every drag does, starts over from these.
+    written: Option<(Vec<HParam>, Vec<Equation>)>,
+
+    mat: SystemMatrix,
+}
+
+impl SystemMatrix {
+    fn new() -> Self {
+        SystemMatrix {
+            param: Vec::new(),
+            eq: Vec::new(),
+            a_sym: Vec::new(),
+            a_num: SparseMatrix::new(0, 0),
+            rank_order: Vec::new(),
+            b_num: Vec::new(),
+            x: Vec::new(),
+        }
+    }
+}
+
+fn is_unreasonable(v: f64) -> bool {
+    v.is_nan() || !(-1e11..=1e11).contains(&v)
+}
+
+impl Default for System {
+    fn default() -> Self {
+        System::new()
+    }
+}
+
+impl System {
+    pub fn new() -> Self {
+        System {
+            param: IdList::new(),
+            eq: Vec::new(),
+            dragged: Vec::new(),
+            backend: SolverBackend::Sparse,
+            relaxed: false,
+            timeout: None,
+            max_iterations: SYSTEM_MAX_ITERATIONS,
+            cancel: None,
+            deadline: f64::INFINITY,
+            written: None,
+            mat: SystemMatrix::new(),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.param.clear();
+        self.eq.clear();
+        self.dragged.clear();
+        self.written = None;
+        self.mat = SystemMatrix::new();
+    }
+
+    pub fn add_param(&mut self, p: Param) {
+        self.param.add(p);
+    }
+
+    pub fn add_equation(&mut self, eq: Equation) {
+        self.eq.push(eq);
+    }
+
+    // Every param in the sketch is copied in as known; the ones that belong
+    // to this group's entities and constraints are the unknowns.
+    pub fn write_params_for_group(&mut self, sk: &Sketch, hg: HGroup) {
+        for p in sk.param.values() {
+            let mut p = p.clone();
+            p.tag = 0;
+            p.known = true;
+            p.substd = NO_PARAM;
+            self.add_param(p);
+        }
+
+        let mut unknown: Vec<HParam> = Vec::new();
+        for e in sk.entity.values().filter(|e| e.group == hg) {
+            unknown.extend(e.param.iter().filter(|h| **h != NO_PARAM));
+        }
+        for c in sk.constraint.values().filter(|c| c.group == hg) {
+            if c.val_p != NO_PARAM {
+                unknown.push(c.val_p);
+            }
+        }
+        for h in unknown {
+            if let Some(p) = self.param.get_mut(&h) {
+                p.known = false;
+            }
+        }
+    }
+
+    pub fn write_equations_for_group(&mut self, sk: &Sketch, hg: HGroup) {
+        let mut constraints: Vec<&Constraint> =
+            sk.constraint.values().filter(|c| c.group == hg).collect();
+        constraints.sort_by_key(|c| c.h);
+        let all_dims_reference = sk.group.get(&hg).is_some_and(|g| g.all_dims_reference);
+        for c in constraints {
+            if all_dims_reference && c.is_dimension() {
+                continue;
+            }
+            c.generate_equations(sk, &mut self.eq, false);
+        }
+
+        let mut entities: Vec<&EntityBase> = sk.entity.values().filter(|e| e.group == hg).collect();
+        entities.sort_by_key(|e| e.h);
+        for e in entities {
+            e.generate_equations(sk, &mut self.eq);
+        }
+
+        if let Some(g) = sk.group.get(&hg) {
+            g.generate_equations(sk, &mut self.eq);
+        }
+    }
+
+    fn out_of_time(&self) -> bool {
+        self.cancel.as_ref().is_some_and(|c| c.is_cancelled()) || get_milliseconds() > self.deadline
+    }
+
+    fn is_dragged(&self, h: HParam) -> bool {
+        self.dragged.contains(&h)
+    }
+
+    fn is_unknown(&self, h: HParam) -> bool {
+        match self.param.get(&h) {
+            Some(p) => !p.known && p.tag != VAR_SUBSTITUTED,
+            None => false,
+        }
+    }
+
+    // Equations of the form a - b = 0 between two unknowns (coincident
+    // points, horizontal and vertical lines, equal params) are solved by
+    // replacing a with b everywhere, which drops an unknown and an equation.
+    fn solve_by_substitution(&mut self) {
+        for i in 0..self.eq.len() {
+            if self.eq[i].tag != 0 {
+                continue;
+            }
+            let (a, b) = match &self.eq[i].e {
+                Expr::Minus(a, b) => match (&**a, &**b) {
+                    (Expr::Param(a), Expr::Param(b)) => (*a, *b),
+                    _ => continue,
+                },
+                _ => continue,
+            };
+            if a == b || !self.is_unknown(a) || !self.is_unknown(b) {
+                continue;
+            }
+            // A dragged param has to survive, so substitute the other one.
+            let (a, b) = if self.is_dragged(a) { (b, a) } else { (a, b) };
+
+            for eq in self.eq.iter_mut() {
+                eq.e.substitute(a, b);
+            }
+            for p in self.param.values_mut() {
+                if p.substd == a {
+                    p.substd = b;
+                }
+            }
+            let p = self.param.get_mut(&a).unwrap();
+            p.tag = VAR_SUBSTITUTED;
+            p.substd = b;
+            self.eq[i].tag = EQ_SUBSTITUTED;
+        }
+    }
+
+    fn write_back_substituted(&mut self) {
+        let substituted: Vec<(HParam, HParam)> = self
+            .param
+            .values()
+            .filter(|p| p.tag == VAR_SUBSTITUTED)
+            .map(|p| (p.h, p.substd))
+            .collect();
+        for (a, b) in substituted {
+            let b = self.param[&b].clone();
+            let p = self.param.get_mut(&a).unwrap();
+            p.val = b.val;
+            p.known = b.known;
+        }
+    }
+
+    // Once the system's solved, what it solved for is known, and so is
+    // whatever was substituted away for it.
+    fn mark_solved_known(&mut self) {
+        for h in &self.mat.param {
+            self.param.get_mut(h).unwrap().known = true;
+        }
+        self.write_back_substituted();
+    }
+
+    fn unsolve(&mut self) {
+        let (unknown, eq) = match self.written.take() {
+            Some(w) => w,
+            None => return,
+        };
+        for p in self.param.values_mut() {
+            if p.tag == VAR_SUBSTITUTED {
+                p.tag = 0;
+            }
+            p.substd = NO_PARAM;
+        }
+        for h in unknown {
+            if let Some(p) = self.param.get_mut(&h) {
+                p.known = false;
+            }
+        }
+        // Anything added since is kept as it is.
+        let n = eq.len().min(self.eq.len());
+        self.eq.splice(..n, eq);
+    }
+
+    fn write_jacobian(&mut self) -> bool {
+        self.mat = SystemMatrix::new();
+
+        let mut param: Vec<HParam> = self
+            .param
+            .values()
+            .filter(|p| !p.known && p.tag != VAR_SUBSTITUTED)
+            .map(|p| p.h)
+            .collect();
+        param.sort();
+        if param.len() > SYSTEM_MAX_UNKNOWNS {
+            return false;
+        }
+
+        for (i, eq) in self.eq.iter().enumerate() {
+            if eq.tag != 0 {
+                continue;
+            }
+            let f = eq.e.fold_constants();
+            let mut depends = Vec::new();
+            f.params(&mut depends);
+            let mut row: Vec<(usize, Expr)> = depends
+                .iter()
+                .filter_map(|p| param.binary_search(p).ok())
+                .map(|c| (c, f.partial_wrt(param[c]).fold_constants()))
+                .filter(|(_, d)| !d.is_constant(0.0))
+                .collect();
+            row.sort_by_key(|(c, _)| *c);
+            self.mat.a_sym.push(row);
+            self.mat.eq.push(i);
+        }
+
+        self.mat.b_num = vec![0.0; self.mat.eq.len()];
+        self.mat.x = vec![0.0; param.len()];
+        self.mat.param = param;
+        true
+    }
+
+    fn eval_jacobian(&mut self) {
+        let mut a = SparseMatrix::new(self.mat.eq.len(), self.mat.param.len());
+        for (r, row) in self.mat.a_sym.iter().enumerate() {
+            for (c, e) in row {
+                a.push(r, *c, e.eval(&self.param));
+            }
+        }
+        self.mat.a_num = a;
+    }
+
+    fn eval_residuals(&mut self) -> f64 {
+        let mut sum = 0.0;
+        for (r, i) in self.mat.eq.iter().enumerate() {
+            let v = self.eq[*i].e.eval(&self.param);
+            self.mat.b_num[r] = v;
+            sum += v * v;
+        }
+        sum
+    }
+
+    fn is_converged(&self) -> bool {
+        self.mat
+            .b_num
+            .iter()
+            .all(|b| !is_unreasonable(*b) && b.abs() <= CONVERGE_TOLERANCE)
+    }
+
+    fn take_step(&mut self, step: f64) -> bool {
+        let mut reasonable = true;
+        for (c, h) in self.mat.param.iter().enumerate() {
+            let p = self.param.get_mut(h).unwrap();
+            p.val -= self.mat.x[c] * step;
+            if is_unreasonable(p.val) {
+                reasonable = false;
+            }
+        }
+        reasonable
+    }
+
+    fn newton_solve(&mut self) -> bool {
+        let mut err = self.eval_residuals();
+        if self.is_converged() {
+            return true;
+        }
+        for _ in 0..self.max_iterations {
+            if self.out_of_time() {
+                return false;
+            }
+            self.eval_jacobian();
+            if !self.solve_least_squares() {
+                return false;
+            }
+
+            // Take the Newton step J(x_n) (x_{n+1} - x_n) = 0 - F(x_n),
+            // backing off by halves while that makes the residual worse.
+            let mut step = 1.0;
+            if !self.take_step(step) {
+                return false;
+            }
+            let mut new_err = self.eval_residuals();
+            for _ in 0..SYSTEM_MAX_STEP_HALVINGS {
+                if new_err <= err {
+                    break;
+                }
+                step *= 0.5;
+                self.take_step(-step);
+                new_err = self.eval_residuals();
+            }
+            err = new_err;
+
+            if self.mat.b_num.iter().any(|b| is_unreasonable(*b)) {
+                return false;
+            }
+            if self.is_converged() {
+                return true;
+            }
+        }
+        false
+    }
+
+    // When the equations can't all be satisfied, look for the params that
+    // minimise the sum of their squares instead.
+    fn relaxed_solve(&mut self) -> bool {
+        let mut err = self.eval_residuals();
+        if self.is_converged() {
+            return true;
+        }
+        let mut damping = RELAX_INITIAL_DAMPING;
+        for _ in 0..self.max_iterations {
+            if self.out_of_time() {
+                return false;
+            }
+            self.eval_jacobian();
+            if !self.solve_normal_equations(damping) {
+                return false;
+            }
+            if !self.take_step(1.0) {
+                return false;
+            }
+            let new_err = self.eval_residuals();
+            if new_err < err {
+                let small_step = self.mat.x.iter().all(|x| x.abs() <= CONVERGE_TOLERANCE);
+                let small_gain = err - new_err <= RELAX_GAIN_TOLERANCE * err;
+                err = new_err;
+                damping = (damping / 10.0).max(1e-12);
+                if self.is_converged() || small_step || small_gain {
+                    return true;
+                }
+            } else {
+                self.take_step(-1.0);
+                self.eval_residuals();
+                damping *= 10.0;
+                if damping > RELAX_MAX_DAMPING {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    // The damped Gauss-Newton step, (A^T A + damping D) x = A^T b.
+    fn solve_normal_equations(&mut self, damping: f64) -> bool {
+        let at = self.mat.a_num.transpose();
+        let atb = self.mat.a_num.mul_transpose(&self.mat.b_num);
+        let mut ata = at.aat_lower();
+        for (i, row) in ata.iter_mut().enumerate() {
+            let d = row.entry(i).or_insert(0.0);
+            *d += damping * (1.0 + *d);
+        }
+
+        self.mat.x = match self.backend {
+            SolverBackend::Dense => {
+                let n = ata.len();
+                let mut a = vec![vec![0.0; n]; n];
+                for (r, row) in ata.iter().enumerate() {
+                    for (c, v) in row {
+                        a[r][*c] = *v;
+                        a[*c][r] = *v;
+                    }
+                }
+                let mut x = vec![0.0; n];
+                if !System::solve_linear_system(&mut x, a, atb) {
+                    return false;
+                }
+                x
+            }
+            SolverBackend::Sparse => SparseLdlt::factor(&ata, 1e-20).solve(&atb),
+        };
+        self.mat.x.iter().all(|v| !is_unreasonable(*v))
+    }
+
+    fn solve_least_squares(&mut self) -> bool {
+        // Weight the columns, so that the minimum norm step below prefers
+        // to move some params more than others.
+        let scale: Vec<f64> = self
+            .mat
+            .param
+            .iter()
+            .map(|h| {
+                if self.is_dragged(*h) {
+                    DRAGGED_PARAM_SCALE
+                } else {
+                    1.0
+                }
+            })
+            .collect();
+        self.mat.a_num.scale_columns(&scale);
+
+        // Find the minimum norm step by solving (A A^T) z = b, x = A^T z.
+        let z = match self.backend {
+            SolverBackend::Dense => {
+                let a = self.mat.a_num.to_dense();
+                let m = a.len();
+                let mut aat = vec![vec![0.0; m]; m];
+                for r in 0..m {
+                    for c in r..m {
+                        let sum: f64 = a[r].iter().zip(a[c].iter()).map(|(x, y)| x * y).sum();
+                        aat[r][c] = sum;
+                        aat[c][r] = sum;
+                    }
+                }
+                let mut z = vec![0.0; m];
+                if !System::solve_linear_system(&mut z, aat, self.mat.b_num.clone()) {
+                    return false;
+                }
+                z
+            }
+            SolverBackend::Sparse => {
+                let ldlt = SparseLdlt::factor(&self.mat.a_num.aat_lower(), 1e-20);
+                let z = ldlt.solve(&self.mat.b_num);
+                if z.iter().any(|v| is_unreasonable(*v)) {
+                    return false;
+                }
+                z
+            }
+        };
+
+        self.mat.x = self.mat.a_num.mul_transpose(&z);
+        for (x, s) in self.mat.x.iter_mut().zip(scale.iter()) {
+            *x *= s;
+        }
+        true
+    }
+
+    #[allow(clippy::needless_range_loop)]
+    pub fn solve_linear_system(x: &mut [f64], mut a: Vec<Vec<f64>>, mut b: Vec<f64>) -> bool {
+        let n = b.len();
+
+        // Gaussian elimination with partial pivoting. A (near) singular
+        // pivot is skipped rather than reported; redundancy is detected by
+        // the rank test instead.
+        for i in 0..n {
+            let mut max = 0.0;
+            let mut imax = i;
+            for ip in i..n {
+                if a[ip][i].abs() > max {
+                    imax = ip;
+                    max = a[ip][i].abs();
+                }
+            }
+            if max < 1e-20 {
+                continue;
+            }
+
+            a.swap(i, imax);
+            b.swap(i, imax);
+
+            for ip in (i + 1)..n {
+                let temp = a[ip][i] / a[i][i];
+                if temp == 0.0 {
+                    continue;
+                }
+                for jp in i..n {
+                    a[ip][jp] -= temp * a[i][jp];
+                }
+                b[ip] -= temp * b[i];
+            }
+        }
+
+        for i in (0..n).rev() {
+            if a[i][i].abs() < 1e-20 {
+                x[i] = 0.0;
+                continue;
+            }
+            let mut temp = b[i];
+            for j in (i + 1)..n {
+                temp -= x[j] * a[i][j];
+            }
+            x[i] = temp / a[i][i];
+        }
+
+        x.iter().all(|v| !is_unreasonable(*v))
+    }
+
+    fn calculate_rank(&mut self) -> usize {
+        self.eval_jacobian();
+        if self.backend == SolverBackend::Sparse {
+            self.mat.rank_order = reverse_cuthill_mckee(&self.mat.a_num.aat_lower());
+        }
+        self.rank_of(&self.mat.a_num)
+    }
+
+    fn rank_of(&self, a: &SparseMatrix) -> usize {
+        if self.backend == SolverBackend::Sparse {
+            // The pivots of A A^T are the squared magnitudes Gram-Schmidt
+            // would leave on each row, taken in some other order, so count
+            // the ones that survive.
+            let aat = a.aat_lower();
+            let ldlt = if self.mat.rank_order.len() == aat.len() {
+                SparseLdlt::factor_permuted(&aat, RANK_MAG_TOLERANCE, &self.mat.rank_order)
+            } else {
+                SparseLdlt::factor(&aat, RANK_MAG_TOLERANCE)
+            };
+            if ldlt.closest_to_tolerance() > RANK_AMBIGUITY {
+                return ldlt.rank();
+            }
+            // Nearly dependent rows, and the order decides which side of
+            // the tolerance the small pivot falls; the rows' own order
+            // leaves it where the dense test would.
+            let in_order: Vec<usize> = (0..aat.len()).collect();
+            return SparseLdlt::factor_permuted(&aat, RANK_MAG_TOLERANCE, &in_order).rank();
+        }
+
+        // Gram-Schmidt on the rows of the Jacobian; every row that keeps a
+        // significant component of its own adds one to the rank.
+        let mut rows = a.to_dense();
+        let mut row_mag = vec![0.0; rows.len()];
+        let mut rank = 0;
+        for i in 0..rows.len() {
+            for j in 0..i {
+                if row_mag[j] == 0.0 {
+                    continue;
+                }
+                let dot: f64 = rows[i].iter().zip(rows[j].iter()).map(|(a, b)| a * b).sum();
+                let k = dot / row_mag[j];
+                let (head, tail) = rows.split_at_mut(i);
+                for (a, b) in tail[0].iter_mut().zip(head[j].iter()) {
+                    *a -= k * b;
+                }
+            }
+            let mag: f64 = rows[i].iter().map(|a| a * a).sum();
+            if mag > RANK_MAG_TOLERANCE {
+                row_mag[i] = mag;
+                rank += 1;
+            }
+        }
+        rank
+    }
+
+    fn unsatisfied_constraints(&self) -> Vec<HConstraint> {
+        let mut bad = Vec::new();
+        for (r, i) in self.mat.eq.iter().enumerate() {
+            let b = self.mat.b_num[r];
+            if b.is_nan() || b.abs() > CONVERGE_TOLERANCE {
+                let h = self.eq[*i].h;
+                if h.is_from_constraint() && !bad.contains(&h.constraint()) {
+                    bad.push(h.constraint());
+                }
+            }
+        }
+        bad
+    }
+
+    // A param is free if fixing it wouldn't add to the rank, which is to
+    // say that the constraints still leave it some way to move. That takes
+    // a rank test per unknown, so it's only done on request.
+    fn find_free_params(&mut self, rank: usize) -> bool {
+        let a = self.mat.a_num.clone();
+        let mut free = Vec::new();
+        for (c, h) in self.mat.param.iter().enumerate() {
+            if self.out_of_time() {
+                return false;
+            }
+            let mut fixed = a.clone();
+            fixed.clear_column(c);
+            if self.rank_of(&fixed) == rank {
+                free.push(*h);
+            }
+        }
+        for p in self.param.values_mut() {
+            let h = if p.tag == VAR_SUBSTITUTED {
+                p.substd
+            } else {
+                p.h
+            };
+            p.free = !p.known && free.contains(&h);
+        }
+        true
+    }
+
+    // The residual of each constraint, as the magnitude of the residuals of
+    // its equations.
+    fn constraint_residuals(&self) -> Vec<(HConstraint, f64)> {
+        let mut sum: Vec<(HConstraint, f64)> = Vec::new();
+        for eq in self.eq.iter() {
+            if !eq.h.is_from_constraint() {
+                continue;
+            }
+            let v = eq.e.eval(&self.param);
+            let hc = eq.h.constraint();
+            match sum.iter_mut().find(|(h, _)| *h == hc) {
+                Some((_, s)) => *s += v * v,
+                None => sum.push((hc, v * v)),
+            }
+        }
+        sum.into_iter().map(|(h, s)| (h, s.sqrt())).collect()
+    }
+
+    // Try dropping the equations of each constraint in turn from the system
+    // as it was before substitution; any constraint whose removal restores
+    // full rank is reported as one that would fix the group.
+    fn find_which_to_remove_to_fix_jacobian(
+        &self,
+        original: &[Equation],
+        solved: &mut GroupSolved,
+    ) {
+        let start = get_milliseconds();
+        solved.timeout = false;
+
+        let mut candidates: Vec<HConstraint> = Vec::new();
+        for eq in original {
+            if eq.h.is_from_constraint() && !candidates.contains(&eq.h.constraint()) {
+                candidates.push(eq.h.constraint());
+            }
+        }
+
+        for c in candidates {
+            if get_milliseconds() - start > solved.find_to_fix_timeout as f64 || self.out_of_time()
+            {
+                solved.timeout = true;
+                return;
+            }
+
+            let mut sys = System::new();
+            sys.backend = self.backend;
+            for p in self.param.values() {
+                let mut p = p.clone();
+                if p.tag == VAR_SUBSTITUTED {
+                    p.tag = 0;
+                    p.substd = NO_PARAM;
+                }
+                sys.add_param(p);
+            }
+            for eq in original {
+                if !(eq.h.is_from_constraint() && eq.h.constraint() == c) {
+                    sys.add_equation(eq.clone());
+                }
+            }
+
+            // Too big to check without this constraint, so it would be too
+            // big without any of the others as well.
+            sys.solve_by_substitution();
+            if !sys.write_jacobian() {
+                return;
+            }
+            if sys.calculate_rank() == sys.mat.eq.len() {
+                solved.remove.push(c);
+            }
+        }
+    }
+
+    pub fn solve(
+        &mut self,
+        solved: &mut GroupSolved,
+        allow_redundant: bool,
+        and_find_bad: bool,
+        and_find_free: bool,
+    ) -> SolveResult {
+        for p in self.param.values_mut() {
+            p.free = false;
+        }
+        solved.remove.clear();
+        solved.residual.clear();
+        solved.timeout = false;
+        self.unsolve();
+        let unknown = self
+            .param
+            .values()
+            .filter(|p| !p.known)
+            .map(|p| p.h)
+            .collect();
+        self.written = Some((unknown, self.eq.clone()));
+        self.deadline = match self.timeout {
+            Some(t) => get_milliseconds() + t,
+            None => f64::INFINITY,
+        };
+
+        let original = if and_find_bad {
+            self.eq.clone()
+        } else {
+            Vec::new()
+        };
+        // Substituting would make an a = b equation hold exactly, where a
+        // relaxed solve only fits it as well as the others allow.
+        if !self.relaxed {
+            self.solve_by_substitution();
+        }
+
+        if !self.write_jacobian() {
+            solved.how = SolveResult::TooManyUnknowns;
+            solved.dof = 0;
+            return solved.how;
+        }
+
+        let converged = if self.relaxed {
+            self.relaxed_solve()
+        } else {
+            self.newton_solve()
+        };
+        let rank = self.calculate_rank();
+        let rank_ok = rank == self.mat.eq.len();
+        solved.dof = (self.mat.param.len() - rank) as i32;
+
+        // Out of time, so hand back what we have without the slow extras.
+        if !converged && self.out_of_time() {
+            solved.timeout = true;
+            solved.how = if rank_ok {
+                SolveResult::DidntConverge
+            } else {
+                SolveResult::RedundantDidntConverge
+            };
+            self.write_back_substituted();
+            return solved.how;
+        }
+
+        if and_find_free && !self.find_free_params(rank) {
+            solved.timeout = true;
+        }
+
+        // A relaxed group is expected to be inconsistent, so there's nothing
+        // to remove; the residuals say how far off each constraint is.
+        if self.relaxed {
+            self.write_back_substituted();
+            solved.residual = self.constraint_residuals();
+            if converged {
+                self.mark_solved_known();
+            }
+            solved.how = if !converged {
+                SolveResult::DidntConverge
+            } else if rank_ok {
+                SolveResult::Okay
+            } else {
+                SolveResult::RedundantOkay
+            };
+            return solved.how;
+        }
+
+        // The substituted params have to be up to date before trying
+        // candidates for removal, since those are tested where we ended up.
+        self.write_back_substituted();
+
+        if and_find_bad {
+            if !rank_ok && !allow_redundant {
+                self.find_which_to_remove_to_fix_jacobian(&original, solved);
+            } else if !converged {
+                solved.remove = self.unsatisfied_constraints();
+            }
+        }
+
+        solved.how = if converged {
+            self.mark_solved_known();
+            if rank_ok {
+                SolveResult::Okay
+            } else {
+                SolveResult::RedundantOkay
+            }
+        } else if rank_ok {
+            SolveResult::DidntConverge
+        } else {
+            SolveResult::RedundantDidntConverge
+        };
+        solved.how
+    }
+
+    // Move the dragged params to their targets and re-solve, treating the
+    // targets as soft: the step minimises the weighted change of every
+    // param, with the dragged ones weighted heavily to stay put.
+    pub fn solve_dragged(
+        &mut self,
+        dragged: &[(HParam, f64)],
+        solved: &mut GroupSolved,
+    ) -> &IdList<HParam, Param> {
+        self.dragged.clear();
+        for (h, val) in dragged {
+            if let Some(p) = self.param.get_mut(h) {
+                p.val = *val;
+                self.dragged.push(*h);
+            }
+        }
+        self.solve(solved, false, false, false);
+        &self.param
+    }
+}
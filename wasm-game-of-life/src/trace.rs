@@ -0,0 +1,86 @@
+use crate::dsc::Vector;
+use crate::sketch::{
+    HEntity, HGroup, Request, RequestType, Sketch, SketchError, MAX_POINTS_IN_ENTITY,
+};
+
+impl Sketch {
+    // Starts or stops tracing a point; starting again clears its path.
+    pub fn set_traced(&mut self, h: HEntity, traced: bool) {
+        if !self.get_entity(h).is_point() {
+            panic!("Sketch::set_traced(...) of entity {}, not a point", h.v);
+        }
+        self.traced.retain(|t| *t != h);
+        self.trace_path.remove(&h);
+        if traced {
+            self.traced.push(h);
+            self.trace_path.insert(h, Vec::new());
+        }
+    }
+
+    pub fn clear_traces(&mut self) {
+        for path in self.trace_path.values_mut() {
+            path.clear();
+        }
+    }
+
+    // Adds where each traced point is now to its path, unless it hasn't
+    // moved. A point that's since been deleted stops being traced.
+    pub fn record_traces(&mut self) {
+        let entity = &self.entity;
+        self.traced.retain(|h| entity.contains_key(h));
+        for h in self.traced.clone() {
+            let p = self.get_entity(h).point_get_num(self);
+            let path = self.trace_path.entry(h).or_default();
+            if path.last().is_none_or(|last| !last.equals_with_def_tol(p)) {
+                path.push(p);
+            }
+        }
+    }
+
+    // The path as "x,y,z" lines, in world coordinates.
+    pub fn trace_csv(&self, h: HEntity) -> String {
+        let mut r = String::from("x,y,z\n");
+        for p in self.trace_path.get(&h).into_iter().flatten() {
+            r.push_str(&format!("{},{},{}\n", p.x, p.y, p.z));
+        }
+        r
+    }
+
+    // Turns the path into a cubic spline in group hg, drawn in the given
+    // workplane or else free in 3d. A cubic can only pass through so many
+    // points, so those are spread evenly along the path. It takes at least
+    // two points of path to make a curve.
+    pub fn trace_to_cubic(
+        &mut self,
+        h: HEntity,
+        hg: HGroup,
+        wrkpl: HEntity,
+    ) -> Result<HEntity, SketchError> {
+        let path = match self.trace_path.get(&h) {
+            Some(path) if path.len() >= 2 => path,
+            _ => return Err(SketchError::NoPath(h.v)),
+        };
+        let n = path.len().min(MAX_POINTS_IN_ENTITY - 2);
+        let through: Vec<Vector> = (0..n)
+            .map(|i| path[i * (path.len() - 1) / (n - 1)])
+            .collect();
+
+        // Start and finish, with the control points a third of the way to
+        // their neighbours so the ends head along the path.
+        let third = 1.0 / 3.0;
+        let mut pts = vec![through[0], through[0] + (through[1] - through[0]) * third];
+        pts.extend(through[1..n - 1].iter());
+        pts.push(through[n - 1] + (through[n - 2] - through[n - 1]) * third);
+        pts.push(through[n - 1]);
+
+        let mut r = Request::new(RequestType::Cubic, hg, wrkpl);
+        r.extra_points = pts.len() - 4;
+        let hr = self.add_request(r)?;
+        let cubic = hr.entity(0);
+        for (i, p) in pts.into_iter().enumerate() {
+            let hp = self.get_entity(cubic).point[i];
+            self.point_force_to(hp, p);
+        }
+        Ok(cubic)
+    }
+}
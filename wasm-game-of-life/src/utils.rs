@@ -1,3 +1,12 @@
+// A file on whatever the platform is, as SolveSpace's Platform::Path.
+#[derive(Clone)]
+pub struct PlatformPath {
+    pub raw: String,
+}
+
+// Only the app runs in the browser, where a panic needs the hook to show
+// up in the console.
+#[cfg(feature = "app")]
 pub fn set_panic_hook() {
     // When the `console_error_panic_hook` feature is enabled, we can call the
     // `set_panic_hook` function at least once during initialization, and then
@@ -8,3 +17,20 @@ pub fn set_panic_hook() {
     #[cfg(feature = "console_error_panic_hook")]
     console_error_panic_hook::set_once();
 }
+
+pub fn get_milliseconds() -> f64 {
+    // std::time is not available on wasm32-unknown-unknown, so ask the
+    // browser for the time there instead.
+    #[cfg(target_arch = "wasm32")]
+    {
+        js_sys::Date::now()
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        use std::time::{SystemTime, UNIX_EPOCH};
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs_f64() * 1000.0)
+            .unwrap_or(0.0)
+    }
+}
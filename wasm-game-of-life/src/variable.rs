@@ -0,0 +1,139 @@
+use crate::expr::{Expr, ExprError};
+use crate::sketch::{HConstraint, Sketch, Variable};
+
+use std::collections::HashMap;
+
+type Evaluated = HashMap<String, Result<f64, ExprError>>;
+
+impl Sketch {
+    // Adds or replaces a variable; its value is worked out by the next
+    // update_expressions(), and until then it keeps the one it had.
+    pub fn set_variable(&mut self, name: &str, expr: &str) -> Result<(), ExprError> {
+        if !Expr::is_valid_name(name) {
+            return Err(ExprError::Syntax(
+                0,
+                format!("'{}' can't be used as a variable name", name),
+            ));
+        }
+        let val = self.variable.get(name).map_or(0.0, |v| v.val);
+        self.variable.insert(
+            name.to_string(),
+            Variable {
+                name: name.to_string(),
+                expr: expr.to_string(),
+                val,
+                error: None,
+            },
+        );
+        // Any dimension in any group might use it.
+        self.mark_groups_dirty_from(i32::MIN);
+        Ok(())
+    }
+
+    pub fn remove_variable(&mut self, name: &str) {
+        self.variable.remove(name);
+        self.mark_groups_dirty_from(i32::MIN);
+    }
+
+    // None makes it a plain number again, keeping whatever value it had.
+    pub fn set_dimension_expr(&mut self, hc: HConstraint, expr: Option<&str>) {
+        let c = match self.constraint.get_mut(&hc) {
+            Some(c) => c,
+            None => panic!(
+                "Sketch::set_dimension_expr(...) of unknown constraint {}",
+                hc.v
+            ),
+        };
+        c.expr = expr.map(|s| s.to_string());
+        c.expr_error = None;
+        let hg = c.group;
+        self.mark_group_dirty(hg);
+    }
+
+    // Works out every variable, then every driving dimension that's given
+    // as an expression. Anything with an error keeps its last good value,
+    // so a typo doesn't collapse the sketch.
+    pub fn update_expressions(&mut self) {
+        let mut done = Evaluated::new();
+        let mut names: Vec<String> = self.variable.keys().cloned().collect();
+        names.sort();
+        for name in names.iter() {
+            let mut stack = Vec::new();
+            let _ = evaluate_variable(&self.variable, name, &mut stack, &mut done);
+        }
+        for v in self.variable.values_mut() {
+            match &done[&v.name] {
+                Ok(val) => {
+                    v.val = *val;
+                    v.error = None;
+                }
+                Err(e) => v.error = Some(e.clone()),
+            }
+        }
+
+        for c in self.constraint.values_mut() {
+            // A reference dimension is measured, whatever it says.
+            if c.reference || !c.is_dimension() {
+                continue;
+            }
+            if let Some(text) = &c.expr {
+                match Expr::parse_value(text, &mut |n| lookup(&done, n)) {
+                    Ok(val) => {
+                        c.val_a = val;
+                        c.expr_error = None;
+                    }
+                    Err(e) => c.expr_error = Some(e),
+                }
+            }
+        }
+    }
+}
+
+fn lookup(done: &Evaluated, name: &str) -> Result<Expr, ExprError> {
+    match done.get(name) {
+        Some(Ok(val)) => Ok(Expr::from_constant(*val)),
+        Some(Err(_)) => Err(ExprError::BadVariable(name.to_string())),
+        None => Err(ExprError::UnknownVariable(name.to_string())),
+    }
+}
+
+// Depth first through the variables each one refers to; meeting one that's
+// already on the stack means a cycle, and everything in it gets the error.
+fn evaluate_variable(
+    vars: &HashMap<String, Variable>,
+    name: &str,
+    stack: &mut Vec<String>,
+    done: &mut Evaluated,
+) -> Result<f64, ExprError> {
+    if let Some(r) = done.get(name) {
+        return r.clone();
+    }
+    if let Some(i) = stack.iter().position(|n| n == name) {
+        let mut cycle = stack[i..].to_vec();
+        cycle.push(name.to_string());
+        let e = ExprError::Cycle(cycle);
+        for n in stack[i..].iter() {
+            done.insert(n.clone(), Err(e.clone()));
+        }
+        return Err(e);
+    }
+    let v = match vars.get(name) {
+        Some(v) => v,
+        None => return Err(ExprError::UnknownVariable(name.to_string())),
+    };
+
+    stack.push(name.to_string());
+    let r = Expr::parse_value(
+        &v.expr,
+        &mut |n| match evaluate_variable(vars, n, stack, done) {
+            Ok(val) => Ok(Expr::from_constant(val)),
+            Err(e @ ExprError::UnknownVariable(_)) => Err(e),
+            Err(ExprError::Cycle(c)) if c.iter().any(|m| m == name) => Err(ExprError::Cycle(c)),
+            Err(_) => Err(ExprError::BadVariable(n.to_string())),
+        },
+    );
+    stack.pop();
+
+    // If this turned out to be part of a cycle, that's already recorded.
+    done.entry(name.to_string()).or_insert(r).clone()
+}
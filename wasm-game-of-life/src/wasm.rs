@@ -0,0 +1,348 @@
+// Every pointer these take is one new_app() or a listen call handed out,
+// owned by the JS side until it's passed to the matching drop; JS has no
+// way to call an unsafe fn, so that contract can't go in the signatures.
+#![allow(clippy::not_unsafe_ptr_arg_deref)]
+
+use crate::app::{App, AppView};
+use crate::delete::SketchItem;
+use crate::sketch::{
+    ConstraintType, GroupType, HConstraint, HEntity, HGroup, RequestType, SketchError,
+};
+use crate::utils::set_panic_hook;
+
+use js_sys::Function;
+use sodium_rust::Listener;
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+pub fn greet() -> String {
+    "Hello, wasm-game-of-life!".into()
+}
+
+pub struct UnsafeSendSync<A> {
+    pub value: A,
+}
+
+unsafe impl<A> Send for UnsafeSendSync<A> {}
+unsafe impl<A> Sync for UnsafeSendSync<A> {}
+
+#[wasm_bindgen]
+pub fn new_app() -> *mut App {
+    set_panic_hook();
+    Box::into_raw(Box::new(App::new()))
+}
+
+#[wasm_bindgen]
+pub fn drop_app(app: *mut App) {
+    unsafe {
+        drop(Box::from_raw(app));
+    }
+}
+
+#[wasm_bindgen]
+pub fn app_c_current_view_listen(app: *mut App, callback: Function) -> *mut Listener {
+    let app = unsafe { &mut *app };
+    let callback = UnsafeSendSync { value: callback };
+    // A listener has nowhere to report to, so an exception thrown by the
+    // callback is dropped here.
+    let listener = app.c_current_view.listen_weak(move |app_view: &AppView| {
+        let _ = match app_view {
+            AppView::LogInOrRegister => callback
+                .value
+                .call1(&JsValue::UNDEFINED, &JsValue::from_f64(1f64)),
+            AppView::ComponentDesigner(_) => callback
+                .value
+                .call1(&JsValue::UNDEFINED, &JsValue::from_f64(2f64)),
+        };
+    });
+    Box::into_raw(Box::new(listener))
+}
+
+#[wasm_bindgen]
+pub fn app_log_in(app: *mut App) {
+    let app = unsafe { &mut *app };
+    app.log_in();
+}
+
+// What went wrong with a call, thrown to the JS side as a string.
+fn js_error(e: SketchError) -> JsValue {
+    JsValue::from_str(&e.to_string())
+}
+
+#[wasm_bindgen]
+pub fn app_solve_group(app: *mut App, hg: u32) -> Result<i32, JsValue> {
+    let app = unsafe { &mut *app };
+    app.solve_group(HGroup { v: hg })
+        .map(|how| how as i32)
+        .map_err(js_error)
+}
+
+// A timeout of zero or less means no limit.
+#[wasm_bindgen]
+pub fn app_set_solve_timeout(app: *mut App, ms: f64) {
+    let app = unsafe { &mut *app };
+    app.set_solve_timeout(if ms > 0.0 { Some(ms) } else { None });
+}
+
+#[wasm_bindgen]
+pub fn app_solve_timed_out(app: *mut App, hg: u32) -> Result<bool, JsValue> {
+    let app = unsafe { &*app };
+    app.solve_timed_out(HGroup { v: hg }).map_err(js_error)
+}
+
+#[wasm_bindgen]
+pub fn app_group_dof(app: *mut App, hg: u32) -> Result<i32, JsValue> {
+    let app = unsafe { &*app };
+    app.group_dof(HGroup { v: hg }).map_err(js_error)
+}
+
+#[wasm_bindgen]
+pub fn app_free_entities(app: *mut App, hg: u32) -> Result<Vec<u32>, JsValue> {
+    let app = unsafe { &*app };
+    app.free_entities(HGroup { v: hg })
+        .map(|l| l.iter().map(|h| h.v).collect())
+        .map_err(js_error)
+}
+
+// Returns why the name can't be used, if it can't.
+#[wasm_bindgen]
+pub fn app_set_variable(app: *mut App, name: &str, expr: &str) -> Option<String> {
+    let app = unsafe { &mut *app };
+    app.set_variable(name, expr).err().map(|e| e.to_string())
+}
+
+#[wasm_bindgen]
+pub fn app_remove_variable(app: *mut App, name: &str) {
+    let app = unsafe { &mut *app };
+    app.remove_variable(name);
+}
+
+#[wasm_bindgen]
+pub fn app_variable_value(app: *mut App, name: &str) -> Option<f64> {
+    let app = unsafe { &*app };
+    app.variable_value(name)
+}
+
+#[wasm_bindgen]
+pub fn app_variable_error(app: *mut App, name: &str) -> Option<String> {
+    let app = unsafe { &*app };
+    app.variable_error(name).map(|e| e.to_string())
+}
+
+// An empty expression makes the dimension a plain number again.
+#[wasm_bindgen]
+pub fn app_set_dimension_expr(app: *mut App, hc: u32, expr: &str) -> Result<(), JsValue> {
+    let app = unsafe { &mut *app };
+    let expr = if expr.trim().is_empty() {
+        None
+    } else {
+        Some(expr)
+    };
+    app.set_dimension_expr(HConstraint { v: hc }, expr)
+        .map_err(js_error)
+}
+
+#[wasm_bindgen]
+pub fn app_dimension_error(app: *mut App, hc: u32) -> Result<Option<String>, JsValue> {
+    let app = unsafe { &*app };
+    app.dimension_error(HConstraint { v: hc })
+        .map(|e| e.map(|e| e.to_string()))
+        .map_err(js_error)
+}
+
+#[wasm_bindgen]
+pub fn app_save_configuration(app: *mut App, name: &str) {
+    let app = unsafe { &mut *app };
+    app.save_configuration(name);
+}
+
+#[wasm_bindgen]
+pub fn app_remove_configuration(app: *mut App, name: &str) {
+    let app = unsafe { &mut *app };
+    app.remove_configuration(name);
+}
+
+#[wasm_bindgen]
+pub fn app_configuration_count(app: *mut App) -> u32 {
+    let app = unsafe { &*app };
+    app.configuration_names().len() as u32
+}
+
+#[wasm_bindgen]
+pub fn app_configuration_name(app: *mut App, i: u32) -> Option<String> {
+    let app = unsafe { &*app };
+    app.configuration_names().get(i as usize).cloned()
+}
+
+// How many groups had to be redone; each one's result is there to read
+// back as usual.
+#[wasm_bindgen]
+pub fn app_regenerate(app: *mut App) -> u32 {
+    let app = unsafe { &mut *app };
+    app.regenerate().len() as u32
+}
+
+// False if there's no such configuration; otherwise each group's result is
+// there to read back as usual.
+#[wasm_bindgen]
+pub fn app_regenerate_configuration(app: *mut App, name: &str) -> bool {
+    let app = unsafe { &mut *app };
+    app.regenerate_configuration(name).is_some()
+}
+
+// One CSV row per step; see step_frames_csv(..).
+#[wasm_bindgen]
+pub fn app_step_dimension(
+    app: *mut App,
+    hc: u32,
+    a: f64,
+    b: f64,
+    steps: u32,
+) -> Result<String, JsValue> {
+    let app = unsafe { &mut *app };
+    app.step_dimension_csv(HConstraint { v: hc }, a, b, steps)
+        .map_err(js_error)
+}
+
+// z is ignored for a point in a workplane.
+#[wasm_bindgen]
+pub fn app_drag_point(
+    app: *mut App,
+    hg: u32,
+    he: u32,
+    x: f64,
+    y: f64,
+    z: f64,
+) -> Result<i32, JsValue> {
+    let app = unsafe { &mut *app };
+    app.drag_point(HGroup { v: hg }, HEntity { v: he }, &[x, y, z])
+        .map(|how| how as i32)
+        .map_err(js_error)
+}
+
+// The new request's handle, or 0 if there's no request of that type.
+// Throws if the request can't be made as asked.
+#[wasm_bindgen]
+pub fn app_add_request(
+    app: *mut App,
+    type_: u32,
+    hg: u32,
+    wrkpl: u32,
+    extra_points: u32,
+) -> Result<u32, JsValue> {
+    let app = unsafe { &mut *app };
+    match RequestType::from_u32(type_) {
+        Some(t) => app
+            .add_request(
+                t,
+                HGroup { v: hg },
+                HEntity { v: wrkpl },
+                extra_points as usize,
+            )
+            .map(|hr| hr.v)
+            .map_err(js_error),
+        None => Ok(0),
+    }
+}
+
+// The new group's handle, or 0 if there's no group of that type. op_a and
+// wrkpl are 0 if it isn't made from a group, or drawn in a workplane.
+#[wasm_bindgen]
+pub fn app_add_group(app: *mut App, type_: u32, op_a: u32, wrkpl: u32) -> Result<u32, JsValue> {
+    let app = unsafe { &mut *app };
+    match GroupType::from_u32(type_) {
+        Some(t) => app
+            .add_group(t, HGroup { v: op_a }, HEntity { v: wrkpl })
+            .map(|hg| hg.v)
+            .map_err(js_error),
+        None => Ok(0),
+    }
+}
+
+// The new constraint's handle, or 0 if there's no constraint of that type.
+// pts are pt_a and pt_b, and entities entity_a to entity_d, as far as the
+// constraint needs them.
+#[wasm_bindgen]
+pub fn app_add_constraint(
+    app: *mut App,
+    type_: u32,
+    hg: u32,
+    wrkpl: u32,
+    val_a: f64,
+    pts: Vec<u32>,
+    entities: Vec<u32>,
+) -> Result<u32, JsValue> {
+    let app = unsafe { &mut *app };
+    let handles = |l: Vec<u32>| -> Vec<HEntity> { l.into_iter().map(|v| HEntity { v }).collect() };
+    match ConstraintType::from_u32(type_) {
+        Some(t) => app
+            .add_constraint(
+                t,
+                HGroup { v: hg },
+                HEntity { v: wrkpl },
+                val_a,
+                &handles(pts),
+                &handles(entities),
+            )
+            .map(|hc| hc.v)
+            .map_err(js_error),
+        None => Ok(0),
+    }
+}
+
+// Each returns what else had to go with it, as in "also removed 3
+// constraints", or "" if nothing did.
+#[wasm_bindgen]
+pub fn app_delete_group(app: *mut App, hg: u32) -> String {
+    let app = unsafe { &mut *app };
+    let items = [SketchItem::Group(HGroup { v: hg })];
+    app.delete(&items).also_removed(&items)
+}
+
+#[wasm_bindgen]
+pub fn app_delete_entity(app: *mut App, he: u32) -> String {
+    let app = unsafe { &mut *app };
+    let items = [SketchItem::Entity(HEntity { v: he })];
+    app.delete(&items).also_removed(&items)
+}
+
+#[wasm_bindgen]
+pub fn app_delete_constraint(app: *mut App, hc: u32) -> String {
+    let app = unsafe { &mut *app };
+    let items = [SketchItem::Constraint(HConstraint { v: hc })];
+    app.delete(&items).also_removed(&items)
+}
+
+#[wasm_bindgen]
+pub fn app_set_traced(app: *mut App, he: u32, traced: bool) -> Result<(), JsValue> {
+    let app = unsafe { &mut *app };
+    app.set_traced(HEntity { v: he }, traced).map_err(js_error)
+}
+
+#[wasm_bindgen]
+pub fn app_clear_traces(app: *mut App) {
+    let app = unsafe { &mut *app };
+    app.clear_traces();
+}
+
+#[wasm_bindgen]
+pub fn app_trace_csv(app: *mut App, he: u32) -> String {
+    let app = unsafe { &*app };
+    app.trace_csv(HEntity { v: he })
+}
+
+// The new cubic's handle.
+#[wasm_bindgen]
+pub fn app_trace_to_cubic(app: *mut App, he: u32, hg: u32, wrkpl: u32) -> Result<u32, JsValue> {
+    let app = unsafe { &mut *app };
+    app.trace_to_cubic(HEntity { v: he }, HGroup { v: hg }, HEntity { v: wrkpl })
+        .map(|h| h.v)
+        .map_err(js_error)
+}
+
+#[wasm_bindgen]
+pub fn drop_listener(listener: *mut Listener) {
+    unsafe {
+        drop(Box::from_raw(listener));
+    }
+}
@@ -0,0 +1,81 @@
+// A solve that runs out of time, iterations or is cancelled stops where it
+// is and says so; one that never got going leaves the params alone.
+
+use wasm_game_of_life::expr::Expr;
+use wasm_game_of_life::sketch::{Equation, GroupSolved, HEquation, HParam, Param, NO_PARAM};
+use wasm_game_of_life::system::{CancelToken, SolveResult, System};
+
+const X1: HParam = HParam { v: 1 };
+const X2: HParam = HParam { v: 2 };
+
+// x1 + x2 = 10, from x1 = 1 and x2 = 2, so it takes a Newton step.
+fn system() -> System {
+    let mut sys = System::new();
+    for (h, val) in [(X1, 1.0), (X2, 2.0)] {
+        sys.add_param(Param {
+            tag: 0,
+            h,
+            val,
+            known: false,
+            free: false,
+            substd: NO_PARAM,
+        });
+    }
+    sys.add_equation(Equation {
+        tag: 0,
+        h: HEquation { v: 0 },
+        e: Expr::from_param(X1) + Expr::from_param(X2) - Expr::from_constant(10.0),
+    });
+    sys
+}
+
+fn solve(sys: &mut System) -> GroupSolved {
+    let mut solved = GroupSolved::default();
+    sys.solve(&mut solved, false, false, false);
+    solved
+}
+
+fn check_untouched(sys: &System) {
+    assert_eq!(sys.param[&X1].val, 1.0);
+    assert_eq!(sys.param[&X2].val, 2.0);
+}
+
+#[test]
+fn unlimited_solves() {
+    let mut sys = system();
+    let solved = solve(&mut sys);
+    assert_eq!(solved.how, SolveResult::Okay);
+    assert!(!solved.timeout);
+    assert!((sys.param[&X1].val + sys.param[&X2].val - 10.0).abs() < 1e-9);
+}
+
+#[test]
+fn cancelled_before_solve() {
+    let mut sys = system();
+    let cancel = CancelToken::new();
+    cancel.cancel();
+    sys.cancel = Some(cancel);
+    let solved = solve(&mut sys);
+    assert!(solved.how != SolveResult::Okay);
+    assert!(solved.timeout);
+    check_untouched(&sys);
+}
+
+#[test]
+fn out_of_time() {
+    let mut sys = system();
+    sys.timeout = Some(-1.0);
+    let solved = solve(&mut sys);
+    assert_eq!(solved.how, SolveResult::DidntConverge);
+    assert!(solved.timeout);
+    check_untouched(&sys);
+}
+
+#[test]
+fn out_of_iterations() {
+    let mut sys = system();
+    sys.max_iterations = 0;
+    let solved = solve(&mut sys);
+    assert_eq!(solved.how, SolveResult::DidntConverge);
+    check_untouched(&sys);
+}
@@ -0,0 +1,79 @@
+// Sketches the integration tests build on, made the way the designer makes
+// them: groups, requests and constraints added by handle. Each test crate
+// uses only some of these.
+#![allow(dead_code)]
+
+use wasm_game_of_life::dsc::Vector;
+use wasm_game_of_life::sketch::{
+    Constraint, ConstraintType, Group, GroupType, HConstraint, HEntity, HGroup, HRequest, Request,
+    RequestType, Sketch, FREE_IN_3D,
+};
+
+pub fn constraint(type_: ConstraintType, hg: HGroup, wrkpl: HEntity) -> Constraint {
+    let mut c = Constraint::new(HConstraint { v: 0 }, type_, hg);
+    c.workplane = wrkpl;
+    c
+}
+
+// A 3d group holding a workplane through the origin, normal to z.
+pub fn workplane(sk: &mut Sketch) -> (HGroup, HEntity) {
+    let g1 = sk.add_group(Group::new(GroupType::Drawing3D)).unwrap();
+    let hw = sk
+        .add_request(Request::new(RequestType::Workplane, g1, FREE_IN_3D))
+        .unwrap();
+    (g1, hw.entity(0))
+}
+
+// A group drawn in wrkpl, after every group so far.
+pub fn sketch_in(sk: &mut Sketch, wrkpl: HEntity) -> HGroup {
+    let mut g = Group::new(GroupType::DrawingWorkplane);
+    g.active_workplane = wrkpl;
+    sk.add_group(g).unwrap()
+}
+
+// A line segment from a to b; its endpoints are entity(1) and entity(2).
+pub fn line(sk: &mut Sketch, hg: HGroup, wrkpl: HEntity, a: Vector, b: Vector) -> HRequest {
+    let hl = sk
+        .add_request(Request::new(RequestType::LineSegment, hg, wrkpl))
+        .unwrap();
+    sk.point_force_to(hl.entity(1), a);
+    sk.point_force_to(hl.entity(2), b);
+    hl
+}
+
+pub fn where_dragged(sk: &mut Sketch, hg: HGroup, wrkpl: HEntity, pt: HEntity) -> HConstraint {
+    let mut c = constraint(ConstraintType::WhereDragged, hg, wrkpl);
+    c.pt_a = pt;
+    sk.add_constraint(c).unwrap()
+}
+
+pub fn distance(
+    sk: &mut Sketch,
+    hg: HGroup,
+    wrkpl: HEntity,
+    a: HEntity,
+    b: HEntity,
+    val: f64,
+) -> HConstraint {
+    let mut c = constraint(ConstraintType::PtPtDistance, hg, wrkpl);
+    c.pt_a = a;
+    c.pt_b = b;
+    c.val_a = val;
+    sk.add_constraint(c).unwrap()
+}
+
+// A line in its own group in the workplane, its first end held where it
+// is and a distance between its ends, so one degree of freedom is left.
+pub fn pinned_line(sk: &mut Sketch, wrkpl: HEntity, len: f64) -> (HGroup, HRequest, HConstraint) {
+    let hg = sketch_in(sk, wrkpl);
+    let hl = line(
+        sk,
+        hg,
+        wrkpl,
+        Vector::new(0.0, 0.0, 0.0),
+        Vector::new(3.0, 1.0, 0.0),
+    );
+    where_dragged(sk, hg, wrkpl, hl.entity(1));
+    let hc = distance(sk, hg, wrkpl, hl.entity(1), hl.entity(2), len);
+    (hg, hl, hc)
+}
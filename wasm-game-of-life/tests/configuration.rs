@@ -0,0 +1,133 @@
+// A configuration puts back the dimensions, variables and suppress states
+// it was captured with: a plain dimension stays plain, one given as an
+// expression gets its expression back, and anything deleted since is
+// skipped.
+
+mod common;
+
+use common::{pinned_line, workplane};
+use wasm_game_of_life::delete::SketchItem;
+use wasm_game_of_life::sketch::{DimensionValue, HConstraint, HRequest, Sketch};
+use wasm_game_of_life::system::SolveResult;
+
+fn length(sk: &Sketch, hl: HRequest) -> f64 {
+    let a = sk.get_entity(hl.entity(1)).point_get_num(sk);
+    let b = sk.get_entity(hl.entity(2)).point_get_num(sk);
+    (b - a).magnitude()
+}
+
+fn set_val(sk: &mut Sketch, hc: HConstraint, val: f64) {
+    sk.constraint.get_mut(&hc).unwrap().val_a = val;
+}
+
+#[test]
+fn capture_and_apply() {
+    let mut sk = Sketch::new();
+    let (_, wrkpl) = workplane(&mut sk);
+    let (_, hl1, hc1) = pinned_line(&mut sk, wrkpl, 5.0);
+    let (_, hl2, hc2) = pinned_line(&mut sk, wrkpl, 1.0);
+    sk.set_variable("w", "3").unwrap();
+    sk.set_dimension_expr(hc2, Some("w"));
+    let small = sk.capture_configuration("small");
+    assert_eq!(small.dimension[&hc1], DimensionValue::Value(5.0));
+    assert_eq!(small.dimension[&hc2], DimensionValue::Expr("w".to_string()));
+    assert_eq!(small.variable["w"], "3");
+    sk.set_configuration(small);
+
+    set_val(&mut sk, hc1, 8.0);
+    sk.set_variable("w", "4").unwrap();
+    sk.set_dimension_expr(hc2, Some("w + 1"));
+    let big = sk.capture_configuration("big");
+    sk.set_configuration(big);
+
+    let r = sk.regenerate_configuration("small").unwrap();
+    assert!(r.iter().all(|(_, how)| *how == SolveResult::Okay));
+    assert_eq!(sk.active_configuration.as_deref(), Some("small"));
+    assert!(sk.constraint[&hc1].expr.is_none());
+    assert_eq!(sk.constraint[&hc2].expr.as_deref(), Some("w"));
+    assert!((length(&sk, hl1) - 5.0).abs() < 1e-9);
+    assert!((length(&sk, hl2) - 3.0).abs() < 1e-9);
+
+    sk.regenerate_configuration("big").unwrap();
+    assert!(sk.constraint[&hc1].expr.is_none());
+    assert!((length(&sk, hl1) - 8.0).abs() < 1e-9);
+    assert!((length(&sk, hl2) - 5.0).abs() < 1e-9);
+
+    // A plain dimension is still plain, so it can be changed directly.
+    set_val(&mut sk, hc1, 2.0);
+    sk.regenerate_all();
+    assert!((length(&sk, hl1) - 2.0).abs() < 1e-9);
+
+    assert!(!sk.apply_configuration("huge"));
+    assert!(sk.regenerate_configuration("huge").is_none());
+    assert_eq!(sk.active_configuration.as_deref(), Some("big"));
+}
+
+#[test]
+fn deleted_items_are_skipped() {
+    let mut sk = Sketch::new();
+    let (_, wrkpl) = workplane(&mut sk);
+    let (_, hl1, hc1) = pinned_line(&mut sk, wrkpl, 5.0);
+    let (g3, _, hc2) = pinned_line(&mut sk, wrkpl, 1.0);
+    sk.set_variable("w", "3").unwrap();
+    let cfg = sk.capture_configuration("all");
+    sk.set_configuration(cfg);
+
+    sk.delete(&[SketchItem::Constraint(hc1), SketchItem::Group(g3)]);
+    sk.remove_variable("w");
+    assert!(sk.apply_configuration("all"));
+    assert!(sk.constraint.get(&hc1).is_none());
+    assert!(sk.constraint.get(&hc2).is_none());
+    assert!(sk.group.get(&g3).is_none());
+    assert!(!sk.variable.contains_key("w"));
+
+    sk.regenerate_all();
+    assert!(sk.entity.contains_key(&hl1.entity(0)));
+}
+
+#[test]
+fn suppress_states() {
+    let mut sk = Sketch::new();
+    let (g1, wrkpl) = workplane(&mut sk);
+    let (g2, _, _) = pinned_line(&mut sk, wrkpl, 5.0);
+    sk.group.get_mut(&g2).unwrap().suppress = true;
+    let cfg = sk.capture_configuration("without");
+    sk.set_configuration(cfg);
+    sk.group.get_mut(&g2).unwrap().suppress = false;
+    let cfg = sk.capture_configuration("with");
+    sk.set_configuration(cfg);
+    sk.regenerate_all();
+
+    assert!(sk.apply_configuration("without"));
+    assert!(sk.get_group(g2).suppress);
+    assert!(!sk.get_group(g1).suppress);
+    assert!(!sk.get_group(g1).clean && !sk.get_group(g2).clean);
+
+    assert!(sk.apply_configuration("with"));
+    assert!(!sk.get_group(g2).suppress);
+}
+
+#[test]
+fn replaced_by_name_and_removed() {
+    let mut sk = Sketch::new();
+    let (_, wrkpl) = workplane(&mut sk);
+    let (_, _, hc1) = pinned_line(&mut sk, wrkpl, 5.0);
+    for name in ["small", "big"] {
+        let cfg = sk.capture_configuration(name);
+        sk.set_configuration(cfg);
+    }
+    assert!(sk.apply_configuration("big"));
+
+    set_val(&mut sk, hc1, 8.0);
+    let big = sk.capture_configuration("big");
+    sk.set_configuration(big);
+    assert_eq!(sk.configuration.len(), 2);
+    assert_eq!(
+        sk.get_configuration("big").unwrap().dimension[&hc1],
+        DimensionValue::Value(8.0)
+    );
+
+    sk.remove_configuration("big");
+    assert!(sk.get_configuration("big").is_none());
+    assert!(sk.active_configuration.is_none());
+}
@@ -0,0 +1,308 @@
+// Each type of constraint is written as equations that are zero just when
+// it's met. Here they're checked one at a time on a layout that meets them
+// all, then a few of them together are solved for.
+
+use wasm_game_of_life::sketch::{
+    Constraint, ConstraintType, EntityBase, EntityBaseType, Equation, GroupSolved, HConstraint,
+    HEntity, HGroup, HParam, Param, Sketch, NO_PARAM,
+};
+use wasm_game_of_life::system::{SolveResult, System};
+
+const WORKPLANE: HEntity = HEntity { v: 1 };
+const G_BASE: HGroup = HGroup { v: 1 };
+const G_SKETCH: HGroup = HGroup { v: 2 };
+
+// A workplane through the origin, normal to z, and whatever's drawn in it.
+struct Drawing {
+    sk: Sketch,
+    next_entity: u32,
+    next_param: u32,
+    next_constraint: u32,
+}
+
+impl Drawing {
+    fn new() -> Self {
+        let mut d = Drawing {
+            sk: Sketch::new(),
+            next_entity: 2,
+            next_param: 1,
+            next_constraint: 1,
+        };
+        let mut o = d.entity(EntityBaseType::PointIn3D, G_BASE);
+        let mut n = d.entity(EntityBaseType::NormalIn3D, G_BASE);
+        for i in 0..4 {
+            n.param[i] = d.param(if i == 0 { 1.0 } else { 0.0 });
+            if i < 3 {
+                o.param[i] = d.param(0.0);
+            }
+        }
+        let mut w = EntityBase::new(WORKPLANE, EntityBaseType::Workplane);
+        w.group = G_BASE;
+        w.point[0] = o.h;
+        w.normal = n.h;
+        for e in [o, n, w] {
+            d.sk.entity.add(e);
+        }
+        d
+    }
+
+    fn entity(&mut self, type_: EntityBaseType, hg: HGroup) -> EntityBase {
+        let mut e = EntityBase::new(
+            HEntity {
+                v: self.next_entity,
+            },
+            type_,
+        );
+        self.next_entity += 1;
+        e.group = hg;
+        e
+    }
+
+    fn param(&mut self, val: f64) -> HParam {
+        let h = HParam { v: self.next_param };
+        self.next_param += 1;
+        self.sk.param.add(Param {
+            tag: 0,
+            h,
+            val,
+            known: false,
+            free: false,
+            substd: NO_PARAM,
+        });
+        h
+    }
+
+    fn point(&mut self, u: f64, v: f64) -> HEntity {
+        let mut p = self.entity(EntityBaseType::PointIn2D, G_SKETCH);
+        p.workplane = WORKPLANE;
+        p.param[0] = self.param(u);
+        p.param[1] = self.param(v);
+        let h = p.h;
+        self.sk.entity.add(p);
+        h
+    }
+
+    fn line(&mut self, a: HEntity, b: HEntity) -> HEntity {
+        let mut l = self.entity(EntityBaseType::LineSegment, G_SKETCH);
+        l.workplane = WORKPLANE;
+        l.point[0] = a;
+        l.point[1] = b;
+        let h = l.h;
+        self.sk.entity.add(l);
+        h
+    }
+
+    fn constrain(&mut self, type_: ConstraintType, f: impl Fn(&mut Constraint)) -> HConstraint {
+        let h = HConstraint {
+            v: self.next_constraint,
+        };
+        self.next_constraint += 1;
+        let mut c = Constraint::new(h, type_, G_SKETCH);
+        c.workplane = WORKPLANE;
+        f(&mut c);
+        if let Some(p) = c.generate_params(&self.sk) {
+            self.sk.param.add(p);
+        }
+        self.sk.constraint.add(c);
+        h
+    }
+
+    fn residuals(&self, hc: HConstraint) -> Vec<f64> {
+        let mut l: Vec<Equation> = Vec::new();
+        self.sk.constraint[&hc].generate_equations(&self.sk, &mut l, false);
+        l.iter().map(|eq| eq.e.eval(&self.sk.param)).collect()
+    }
+
+    fn point_at(&self, h: HEntity) -> (f64, f64) {
+        let e = self.sk.get_entity(h);
+        (
+            self.sk.get_param(e.param[0]).val,
+            self.sk.get_param(e.param[1]).val,
+        )
+    }
+}
+
+#[test]
+fn each_holds_where_its_met() {
+    use ConstraintType::*;
+
+    let mut d = Drawing::new();
+    // A 4 by 3 rectangle, the midpoint of its bottom twice over, and a
+    // point with its mirror images in both axes.
+    let a = d.point(0.0, 0.0);
+    let b = d.point(4.0, 0.0);
+    let c = d.point(0.0, 3.0);
+    let e = d.point(4.0, 3.0);
+    let m = d.point(2.0, 0.0);
+    let m2 = d.point(2.0, 0.0);
+    let s = d.point(2.0, 1.0);
+    let s_u = d.point(-2.0, 1.0);
+    let s_v = d.point(2.0, -1.0);
+    let ab = d.line(a, b);
+    let ce = d.line(c, e);
+    let ac = d.line(a, c);
+    let ae = d.line(a, e);
+    let ec = d.line(e, c);
+    let diagonal = (4.0f64 / 5.0).acos().to_degrees();
+
+    let met = vec![
+        d.constrain(PointsCoincident, |k| {
+            k.pt_a = m;
+            k.pt_b = m2;
+        }),
+        d.constrain(PtPtDistance, |k| {
+            k.pt_a = a;
+            k.pt_b = b;
+            k.val_a = 4.0;
+        }),
+        d.constrain(PtLineDistance, |k| {
+            k.pt_a = e;
+            k.entity_a = ab;
+            // Signed, and e is to the left of a to b.
+            k.val_a = -3.0;
+        }),
+        d.constrain(PtOnLine, |k| {
+            k.pt_a = m;
+            k.entity_a = ab;
+        }),
+        d.constrain(AtMidpoint, |k| {
+            k.pt_a = m;
+            k.entity_a = ab;
+        }),
+        d.constrain(EqualLengthLines, |k| {
+            k.entity_a = ab;
+            k.entity_b = ce;
+        }),
+        d.constrain(LengthRatio, |k| {
+            k.entity_a = ab;
+            k.entity_b = ac;
+            k.val_a = 4.0 / 3.0;
+        }),
+        d.constrain(LengthDifference, |k| {
+            k.entity_a = ae;
+            k.entity_b = ab;
+            k.val_a = 1.0;
+        }),
+        d.constrain(EqLenPtLineD, |k| {
+            k.entity_a = ac;
+            k.pt_a = e;
+            k.entity_b = ab;
+        }),
+        d.constrain(EqPtLnDistances, |k| {
+            k.pt_a = e;
+            k.entity_a = ab;
+            // b is as far to the left of e to c.
+            k.pt_b = b;
+            k.entity_b = ec;
+        }),
+        d.constrain(Horizontal, |k| k.entity_a = ab),
+        d.constrain(Vertical, |k| k.entity_a = ac),
+        d.constrain(Horizontal, |k| {
+            k.pt_a = s;
+            k.pt_b = s_u;
+        }),
+        d.constrain(Parallel, |k| {
+            k.entity_a = ab;
+            k.entity_b = ce;
+        }),
+        d.constrain(Perpendicular, |k| {
+            k.entity_a = ab;
+            k.entity_b = ac;
+        }),
+        d.constrain(Angle, |k| {
+            k.entity_a = ab;
+            k.entity_b = ae;
+            k.val_a = diagonal;
+        }),
+        d.constrain(SymmetricHoriz, |k| {
+            k.pt_a = s;
+            k.pt_b = s_u;
+        }),
+        d.constrain(SymmetricVert, |k| {
+            k.pt_a = s;
+            k.pt_b = s_v;
+        }),
+        d.constrain(SymmetricLine, |k| {
+            k.pt_a = s;
+            k.pt_b = s_v;
+            k.entity_a = ab;
+        }),
+        d.constrain(WhereDragged, |k| k.pt_a = s),
+    ];
+    for hc in &met {
+        let type_ = d.sk.constraint[hc].type_;
+        let r = d.residuals(*hc);
+        assert!(!r.is_empty(), "{:?}", type_);
+        assert!(r.iter().all(|v| v.abs() < 1e-9), "{:?}: {:?}", type_, r);
+    }
+
+    // Move a corner aside, and what it's in is no longer met.
+    let cu = d.sk.get_entity(c).param[0];
+    d.sk.param.get_mut(&cu).unwrap().val = 0.5;
+    for hc in &met {
+        let k = &d.sk.constraint[hc];
+        let moved = [k.entity_a, k.entity_b].contains(&ac) || k.pt_a == c;
+        let r = d.residuals(*hc);
+        if moved {
+            assert!(r.iter().any(|v| v.abs() > 1e-3), "{:?}", k.type_);
+        }
+    }
+}
+
+// A right triangle, one corner held and its two legs given, from a rough
+// sketch of it.
+#[test]
+fn solve_a_triangle() {
+    use ConstraintType::*;
+
+    let mut d = Drawing::new();
+    let a = d.point(0.0, 0.0);
+    let b = d.point(3.7, 0.3);
+    let c = d.point(0.4, 2.6);
+    let ab = d.line(a, b);
+    let ac = d.line(a, c);
+    d.constrain(WhereDragged, |k| k.pt_a = a);
+    d.constrain(Horizontal, |k| k.entity_a = ab);
+    d.constrain(Perpendicular, |k| {
+        k.entity_a = ab;
+        k.entity_b = ac;
+    });
+    d.constrain(PtPtDistance, |k| {
+        k.pt_a = a;
+        k.pt_b = b;
+        k.val_a = 4.0;
+    });
+    d.constrain(PtPtDistance, |k| {
+        k.pt_a = a;
+        k.pt_b = c;
+        k.val_a = 3.0;
+    });
+
+    let mut sys = System::new();
+    sys.write_params_for_group(&d.sk, G_SKETCH);
+    sys.write_equations_for_group(&d.sk, G_SKETCH);
+    let mut solved = GroupSolved {
+        how: SolveResult::Okay,
+        dof: -1,
+        find_to_fix_timeout: 0,
+        timeout: false,
+        remove: Vec::new(),
+        residual: Vec::new(),
+    };
+    assert_eq!(
+        sys.solve(&mut solved, false, false, false),
+        SolveResult::Okay
+    );
+    assert_eq!(solved.dof, 0);
+    d.sk.param = sys.param.clone();
+
+    for (h, (u, v)) in [(a, (0.0, 0.0)), (b, (4.0, 0.0)), (c, (0.0, 3.0))] {
+        let (pu, pv) = d.point_at(h);
+        assert!(
+            (pu - u).abs() < 1e-6 && (pv - v).abs() < 1e-6,
+            "{} {}",
+            pu,
+            pv
+        );
+    }
+}
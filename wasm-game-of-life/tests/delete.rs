@@ -0,0 +1,140 @@
+// Deleting takes whatever depends on what's deleted with it, down to the
+// groups drawn in a workplane that's gone, and says what else went.
+
+mod common;
+
+use common::{constraint, distance, line, sketch_in, where_dragged, workplane};
+use wasm_game_of_life::delete::SketchItem;
+use wasm_game_of_life::dsc::Vector;
+use wasm_game_of_life::sketch::{
+    ConstraintType, HConstraint, HEntity, HGroup, HRequest, Request, RequestType, Sketch,
+};
+use wasm_game_of_life::system::SolveResult;
+
+// Two groups drawn in a workplane; the first holds a datum point on one
+// line, and another line that's horizontal and held where it was dragged.
+struct Model {
+    sk: Sketch,
+    g1: HGroup,
+    hw: HRequest,
+    g2: HGroup,
+    g3: HGroup,
+    hp: HRequest,
+    hl1: HRequest,
+    hl2: HRequest,
+    dist: HConstraint,
+    on: HConstraint,
+    horiz: HConstraint,
+    dragged: HConstraint,
+}
+
+fn model() -> Model {
+    let mut sk = Sketch::new();
+    let (g1, wrkpl) = workplane(&mut sk);
+    let hw = wrkpl.request();
+    let g2 = sketch_in(&mut sk, wrkpl);
+    let g3 = sketch_in(&mut sk, wrkpl);
+
+    let hp = sk
+        .add_request(Request::new(RequestType::DatumPoint, g2, wrkpl))
+        .unwrap();
+    let o = Vector::new(0.0, 0.0, 0.0);
+    let hl1 = line(&mut sk, g2, wrkpl, o, Vector::new(4.0, 0.0, 0.0));
+    let hl2 = line(&mut sk, g2, wrkpl, o, Vector::new(0.0, 3.0, 0.0));
+
+    let dist = distance(&mut sk, g2, wrkpl, hl1.entity(1), hl1.entity(2), 4.0);
+    let mut c = constraint(ConstraintType::PtOnLine, g2, wrkpl);
+    c.pt_a = hp.entity(0);
+    c.entity_a = hl1.entity(0);
+    let on = sk.add_constraint(c).unwrap();
+    let mut c = constraint(ConstraintType::Horizontal, g2, wrkpl);
+    c.entity_a = hl2.entity(0);
+    let horiz = sk.add_constraint(c).unwrap();
+    let dragged = where_dragged(&mut sk, g2, wrkpl, hl2.entity(1));
+    sk.regenerate_all();
+
+    Model {
+        sk,
+        g1,
+        hw,
+        g2,
+        g3,
+        hp,
+        hl1,
+        hl2,
+        dist,
+        on,
+        horiz,
+        dragged,
+    }
+}
+
+fn entities(hr: HRequest, ids: &[u32]) -> Vec<HEntity> {
+    ids.iter().map(|i| hr.entity(*i)).collect()
+}
+
+#[test]
+fn delete_a_point() {
+    let mut m = model();
+    let p = m.sk.get_entity(m.hp.entity(0)).param;
+    let items = [SketchItem::Entity(m.hp.entity(0))];
+    let r = m.sk.delete(&items);
+    assert!(r.group.is_empty());
+    assert!(r.request == vec![m.hp]);
+    assert!(r.entity == entities(m.hp, &[0]));
+    assert!(r.constraint == vec![m.on]);
+    // The point's two, and the one the point on the line solves for.
+    assert!(r.param == vec![p[0], p[1], m.on.param(0)]);
+    assert_eq!(r.also_removed(&items), "also removed 1 constraint");
+    assert_eq!(m.sk.constraint.len(), 3);
+}
+
+#[test]
+fn delete_a_line_by_its_endpoint() {
+    let mut m = model();
+    let items = [SketchItem::Entity(m.hl1.entity(2))];
+    let r = m.sk.delete(&items);
+    assert!(r.request == vec![m.hl1]);
+    assert!(r.entity == entities(m.hl1, &[0, 1, 2]));
+    assert!(r.constraint == vec![m.dist, m.on]);
+    assert_eq!(r.also_removed(&items), "also removed 2 constraints");
+    assert!(m.sk.request.contains_key(&m.hp));
+}
+
+#[test]
+fn delete_a_line() {
+    let mut m = model();
+    let items = [SketchItem::Request(m.hl2)];
+    let r = m.sk.delete(&items);
+    assert!(r.group.is_empty());
+    assert!(r.request == vec![m.hl2]);
+    assert!(r.entity == entities(m.hl2, &[0, 1, 2]));
+    assert!(r.constraint == vec![m.horiz, m.dragged]);
+    assert_eq!(r.also_removed(&items), "also removed 2 constraints");
+    assert!(m.sk.constraint.contains_key(&m.dist) && m.sk.constraint.contains_key(&m.on));
+}
+
+#[test]
+fn delete_a_workplane() {
+    let mut m = model();
+    let items = [SketchItem::Request(m.hw)];
+    let r = m.sk.delete(&items);
+    assert!(r.group == vec![m.g2, m.g3]);
+    assert!(r.request == vec![m.hw, m.hp, m.hl1, m.hl2]);
+    let mut all = entities(m.hw, &[0, 1, 32]);
+    all.extend(entities(m.hp, &[0]));
+    all.extend(entities(m.hl1, &[0, 1, 2]));
+    all.extend(entities(m.hl2, &[0, 1, 2]));
+    all.sort();
+    assert!(r.entity == all);
+    assert!(r.constraint == vec![m.dist, m.on, m.horiz, m.dragged]);
+    assert_eq!(
+        r.also_removed(&items),
+        "also removed 2 groups, 3 requests, 4 constraints"
+    );
+
+    // Nothing's left drawn in the workplane that's gone.
+    assert_eq!(m.sk.group.len(), 1);
+    assert!(m.sk.entity.is_empty() && m.sk.param.is_empty());
+    assert!(m.sk.regenerate_all() == vec![(m.g1, SolveResult::Okay)]);
+}
@@ -0,0 +1,90 @@
+// A dragged param is weighted to stay where it was dragged to, so it's
+// everything else that moves to meet the constraints. A System is solved
+// again for every step of a drag, so whatever one solve marks known or
+// substitutes away has to be undone by the next.
+
+use wasm_game_of_life::expr::Expr;
+use wasm_game_of_life::sketch::{Equation, GroupSolved, HEquation, HParam, Param, NO_PARAM};
+use wasm_game_of_life::system::{SolveResult, System};
+
+const X1: HParam = HParam { v: 1 };
+const X2: HParam = HParam { v: 2 };
+
+fn system(eqs: Vec<Expr>) -> System {
+    let mut sys = System::new();
+    for (h, val) in [(X1, 1.0), (X2, 2.0)] {
+        sys.add_param(Param {
+            tag: 0,
+            h,
+            val,
+            known: false,
+            free: false,
+            substd: NO_PARAM,
+        });
+    }
+    for (v, e) in eqs.into_iter().enumerate() {
+        sys.add_equation(Equation {
+            tag: 0,
+            h: HEquation { v: v as u32 },
+            e,
+        });
+    }
+    sys
+}
+
+fn drag(sys: &mut System, to: f64) -> (SolveResult, f64, f64) {
+    let mut solved = GroupSolved::default();
+    sys.solve_dragged(&[(X1, to)], &mut solved);
+    (solved.how, sys.param[&X1].val, sys.param[&X2].val)
+}
+
+#[test]
+fn the_other_one_gives_way() {
+    let sum = Expr::from_param(X1) + Expr::from_param(X2) - Expr::from_constant(10.0);
+    let mut sys = system(vec![sum]);
+    sys.param.get_mut(&X2).unwrap().val = 9.0;
+    // It gives way only a little, about a four hundredth of however far it
+    // was dragged.
+    let (how, x1, x2) = drag(&mut sys, 3.0);
+    assert_eq!(how, SolveResult::Okay);
+    assert!((x2 - (10.0 - x1)).abs() < 1e-9, "{} + {}", x1, x2);
+    assert!((x1 - 3.0).abs() < 1e-2, "{} dragged to 3", x1);
+}
+
+#[test]
+fn drag_twice_keeps_the_sum() {
+    let sum = Expr::from_param(X1) + Expr::from_param(X2) - Expr::from_constant(10.0);
+    let mut sys = system(vec![sum]);
+    sys.solve(&mut GroupSolved::default(), false, false, false);
+    // The dragged param is weighted to give way only a little, about a
+    // four hundredth of however far it was dragged.
+    for to in [3.0, 5.0, 2.0] {
+        let (how, x1, x2) = drag(&mut sys, to);
+        assert_eq!(how, SolveResult::Okay);
+        assert!((x2 - (10.0 - x1)).abs() < 1e-9, "{} + {}", x1, x2);
+        assert!((x1 - to).abs() < 1e-2, "{} dragged to {}", x1, to);
+    }
+}
+
+#[test]
+fn drag_twice_through_a_substitution() {
+    let equal = Expr::from_param(X1) - Expr::from_param(X2);
+    let mut sys = system(vec![equal]);
+    for to in [3.0, 7.0] {
+        let (how, x1, x2) = drag(&mut sys, to);
+        assert_eq!(how, SolveResult::Okay);
+        assert_eq!(x1, to);
+        assert_eq!(x2, to);
+    }
+}
+
+#[test]
+fn dragged_param_isnt_substituted() {
+    let equal = Expr::from_param(X1) - Expr::from_param(X2);
+    let mut sys = system(vec![equal]);
+    let (how, x1, x2) = drag(&mut sys, 3.0);
+    assert_eq!(how, SolveResult::Okay);
+    assert_eq!(x1, 3.0);
+    assert_eq!(x2, 3.0);
+    assert_eq!(sys.param[&X1].tag, 0);
+}
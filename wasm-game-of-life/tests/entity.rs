@@ -0,0 +1,173 @@
+// Entities evaluated where their params are now: a workplane's axes, the
+// radius and angles of circles and arcs, and points along each kind of
+// curve, all in world coordinates.
+
+use std::f64::consts::PI;
+use wasm_game_of_life::dsc::{Quaternion, Vector};
+use wasm_game_of_life::sketch::{
+    EntityBase, EntityBaseType, HEntity, HParam, Param, Sketch, NO_PARAM,
+};
+
+const WORKPLANE: HEntity = HEntity { v: 1 };
+const NORMAL: HEntity = HEntity { v: 3 };
+
+// A workplane at (1, 2, 3) with its u along y and its v along z, and
+// whatever's drawn in it.
+struct Drawing {
+    sk: Sketch,
+    next_entity: u32,
+    next_param: u32,
+}
+
+impl Drawing {
+    fn new() -> Self {
+        let mut d = Drawing {
+            sk: Sketch::new(),
+            next_entity: 2,
+            next_param: 1,
+        };
+        let q = Quaternion::from_uv(Vector::new(0.0, 1.0, 0.0), Vector::new(0.0, 0.0, 1.0));
+        let o = d.entity(EntityBaseType::PointIn3D, &[1.0, 2.0, 3.0]);
+        let n = d.entity(EntityBaseType::NormalIn3D, &[q.w, q.vx, q.vy, q.vz]);
+        let mut w = EntityBase::new(WORKPLANE, EntityBaseType::Workplane);
+        w.point[0] = o;
+        w.normal = n;
+        d.sk.entity.add(w);
+        d
+    }
+
+    fn entity(&mut self, type_: EntityBaseType, vals: &[f64]) -> HEntity {
+        let h = HEntity {
+            v: self.next_entity,
+        };
+        self.next_entity += 1;
+        let mut e = EntityBase::new(h, type_);
+        e.workplane = WORKPLANE;
+        e.normal = NORMAL;
+        for (i, val) in vals.iter().enumerate() {
+            let hp = HParam { v: self.next_param };
+            self.next_param += 1;
+            e.param[i] = hp;
+            self.sk.param.add(Param {
+                tag: 0,
+                h: hp,
+                val: *val,
+                known: false,
+                free: false,
+                substd: NO_PARAM,
+            });
+        }
+        self.sk.entity.add(e);
+        h
+    }
+
+    fn curve(&mut self, type_: EntityBaseType, uvs: &[(f64, f64)]) -> HEntity {
+        let h = self.entity(type_, &[]);
+        for (i, (u, v)) in uvs.iter().enumerate() {
+            let p = self.entity(EntityBaseType::PointIn2D, &[*u, *v]);
+            self.sk.entity.get_mut(&h).unwrap().point[i] = p;
+        }
+        h
+    }
+
+    fn get(&self, h: HEntity) -> &EntityBase {
+        self.sk.get_entity(h)
+    }
+}
+
+// Where (u, v) in the workplane is, in world coordinates.
+fn world(u: f64, v: f64) -> Vector {
+    Vector::new(1.0, 2.0 + u, 3.0 + v)
+}
+
+fn check(got: Vector, want: Vector) {
+    assert!(
+        got.equals_with_def_tol(want),
+        "({}, {}, {}) not ({}, {}, {})",
+        got.x,
+        got.y,
+        got.z,
+        want.x,
+        want.y,
+        want.z
+    );
+}
+
+#[test]
+fn workplane_csys() {
+    let d = Drawing::new();
+    let (o, u, v, n) = d.get(WORKPLANE).workplane_get_csys(&d.sk);
+    check(o, Vector::new(1.0, 2.0, 3.0));
+    check(u, Vector::new(0.0, 1.0, 0.0));
+    check(v, Vector::new(0.0, 0.0, 1.0));
+    check(n, Vector::new(1.0, 0.0, 0.0));
+    check(d.get(NORMAL).normal_n(&d.sk), n);
+
+    let (pn, dn) = d.get(WORKPLANE).workplane_get_plane(&d.sk);
+    check(pn, n);
+    assert!((dn - 1.0).abs() < 1e-9);
+}
+
+#[test]
+fn line() {
+    let mut d = Drawing::new();
+    let l = d.curve(EntityBaseType::LineSegment, &[(0.0, 0.0), (4.0, 2.0)]);
+    let e = d.get(l);
+    check(e.endpoint_start(&d.sk), world(0.0, 0.0));
+    check(e.endpoint_finish(&d.sk), world(4.0, 2.0));
+    check(e.curve_point_at(&d.sk, 0.25), world(1.0, 0.5));
+    // As in SolveSpace, a line's vector points back to its start.
+    check(e.vector_get_num(&d.sk), world(0.0, 0.0) - world(4.0, 2.0));
+}
+
+#[test]
+fn circle_and_arc() {
+    let mut d = Drawing::new();
+    let r = d.entity(EntityBaseType::Distance, &[3.0]);
+    let c = d.curve(EntityBaseType::Circle, &[(1.0, 1.0)]);
+    d.sk.entity.get_mut(&c).unwrap().distance = r;
+    let e = d.get(c);
+    assert_eq!(e.circle_get_radius_num(&d.sk), 3.0);
+    check(e.curve_point_at(&d.sk, 0.0), world(4.0, 1.0));
+    check(e.curve_point_at(&d.sk, 0.25), world(1.0, 4.0));
+
+    // A quarter turn, from u round to v.
+    let a = d.curve(
+        EntityBaseType::ArcOfCircle,
+        &[(0.0, 0.0), (2.0, 0.0), (0.0, 2.0)],
+    );
+    let e = d.get(a);
+    assert!((e.circle_get_radius_num(&d.sk) - 2.0).abs() < 1e-9);
+    let (theta_a, theta_b, dtheta) = e.arc_get_angles(&d.sk);
+    assert!(theta_a.abs() < 1e-9);
+    assert!((theta_b - PI / 2.0).abs() < 1e-9);
+    assert!((dtheta - PI / 2.0).abs() < 1e-9);
+    let s = 2.0f64.sqrt();
+    check(e.curve_point_at(&d.sk, 0.5), world(s, s));
+    check(e.endpoint_start(&d.sk), world(2.0, 0.0));
+    check(e.endpoint_finish(&d.sk), world(0.0, 2.0));
+
+    // Ends that meet go all the way round.
+    let a = d.curve(
+        EntityBaseType::ArcOfCircle,
+        &[(0.0, 0.0), (2.0, 0.0), (2.0, 0.0)],
+    );
+    assert!((d.get(a).arc_get_angles(&d.sk).2 - 2.0 * PI).abs() < 1e-9);
+}
+
+#[test]
+fn cubic() {
+    let mut d = Drawing::new();
+    // Four points make the one Bezier segment.
+    let c = d.curve(
+        EntityBaseType::Cubic,
+        &[(0.0, 0.0), (1.0, 1.0), (2.0, 1.0), (3.0, 0.0)],
+    );
+    let e = d.get(c);
+    let segs = e.cubic_bezier_segments(&d.sk);
+    assert_eq!(segs.len(), 1);
+    check(segs[0][1], world(1.0, 1.0));
+    check(e.curve_point_at(&d.sk, 0.0), world(0.0, 0.0));
+    check(e.curve_point_at(&d.sk, 0.5), world(1.5, 0.75));
+    check(e.curve_point_at(&d.sk, 1.0), world(3.0, 0.0));
+}
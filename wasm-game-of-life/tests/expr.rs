@@ -0,0 +1,158 @@
+// The solver's Jacobian is made of partial_wrt(..), so each operator's
+// derivative has to agree with the slope eval(..) gives either side of the
+// point; and fold_constants(..) mustn't change what anything evaluates to.
+
+use wasm_game_of_life::expr::Expr;
+use wasm_game_of_life::id_list::IdList;
+use wasm_game_of_life::sketch::{HParam, Param, NO_PARAM};
+
+const X: HParam = HParam { v: 1 };
+const Y: HParam = HParam { v: 2 };
+const Z: HParam = HParam { v: 3 };
+
+fn params(x: f64, y: f64) -> IdList<HParam, Param> {
+    let mut l = IdList::new();
+    for (h, val) in [(X, x), (Y, y)] {
+        l.add(Param {
+            tag: 0,
+            h,
+            val,
+            known: false,
+            free: false,
+            substd: NO_PARAM,
+        });
+    }
+    l
+}
+
+fn x() -> Expr {
+    Expr::from_param(X)
+}
+
+fn y() -> Expr {
+    Expr::from_param(Y)
+}
+
+fn c(v: f64) -> Expr {
+    Expr::from_constant(v)
+}
+
+// One of each operator, on x and y where they're both in its domain, so
+// x and y in (0, 1) for asin and acos.
+fn each_operator() -> Vec<(&'static str, Expr)> {
+    vec![
+        ("param", x()),
+        ("constant", c(3.0)),
+        ("plus", x() + y()),
+        ("minus", x() - y()),
+        ("times", x() * y()),
+        ("div", x() / y()),
+        ("negate", -(x() * y())),
+        ("sqrt", (x() + y()).sqrt()),
+        ("square", (x() - y()).square()),
+        ("sin", (x() * y()).sin()),
+        ("cos", (x() * y()).cos()),
+        ("asin", (x() * y()).asin()),
+        ("acos", (x() * y()).acos()),
+        ("nested", (x() / (y() + c(1.0))).sqrt() * (x() - y()).cos()),
+    ]
+}
+
+#[test]
+fn eval_each_operator() {
+    let p = params(0.3, 0.7);
+    let want = [
+        0.3,
+        3.0,
+        1.0,
+        -0.4,
+        0.21,
+        0.3 / 0.7,
+        -0.21,
+        1.0,
+        0.16,
+        0.21f64.sin(),
+        0.21f64.cos(),
+        0.21f64.asin(),
+        0.21f64.acos(),
+        (0.3f64 / 1.7).sqrt() * (-0.4f64).cos(),
+    ];
+    for ((name, e), want) in each_operator().iter().zip(want.iter()) {
+        let v = e.eval(&p);
+        assert!((v - want).abs() < 1e-12, "{}: {} not {}", name, v, want);
+    }
+}
+
+#[test]
+#[should_panic(expected = "unknown param 3")]
+fn eval_of_unknown_param_panics() {
+    (x() + Expr::from_param(Z)).eval(&params(0.3, 0.7));
+}
+
+#[test]
+fn partials_match_finite_differences() {
+    let (x0, y0) = (0.3, 0.7);
+    let h = 1e-6;
+    for (name, e) in each_operator() {
+        for (wrt, (dx, dy)) in [(X, (h, 0.0)), (Y, (0.0, h))] {
+            let slope =
+                (e.eval(&params(x0 + dx, y0 + dy)) - e.eval(&params(x0 - dx, y0 - dy))) / (2.0 * h);
+            let d = e.partial_wrt(wrt).eval(&params(x0, y0));
+            assert!(
+                (d - slope).abs() < 1e-6 * (1.0 + slope.abs()),
+                "{} wrt {}: {} not {}",
+                name,
+                wrt.v,
+                d,
+                slope
+            );
+        }
+        assert_eq!(e.partial_wrt(Z).fold_constants().eval(&params(x0, y0)), 0.0);
+    }
+}
+
+#[test]
+fn folding_keeps_the_value() {
+    let p = params(0.3, 0.7);
+    for (name, e) in each_operator() {
+        let folded = e.fold_constants();
+        assert!((folded.eval(&p) - e.eval(&p)).abs() < 1e-12, "{}", name);
+        let d = e.partial_wrt(X);
+        assert!(
+            (d.fold_constants().eval(&p) - d.eval(&p)).abs() < 1e-12,
+            "{}",
+            name
+        );
+    }
+}
+
+#[test]
+fn folding_simplifies() {
+    let p = params(0.3, 0.7);
+    let folded = |e: Expr| e.fold_constants();
+
+    assert!(folded((c(2.0) * c(3.0) + c(1.0)).sqrt()).is_constant(7.0f64.sqrt()));
+    assert!(folded(c(0.0) * x()).is_constant(0.0));
+    assert!(folded(x() * c(0.0)).is_constant(0.0));
+    assert!(folded(c(0.0) / y()).is_constant(0.0));
+    assert!(folded(-c(2.0)).is_constant(-2.0));
+    for e in [
+        x() + c(0.0),
+        c(0.0) + x(),
+        x() - c(0.0),
+        c(1.0) * x(),
+        x() / c(1.0),
+        -(-x()),
+    ] {
+        assert!(matches!(folded(e), Expr::Param(X)));
+    }
+    match folded(c(0.0) - x()) {
+        Expr::Negate(a) => assert!(matches!(*a, Expr::Param(X))),
+        _ => panic!("0 - x should be -x"),
+    }
+
+    // The derivative of x*y wrt x is y, once the zeros and ones are gone.
+    assert!(matches!(folded((x() * y()).partial_wrt(X)), Expr::Param(Y)));
+    assert!(folded((x() * y()).partial_wrt(Z)).is_constant(0.0));
+    assert_eq!(folded(x() * y()).eval(&p), 0.21);
+}
@@ -0,0 +1,280 @@
+// Extruding a rectangle has to give a closed box: six faces, each with the
+// entity that stands for it, wound so that they all face out whichever way
+// the extrusion goes.
+
+mod common;
+
+use common::{constraint, line, sketch_in, workplane};
+use wasm_game_of_life::dsc::Vector;
+use wasm_game_of_life::sketch::{
+    ConstraintType, EntityBaseType, EntityKey, Group, GroupCombineAs, GroupRemap, GroupSubtype,
+    GroupType, HEntity, HGroup, Sketch, NO_ENTITY,
+};
+
+const W: f64 = 4.0;
+const H: f64 = 2.0;
+
+struct Rect {
+    g2: HGroup,
+    lines: Vec<HEntity>,
+    points: Vec<HEntity>,
+}
+
+// A W by H rectangle in a workplane through the origin, normal to z.
+fn rectangle(sk: &mut Sketch) -> Rect {
+    let (_, wrkpl) = workplane(sk);
+    let g2 = sketch_in(sk, wrkpl);
+
+    let corners = [
+        Vector::new(0.0, 0.0, 0.0),
+        Vector::new(W, 0.0, 0.0),
+        Vector::new(W, H, 0.0),
+        Vector::new(0.0, H, 0.0),
+    ];
+    let mut rect = Rect {
+        g2,
+        lines: Vec::new(),
+        points: Vec::new(),
+    };
+    for i in 0..4 {
+        let hl = line(sk, g2, wrkpl, corners[i], corners[(i + 1) % 4]);
+        rect.lines.push(hl.entity(0));
+        rect.points.push(hl.entity(1));
+        rect.points.push(hl.entity(2));
+    }
+    for i in 0..4 {
+        let mut c = constraint(ConstraintType::PointsCoincident, g2, wrkpl);
+        c.pt_a = rect.points[2 * i + 1];
+        c.pt_b = rect.points[(2 * i + 2) % 8];
+        sk.add_constraint(c).unwrap();
+    }
+    rect
+}
+
+fn extrude(sk: &mut Sketch, rect: &Rect, subtype: GroupSubtype, skip_first: bool) -> HGroup {
+    let mut g = Group::new(GroupType::Extrude);
+    g.op_a = rect.g2;
+    g.subtype = subtype;
+    g.skip_first = skip_first;
+    let g3 = sk.add_group(g).unwrap();
+    sk.regenerate_dirty();
+    sk.generate_display_items(g3);
+    g3
+}
+
+// Where the group put its copy of input, if it made one.
+fn copy(sk: &Sketch, hg: HGroup, input: HEntity, how: GroupRemap) -> Option<HEntity> {
+    let key = EntityKey {
+        input,
+        copy_number: how as i32,
+    };
+    let id = sk.get_group(hg).remap.get(&key)?;
+    let h = hg.entity(id.v);
+    assert!(sk.entity.contains_key(&h), "{} isn't in the sketch", h.v);
+    Some(h)
+}
+
+fn face(sk: &Sketch, hg: HGroup, how: GroupRemap) -> HEntity {
+    let h = copy(sk, hg, NO_ENTITY, how).unwrap();
+    assert!(sk.get_entity(h).type_ == EntityBaseType::FaceNormalPt);
+    h
+}
+
+// The solid is closed and faces out: twelve triangles, twelve sharp edges
+// each with a face on either side, and a positive volume.
+fn check_box(sk: &Sketch, hg: HGroup, depth: f64) {
+    let g = sk.get_group(hg);
+    assert_eq!(g.this_shell.surface.len(), 6);
+    assert_eq!(g.this_mesh.l.len(), 12);
+    assert_eq!(g.running_mesh.l.len(), 12);
+    let volume = g.running_mesh.calculate_volume();
+    assert!((volume - W * H * depth).abs() < 1e-9, "volume {}", volume);
+
+    assert_eq!(g.display_outlines.l.len(), 12);
+    for o in g.display_outlines.l.iter() {
+        assert!(o.nl.dot(o.nr).abs() < 1e-9, "{} and {}", o.nl.z, o.nr.z);
+    }
+}
+
+// Every face of the shell is one of the group's face entities: the top,
+// the bottom, or the face a line of the sketch swept out.
+fn check_faces(sk: &Sketch, hg: HGroup, rect: &Rect) {
+    let mut faces = vec![
+        face(sk, hg, GroupRemap::Top).v,
+        face(sk, hg, GroupRemap::Bottom).v,
+    ];
+    for hl in rect.lines.iter() {
+        let hf = copy(sk, hg, *hl, GroupRemap::LineToFace).unwrap();
+        assert!(sk.get_entity(hf).type_ == EntityBaseType::FaceXProd);
+        faces.push(hf.v);
+    }
+    let mut made: Vec<u32> = sk
+        .get_group(hg)
+        .this_shell
+        .surface
+        .iter()
+        .map(|s| s.face)
+        .collect();
+    faces.sort_unstable();
+    made.sort_unstable();
+    assert_eq!(made, faces);
+}
+
+fn z(sk: &Sketch, h: HEntity) -> f64 {
+    sk.get_entity(h).point_get_num(sk).z
+}
+
+#[test]
+fn one_sided() {
+    let mut sk = Sketch::new();
+    let rect = rectangle(&mut sk);
+    let g3 = extrude(&mut sk, &rect, GroupSubtype::OneSided, false);
+    check_box(&sk, g3, 1.0);
+    check_faces(&sk, g3, &rect);
+
+    for hp in rect.points.iter() {
+        let bottom = copy(&sk, g3, *hp, GroupRemap::Bottom).unwrap();
+        let top = copy(&sk, g3, *hp, GroupRemap::Top).unwrap();
+        assert!(sk.get_entity(bottom).type_ == EntityBaseType::PointNTrans);
+        assert!((z(&sk, bottom) - 0.0).abs() < 1e-9);
+        assert!((z(&sk, top) - 1.0).abs() < 1e-9);
+
+        let hl = copy(&sk, g3, *hp, GroupRemap::PtToLine).unwrap();
+        let l = sk.get_entity(hl);
+        assert!(l.type_ == EntityBaseType::LineSegment);
+        assert!(l.point[0] == top && l.point[1] == bottom);
+    }
+    for hl in rect.lines.iter() {
+        for how in [GroupRemap::Bottom, GroupRemap::Top] {
+            let l = copy(&sk, g3, *hl, how).unwrap();
+            assert!(sk.get_entity(l).type_ == EntityBaseType::LineSegment);
+        }
+    }
+}
+
+#[test]
+fn two_sided() {
+    let mut sk = Sketch::new();
+    let rect = rectangle(&mut sk);
+    let g3 = extrude(&mut sk, &rect, GroupSubtype::TwoSided, false);
+    check_box(&sk, g3, 2.0);
+    check_faces(&sk, g3, &rect);
+
+    for hp in rect.points.iter() {
+        let bottom = copy(&sk, g3, *hp, GroupRemap::Bottom).unwrap();
+        let top = copy(&sk, g3, *hp, GroupRemap::Top).unwrap();
+        assert!((z(&sk, bottom) + 1.0).abs() < 1e-9);
+        assert!((z(&sk, top) - 1.0).abs() < 1e-9);
+    }
+}
+
+#[test]
+fn skip_first_builds_on_the_sketch() {
+    let mut sk = Sketch::new();
+    let rect = rectangle(&mut sk);
+    let g3 = extrude(&mut sk, &rect, GroupSubtype::OneSided, true);
+    check_box(&sk, g3, 1.0);
+    check_faces(&sk, g3, &rect);
+
+    for hp in rect.points.iter().chain(rect.lines.iter()) {
+        assert!(copy(&sk, g3, *hp, GroupRemap::Bottom).is_none());
+    }
+    for hp in rect.points.iter() {
+        let hl = copy(&sk, g3, *hp, GroupRemap::PtToLine).unwrap();
+        assert!(sk.get_entity(hl).point[1] == *hp);
+    }
+    let bottom = sk.get_entity(face(&sk, g3, GroupRemap::Bottom));
+    assert!(rect.points.contains(&bottom.point[0]));
+}
+
+#[test]
+fn extruded_backwards() {
+    let mut sk = Sketch::new();
+    let rect = rectangle(&mut sk);
+    let g3 = extrude(&mut sk, &rect, GroupSubtype::OneSided, false);
+
+    sk.param.get_mut(&g3.param(2)).unwrap().val = -1.0;
+    sk.mark_group_dirty(g3);
+    sk.regenerate_dirty();
+    sk.generate_display_items(g3);
+    check_box(&sk, g3, 1.0);
+    check_faces(&sk, g3, &rect);
+
+    // The top is below the sketch now, and has to face down to face out.
+    let top = face(&sk, g3, GroupRemap::Top).v;
+    let g = sk.get_group(g3);
+    let tris: Vec<_> = g
+        .this_mesh
+        .l
+        .iter()
+        .filter(|t| t.meta.face == top)
+        .collect();
+    assert_eq!(tris.len(), 2);
+    for t in tris {
+        assert!((t.a.z + 1.0).abs() < 1e-9);
+        assert!(t.normal().z < 0.0);
+    }
+}
+
+// A two-sided extrusion, z from -1 to 1, combined with the one-sided box
+// from z = 0 to 1 before it.
+fn combined(how: GroupCombineAs) -> (Sketch, HGroup, HGroup) {
+    let mut sk = Sketch::new();
+    let rect = rectangle(&mut sk);
+    let g3 = extrude(&mut sk, &rect, GroupSubtype::OneSided, false);
+    let g4 = extrude(&mut sk, &rect, GroupSubtype::TwoSided, false);
+    sk.group.get_mut(&g4).unwrap().mesh_combine = how;
+    sk.mark_group_dirty(g4);
+    sk.regenerate_dirty();
+    (sk, g3, g4)
+}
+
+#[test]
+fn booleans_leave_just_the_mesh() {
+    for (how, depth) in [
+        (GroupCombineAs::Union, 2.0),
+        (GroupCombineAs::Difference, 0.0),
+        (GroupCombineAs::Intersection, 1.0),
+    ] {
+        let (sk, g3, g4) = combined(how);
+        assert!(!sk.get_group(g3).is_mesh_only());
+        assert_eq!(sk.get_group(g3).running_shell.surface.len(), 6);
+
+        let g = sk.get_group(g4);
+        assert!(!g.boolean_failed);
+        assert!(g.running_shell.is_empty());
+        assert_eq!(g.is_mesh_only(), depth > 0.0);
+        let volume = g.running_mesh.calculate_volume();
+        assert!((volume - W * H * depth).abs() < 1e-9, "volume {}", volume);
+    }
+
+    let (sk, _, g4) = combined(GroupCombineAs::Assemble);
+    let g = sk.get_group(g4);
+    assert!(!g.boolean_failed && !g.is_mesh_only());
+    assert_eq!(g.running_shell.surface.len(), 12);
+}
+
+#[test]
+fn boolean_with_an_open_mesh_fails() {
+    let mut sk = Sketch::new();
+    let rect = rectangle(&mut sk);
+    let g3 = extrude(&mut sk, &rect, GroupSubtype::OneSided, false);
+
+    // A linked file that had just one triangle to give.
+    let mut g = Group::new(GroupType::Linked);
+    g.imp_mesh.add_triangle(
+        sk.get_group(g3).this_mesh.l[0].meta,
+        Vector::new(1.0, 1.0, 0.5),
+        Vector::new(9.0, 1.0, 0.5),
+        Vector::new(1.0, 9.0, 0.5),
+    );
+    let g4 = sk.add_group(g).unwrap();
+    sk.regenerate_dirty();
+    assert!(sk.get_group(g4).boolean_failed);
+    assert!(!sk.get_group(g4).running_mesh.is_closed());
+
+    sk.group.get_mut(&g4).unwrap().suppress = true;
+    sk.mark_group_dirty(g4);
+    sk.regenerate_dirty();
+    assert!(!sk.get_group(g4).boolean_failed);
+}
@@ -0,0 +1,154 @@
+// Slvs_Solve as a C caller sees it: whatever goes wrong, the answer is in
+// ssys->result, never a missing one or an unwind across the boundary.
+
+use std::ptr;
+use wasm_game_of_life::ffi::*;
+
+fn point(h: Slvs_hEntity, p: [Slvs_hParam; 3]) -> Slvs_Entity {
+    Slvs_Entity {
+        h,
+        group: 1,
+        r#type: SLVS_E_POINT_IN_3D,
+        wrkpl: SLVS_FREE_IN_3D,
+        point: [0; 4],
+        normal: 0,
+        distance: 0,
+        param: [p[0], p[1], p[2], 0],
+    }
+}
+
+fn distance(a: Slvs_hEntity, b: Slvs_hEntity) -> Slvs_Constraint {
+    Slvs_Constraint {
+        h: 1,
+        group: 1,
+        r#type: SLVS_C_PT_PT_DISTANCE,
+        wrkpl: SLVS_FREE_IN_3D,
+        valA: 5.0,
+        ptA: a,
+        ptB: b,
+        entityA: 0,
+        entityB: 0,
+        entityC: 0,
+        entityD: 0,
+        other: 0,
+        other2: 0,
+    }
+}
+
+// Solves two points and the constraints, with failed left room for two,
+// and returns the system as Slvs_Solve left it.
+fn solve(
+    entity: &mut [Slvs_Entity],
+    constraint: &mut [Slvs_Constraint],
+) -> (Slvs_System, [f64; 6]) {
+    let mut param: Vec<Slvs_Param> = (1..=6)
+        .map(|h| Slvs_Param {
+            h,
+            group: 1,
+            val: h as f64,
+        })
+        .collect();
+    let mut failed = [0; 2];
+    let mut sys = Slvs_System {
+        param: param.as_mut_ptr(),
+        params: param.len() as i32,
+        entity: entity.as_mut_ptr(),
+        entities: entity.len() as i32,
+        constraint: constraint.as_mut_ptr(),
+        constraints: constraint.len() as i32,
+        dragged: [0; 4],
+        calculateFaileds: 1,
+        failed: failed.as_mut_ptr(),
+        faileds: failed.len() as i32,
+        dof: -1,
+        result: -1,
+    };
+    unsafe { Slvs_Solve(&mut sys, 1) };
+    sys.param = ptr::null_mut();
+    sys.entity = ptr::null_mut();
+    sys.constraint = ptr::null_mut();
+    sys.failed = ptr::null_mut();
+    let mut vals = [0.0; 6];
+    for (v, p) in vals.iter_mut().zip(&param) {
+        *v = p.val;
+    }
+    (sys, vals)
+}
+
+#[test]
+fn two_points_apart() {
+    let mut entity = [point(101, [1, 2, 3]), point(102, [4, 5, 6])];
+    let (sys, p) = solve(&mut entity, &mut [distance(101, 102)]);
+    assert_eq!(sys.result, SLVS_RESULT_OKAY);
+    assert_eq!(sys.dof, 5);
+    assert_eq!(sys.faileds, 0);
+    let d = ((p[3] - p[0]).powi(2) + (p[4] - p[1]).powi(2) + (p[5] - p[2]).powi(2)).sqrt();
+    assert!((d - 5.0).abs() < 1e-9);
+}
+
+#[test]
+fn inconsistent_lists_the_failed() {
+    let mut entity = [point(101, [1, 2, 3]), point(102, [4, 5, 6])];
+    let mut constraint = [distance(101, 102), distance(101, 102)];
+    constraint[1].h = 2;
+    constraint[1].valA = 7.0;
+    let (sys, _) = solve(&mut entity, &mut constraint);
+    assert_eq!(sys.result, SLVS_RESULT_INCONSISTENT);
+    assert_eq!(sys.faileds, 2);
+}
+
+#[test]
+fn unknown_entity_type() {
+    let mut entity = [point(101, [1, 2, 3]), point(102, [4, 5, 6])];
+    entity[1].r#type = 12345;
+    let (sys, p) = solve(&mut entity, &mut [distance(101, 102)]);
+    assert_eq!(sys.result, SLVS_RESULT_UNKNOWN_TYPE);
+    assert_eq!(sys.dof, 0);
+    assert_eq!(sys.faileds, 0);
+    assert_eq!(p, [1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+}
+
+#[test]
+fn unknown_constraint_type() {
+    let mut entity = [point(101, [1, 2, 3]), point(102, [4, 5, 6])];
+    let mut c = distance(101, 102);
+    c.r#type = 12345;
+    let (sys, _) = solve(&mut entity, &mut [c]);
+    assert_eq!(sys.result, SLVS_RESULT_UNKNOWN_TYPE);
+    assert_eq!(sys.faileds, 0);
+}
+
+#[test]
+fn constraint_on_missing_entity() {
+    let mut entity = [point(101, [1, 2, 3]), point(102, [4, 5, 6])];
+    let (sys, _) = solve(&mut entity, &mut [distance(101, 999)]);
+    assert_eq!(sys.result, SLVS_RESULT_BAD_INPUT);
+    assert_eq!(sys.dof, 0);
+    assert_eq!(sys.faileds, 0);
+}
+
+#[test]
+fn quaternion_round_trip() {
+    let (u, v) = ([0.0, 1.0, 0.0], [0.0, 0.0, 1.0]);
+    let (mut qw, mut qx, mut qy, mut qz) = (0.0, 0.0, 0.0, 0.0);
+    unsafe {
+        Slvs_MakeQuaternion(
+            u[0], u[1], u[2], v[0], v[1], v[2], &mut qw, &mut qx, &mut qy, &mut qz,
+        )
+    };
+    for (f, want) in [
+        (
+            Slvs_QuaternionU
+                as unsafe extern "C" fn(f64, f64, f64, f64, *mut f64, *mut f64, *mut f64),
+            u,
+        ),
+        (Slvs_QuaternionV, v),
+        (Slvs_QuaternionN, [1.0, 0.0, 0.0]),
+    ] {
+        let mut got = [0.0; 3];
+        unsafe { f(qw, qx, qy, qz, &mut got[0], &mut got[1], &mut got[2]) };
+        for i in 0..3 {
+            assert!((got[i] - want[i]).abs() < 1e-9, "{:?} not {:?}", got, want);
+        }
+    }
+}
@@ -0,0 +1,112 @@
+// When a group's constraints can't all be met, the solve can say which of
+// them to take away: one whose removal leaves the Jacobian full rank if
+// some are redundant, or else the ones left unsatisfied.
+
+use wasm_game_of_life::expr::Expr;
+use wasm_game_of_life::sketch::{Equation, GroupSolved, HConstraint, HParam, Param};
+use wasm_game_of_life::system::{SolveResult, System};
+
+const X: HParam = HParam { v: 1 };
+const Y: HParam = HParam { v: 2 };
+
+fn x() -> Expr {
+    Expr::from_param(X)
+}
+
+fn y() -> Expr {
+    Expr::from_param(Y)
+}
+
+fn c(v: f64) -> Expr {
+    Expr::from_constant(v)
+}
+
+// Each equation as the only one of its own constraint, 1, 2, 3 and on.
+fn system(eqs: Vec<Expr>) -> System {
+    let mut sys = System::new();
+    for h in [X, Y] {
+        sys.add_param(Param {
+            tag: 0,
+            h,
+            val: 0.0,
+            known: false,
+            free: false,
+            substd: HParam { v: 0 },
+        });
+    }
+    for (i, e) in eqs.into_iter().enumerate() {
+        let hc = HConstraint { v: i as u32 + 1 };
+        sys.add_equation(Equation {
+            tag: 0,
+            h: hc.equation(0),
+            e,
+        });
+    }
+    sys
+}
+
+fn solve(eqs: Vec<Expr>, allow_redundant: bool, timeout: i32) -> GroupSolved {
+    let mut solved = GroupSolved {
+        how: SolveResult::Okay,
+        dof: 0,
+        find_to_fix_timeout: timeout,
+        timeout: false,
+        remove: Vec::new(),
+        residual: Vec::new(),
+    };
+    system(eqs).solve(&mut solved, allow_redundant, true, false);
+    solved
+}
+
+fn removed(solved: &GroupSolved) -> Vec<u32> {
+    solved.remove.iter().map(|h| h.v).collect()
+}
+
+// x = 1 said twice over, and y = 2.
+fn said_twice() -> Vec<Expr> {
+    vec![x() - c(1.0), y() - c(2.0), c(2.0) * x() - c(2.0)]
+}
+
+#[test]
+fn either_of_the_repeats() {
+    let solved = solve(said_twice(), false, 10_000);
+    assert_eq!(solved.how, SolveResult::RedundantOkay);
+    assert_eq!(removed(&solved), vec![1, 3]);
+    assert!(!solved.timeout);
+}
+
+#[test]
+fn nothing_if_redundant_is_allowed() {
+    let solved = solve(said_twice(), true, 10_000);
+    assert_eq!(solved.how, SolveResult::RedundantOkay);
+    assert!(solved.remove.is_empty());
+}
+
+#[test]
+fn unsatisfied_if_redundant_is_allowed() {
+    let eqs = vec![x() - c(1.0), x() - c(2.0), y()];
+    let mut sys = system(eqs.clone());
+    let mut solved = GroupSolved {
+        how: SolveResult::Okay,
+        dof: 0,
+        find_to_fix_timeout: 10_000,
+        timeout: false,
+        remove: Vec::new(),
+        residual: Vec::new(),
+    };
+    sys.solve(&mut solved, true, true, false);
+    assert_eq!(solved.how, SolveResult::RedundantDidntConverge);
+    // Just the ones that x can't be made to meet where it stopped.
+    let unmet: Vec<u32> = (1..=3)
+        .filter(|v| eqs[*v as usize - 1].eval(&sys.param).abs() > 1e-6)
+        .collect();
+    assert!(!unmet.is_empty() && !unmet.contains(&3));
+    assert_eq!(removed(&solved), unmet);
+}
+
+#[test]
+fn out_of_time() {
+    let solved = solve(said_twice(), false, -1);
+    assert!(solved.timeout);
+    assert!(solved.remove.is_empty());
+}
@@ -0,0 +1,61 @@
+// Asked to, a solve finds which params the constraints still leave room to
+// move, and so which entities can still be dragged around.
+
+mod common;
+
+use common::{line, sketch_in, where_dragged, workplane};
+use wasm_game_of_life::dsc::Vector;
+use wasm_game_of_life::sketch::{HEntity, Sketch};
+use wasm_game_of_life::system::SolveResult;
+
+#[test]
+fn one_endpoint_left_free() {
+    let mut sk = Sketch::new();
+    let (_, wrkpl) = workplane(&mut sk);
+    let hg = sketch_in(&mut sk, wrkpl);
+    let held = line(
+        &mut sk,
+        hg,
+        wrkpl,
+        Vector::new(0.0, 0.0, 0.0),
+        Vector::new(3.0, 1.0, 0.0),
+    );
+    let loose = line(
+        &mut sk,
+        hg,
+        wrkpl,
+        Vector::new(0.0, 2.0, 0.0),
+        Vector::new(3.0, 4.0, 0.0),
+    );
+    for pt in [held.entity(1), held.entity(2), loose.entity(1)] {
+        where_dragged(&mut sk, hg, wrkpl, pt);
+    }
+
+    // Without asking, nothing is found free.
+    assert_eq!(sk.solve_group(hg, false, false), SolveResult::Okay);
+    assert!(sk.free_entities(hg).is_empty());
+
+    assert_eq!(sk.solve_group(hg, false, true), SolveResult::Okay);
+    assert_eq!(sk.get_group(hg).solved.dof, 2);
+    let pt = loose.entity(2);
+    let mut free: Vec<u32> = sk
+        .param
+        .values()
+        .filter(|p| p.free)
+        .map(|p| p.h.v)
+        .collect();
+    free.sort_unstable();
+    let mut want: Vec<u32> = sk.get_entity(pt).param[..2].iter().map(|h| h.v).collect();
+    want.sort_unstable();
+    assert_eq!(free, want);
+
+    // Just that point, and the line it's the end of.
+    let want: Vec<HEntity> = vec![loose.entity(0), pt];
+    assert!(sk.free_entities(hg) == want);
+
+    // Once it's held too, nothing is.
+    where_dragged(&mut sk, hg, wrkpl, pt);
+    sk.solve_group(hg, false, true);
+    assert_eq!(sk.get_group(hg).solved.dof, 0);
+    assert!(sk.free_entities(hg).is_empty());
+}
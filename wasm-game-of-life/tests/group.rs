@@ -0,0 +1,200 @@
+// Building a sketch the way the designer does: groups, then requests and
+// constraints in them, all by handle, with unknown handles turned away
+// before anything changes.
+
+mod common;
+
+use common::{constraint, distance, line, pinned_line, sketch_in, where_dragged, workplane};
+use wasm_game_of_life::dsc::Vector;
+use wasm_game_of_life::sketch::{
+    ConstraintType, Group, GroupType, HEntity, HGroup, Request, RequestType, Sketch, SketchError,
+    FREE_IN_3D,
+};
+use wasm_game_of_life::system::SolveResult;
+
+#[test]
+fn line_of_given_length() {
+    let mut sk = Sketch::new();
+    let (g1, wrkpl) = workplane(&mut sk);
+    let g2 = sketch_in(&mut sk, wrkpl);
+    assert!(sk.get_group(g2).order > sk.get_group(g1).order);
+
+    let hl = line(
+        &mut sk,
+        g2,
+        wrkpl,
+        Vector::new(0.0, 0.0, 0.0),
+        Vector::new(3.0, 1.0, 0.0),
+    );
+    let (a, b) = (hl.entity(1), hl.entity(2));
+    where_dragged(&mut sk, g2, wrkpl, a);
+    let mut c = constraint(ConstraintType::Horizontal, g2, wrkpl);
+    c.entity_a = hl.entity(0);
+    sk.add_constraint(c).unwrap();
+    distance(&mut sk, g2, wrkpl, a, b, 5.0);
+
+    let r = sk.regenerate_dirty();
+    assert!(r.contains(&(g2, SolveResult::Okay)));
+    assert_eq!(sk.get_group(g2).solved.dof, 0);
+    assert!(sk.get_group(g2).dof_check_ok);
+    let pa = sk.get_entity(a).point_get_num(&sk);
+    let pb = sk.get_entity(b).point_get_num(&sk);
+    assert!(((pb - pa).magnitude() - 5.0).abs() < 1e-9);
+}
+
+#[test]
+fn only_dirty_groups_are_rebuilt() {
+    let mut sk = Sketch::new();
+    let (g1, wrkpl) = workplane(&mut sk);
+    let (g2, hl2, _) = pinned_line(&mut sk, wrkpl, 5.0);
+    let (g3, _, _) = pinned_line(&mut sk, wrkpl, 5.0);
+    let all = sk.regenerate_dirty();
+    assert_eq!(all.len(), 3);
+
+    // Left as they are unless g2 is solved, or its request made again.
+    let hp = sk.get_entity(hl2.entity(2)).param[0];
+    sk.param.get_mut(&hp).unwrap().val = 100.0;
+    sk.entity.get_mut(&hl2.entity(0)).unwrap().construction = true;
+
+    sk.mark_group_dirty(g3);
+    assert!(sk.regenerate_dirty() == vec![(g3, SolveResult::Okay)]);
+    assert_eq!(sk.param[&hp].val, 100.0);
+    assert!(sk.get_entity(hl2.entity(0)).construction);
+    assert!(sk.get_group(g1).clean && sk.get_group(g2).clean);
+
+    sk.mark_group_dirty(g2);
+    assert!(sk.regenerate_dirty() == vec![(g2, SolveResult::Okay), (g3, SolveResult::Okay)]);
+    assert!(sk.param[&hp].val != 100.0);
+    assert!(!sk.get_entity(hl2.entity(0)).construction);
+
+    // Adding to a group dirties it and everything after it.
+    sk.add_request(Request::new(RequestType::DatumPoint, g2, wrkpl))
+        .unwrap();
+    assert!(sk.get_group(g1).clean);
+    assert!(!sk.get_group(g2).clean && !sk.get_group(g3).clean);
+    assert_eq!(sk.regenerate_dirty().len(), 2);
+}
+
+#[test]
+fn unknown_handles_change_nothing() {
+    let mut sk = Sketch::new();
+    let g1 = sk.add_group(Group::new(GroupType::Drawing3D)).unwrap();
+    let hl = sk
+        .add_request(Request::new(RequestType::LineSegment, g1, FREE_IN_3D))
+        .unwrap();
+
+    assert_eq!(
+        sk.add_group(Group::new(GroupType::Extrude)).err(),
+        Some(SketchError::UnknownGroup(0))
+    );
+    let mut g = Group::new(GroupType::DrawingWorkplane);
+    g.active_workplane = hl.entity(0);
+    assert_eq!(
+        sk.add_group(g).err(),
+        Some(SketchError::NotAWorkplane(hl.entity(0).v))
+    );
+    assert_eq!(
+        sk.add_request(Request::new(
+            RequestType::LineSegment,
+            HGroup { v: 99 },
+            FREE_IN_3D
+        ))
+        .err(),
+        Some(SketchError::UnknownGroup(99))
+    );
+
+    let mut c = constraint(ConstraintType::PtPtDistance, HGroup { v: 99 }, FREE_IN_3D);
+    c.pt_a = hl.entity(1);
+    assert_eq!(
+        sk.add_constraint(c).err(),
+        Some(SketchError::UnknownGroup(99))
+    );
+    let mut c = constraint(ConstraintType::PtPtDistance, g1, FREE_IN_3D);
+    c.pt_a = hl.entity(1);
+    c.pt_b = HEntity { v: 12345 };
+    assert_eq!(
+        sk.add_constraint(c).err(),
+        Some(SketchError::UnknownEntity(12345))
+    );
+
+    assert_eq!(sk.group.len(), 1);
+    assert_eq!(sk.request.len(), 1);
+    assert!(sk.constraint.is_empty());
+    assert!(sk.find_group(HGroup { v: 99 }).is_err());
+}
+
+#[test]
+fn wrong_entity_types_change_nothing() {
+    let mut sk = Sketch::new();
+    let g1 = sk.add_group(Group::new(GroupType::Drawing3D)).unwrap();
+    let hl = sk
+        .add_request(Request::new(RequestType::LineSegment, g1, FREE_IN_3D))
+        .unwrap();
+    let (ln, a) = (hl.entity(0), hl.entity(1));
+
+    let mut c = constraint(ConstraintType::PtPtDistance, g1, FREE_IN_3D);
+    c.pt_a = a;
+    c.pt_b = ln;
+    assert_eq!(
+        sk.add_constraint(c).err(),
+        Some(SketchError::NotAPoint(ln.v))
+    );
+    let mut c = constraint(ConstraintType::PtOnLine, g1, FREE_IN_3D);
+    c.pt_a = a;
+    c.entity_a = a;
+    assert_eq!(
+        sk.add_constraint(c).err(),
+        Some(SketchError::WrongEntityType(a.v))
+    );
+    let mut c = constraint(ConstraintType::Diameter, g1, FREE_IN_3D);
+    c.entity_a = ln;
+    assert_eq!(
+        sk.add_constraint(c).err(),
+        Some(SketchError::WrongEntityType(ln.v))
+    );
+    let mut c = constraint(ConstraintType::PtPtDistance, g1, ln);
+    c.pt_a = a;
+    c.pt_b = hl.entity(2);
+    assert_eq!(
+        sk.add_constraint(c).err(),
+        Some(SketchError::NotAWorkplane(ln.v))
+    );
+
+    // Horizontal and vertical only mean something in a workplane.
+    let mut c = constraint(ConstraintType::Horizontal, g1, FREE_IN_3D);
+    c.entity_a = ln;
+    assert_eq!(
+        sk.add_constraint(c).err(),
+        Some(SketchError::NotInWorkplane)
+    );
+    let mut c = constraint(ConstraintType::SymmetricVert, g1, FREE_IN_3D);
+    c.pt_a = a;
+    c.pt_b = hl.entity(2);
+    assert_eq!(
+        sk.add_constraint(c).err(),
+        Some(SketchError::NotInWorkplane)
+    );
+
+    assert!(sk.constraint.is_empty());
+}
+
+#[test]
+fn measure_only_what_has_a_value() {
+    let mut sk = Sketch::new();
+    let g1 = sk.add_group(Group::new(GroupType::Drawing3D)).unwrap();
+    let hl = sk
+        .add_request(Request::new(RequestType::LineSegment, g1, FREE_IN_3D))
+        .unwrap();
+    let (a, b) = (hl.entity(1), hl.entity(2));
+    sk.point_force_to(a, Vector::new(0.0, 0.0, 0.0));
+    sk.point_force_to(b, Vector::new(3.0, 4.0, 0.0));
+
+    let mut c = constraint(ConstraintType::PtPtDistance, g1, FREE_IN_3D);
+    c.pt_a = a;
+    c.pt_b = b;
+    assert!((c.measure(&sk).unwrap() - 5.0).abs() < 1e-9);
+
+    // Three equations in 3d, and no value to measure.
+    c.type_ = ConstraintType::PointsCoincident;
+    assert_eq!(c.measure(&sk), None);
+}
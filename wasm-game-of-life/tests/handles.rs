@@ -0,0 +1,58 @@
+// Derived handles carry what they're derived from under two tag bits, so a
+// constraint, a request with params, or the request of an entity with an
+// equation, has 14 bits; past that it would run into the tags and alias
+// some other handle.
+
+use wasm_game_of_life::sketch::{HConstraint, HGroup, HRequest};
+
+const LAST: u32 = (1 << 14) - 1;
+
+#[test]
+fn last_constraint_keeps_its_tags() {
+    let hc = HConstraint { v: LAST };
+    let he = hc.equation(0xffff);
+    assert!(he.is_from_constraint());
+    assert!(he.constraint() == hc);
+
+    let hp = hc.param(0xffff);
+    assert_eq!(hp.v & 0xc0000000, 0x40000000);
+    assert!(!hp.is_from_group());
+    assert!(hp != HRequest { v: LAST }.param(0xffff));
+}
+
+#[test]
+fn last_request_entity_equation_keeps_its_tags() {
+    let he = HRequest { v: LAST }.entity(0xffff);
+    let eq = he.equation();
+    assert!(!eq.is_from_constraint());
+    assert!(eq != HConstraint { v: LAST }.equation(0xffff));
+    assert!(eq != HGroup { v: LAST }.equation(0xffff));
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "out of range")]
+fn constraint_param_past_14_bits() {
+    HConstraint { v: 1 << 14 }.param(0);
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "out of range")]
+fn constraint_equation_past_14_bits() {
+    HConstraint { v: 1 << 14 }.equation(0);
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "out of range")]
+fn entity_equation_past_14_bits() {
+    HRequest { v: 1 << 14 }.entity(0).equation();
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "out of range")]
+fn request_param_past_14_bits() {
+    HRequest { v: 1 << 14 }.param(0);
+}
@@ -0,0 +1,171 @@
+// Handles that outlive what they pointed at: once something is removed,
+// its handle has to miss, however much is added afterwards; and when there
+// are no handles left, adding fails rather than reusing one.
+
+use wasm_game_of_life::delete::SketchItem;
+use wasm_game_of_life::id_list::IdList;
+use wasm_game_of_life::sketch::{
+    Group, GroupType, HGroup, HParam, Param, Request, RequestType, Sketch, SketchError, FREE_IN_3D,
+    NO_PARAM,
+};
+
+fn param(val: f64) -> Param {
+    Param {
+        tag: 0,
+        h: HParam { v: 0 },
+        val,
+        known: false,
+        free: false,
+        substd: NO_PARAM,
+    }
+}
+
+fn param_at(v: u32, val: f64) -> Param {
+    let mut p = param(val);
+    p.h = HParam { v };
+    p
+}
+
+#[test]
+fn stale_handle_misses_after_remove_and_add() {
+    let mut l: IdList<HParam, Param> = IdList::new();
+    let a = l.add_and_assign_id(param(1.0)).unwrap();
+    l.remove(&a);
+    let b = l.add_and_assign_id(param(2.0)).unwrap();
+
+    assert!(a != b);
+    assert!(l.get(&a).is_none());
+    assert!(l.is_stale(&a));
+    assert_eq!(l[&b].val, 2.0);
+}
+
+#[test]
+fn kept_in_handle_order() {
+    let mut l: IdList<HParam, Param> = IdList::new();
+    for v in [5, 2, 9, 1] {
+        l.add(param_at(v, v as f64));
+    }
+    let keys: Vec<u32> = l.keys().map(|h| h.v).collect();
+    assert_eq!(keys, vec![1, 2, 5, 9]);
+    // Fresh handles come after any that were ever added.
+    assert_eq!(l.fresh_handle().unwrap().v, 10);
+
+    l.add(param_at(5, 50.0));
+    assert_eq!(l.len(), 4);
+    assert_eq!(l[&HParam { v: 5 }].val, 50.0);
+}
+
+#[test]
+fn remove_tagged() {
+    let mut l: IdList<HParam, Param> = IdList::new();
+    for v in 1..=4 {
+        l.add(param_at(v, 0.0));
+    }
+    l.tag(&HParam { v: 3 }, 1);
+    l.tag(&HParam { v: 1 }, 1);
+    let gone: Vec<u32> = l.remove_tagged().iter().map(|p| p.h.v).collect();
+    assert_eq!(gone, vec![1, 3]);
+    assert!(l.is_stale(&HParam { v: 1 }));
+    assert_eq!(l.len(), 2);
+
+    l.clear();
+    assert!(l.is_empty());
+    assert!(!l.is_stale(&HParam { v: 1 }));
+    assert_eq!(l.fresh_handle().unwrap().v, 5);
+}
+
+#[test]
+#[should_panic(expected = "removed handle")]
+fn add_under_removed_handle_panics() {
+    let mut l: IdList<HParam, Param> = IdList::new();
+    let a = l.add_and_assign_id(param(1.0)).unwrap();
+    l.remove(&a);
+    let mut p = param(2.0);
+    p.h = a;
+    l.add(p);
+}
+
+#[test]
+fn taken_handle_can_come_back() {
+    let mut l: IdList<HParam, Param> = IdList::new();
+    let a = l.add_and_assign_id(param(1.0)).unwrap();
+    let mut p = l.take(&a).unwrap();
+    assert!(!l.is_stale(&a));
+    p.val = 2.0;
+    l.add(p);
+    assert_eq!(l[&a].val, 2.0);
+}
+
+#[test]
+fn deleted_request_entities_stay_stale() {
+    let mut sk = Sketch::new();
+    let hg = sk.add_group(Group::new(GroupType::Drawing3D)).unwrap();
+    let hr = sk
+        .add_request(Request::new(RequestType::LineSegment, hg, FREE_IN_3D))
+        .unwrap();
+    let he = hr.entity(1);
+    let hp = sk.get_entity(he).param[0];
+
+    sk.delete(&[SketchItem::Request(hr)]);
+    let hr2 = sk
+        .add_request(Request::new(RequestType::LineSegment, hg, FREE_IN_3D))
+        .unwrap();
+
+    assert!(hr2 != hr);
+    assert!(sk.entity.get(&he).is_none());
+    assert!(sk.entity.is_stale(&he));
+    assert!(sk.param.is_stale(&hp));
+}
+
+#[test]
+fn removed_runs_join_up() {
+    let mut l: IdList<HParam, Param> = IdList::new();
+    let h: Vec<HParam> = (0..8)
+        .map(|_| l.add_and_assign_id(param(0.0)).unwrap())
+        .collect();
+    // Out of order, so runs are made on both sides and then joined.
+    for i in [1, 5, 3, 2, 4, 6] {
+        l.remove(&h[i]);
+    }
+    for (i, h) in h.iter().enumerate() {
+        assert_eq!(l.is_stale(h), (1..=6).contains(&i), "{}", i);
+        assert_eq!(l.contains_key(h), !(1..=6).contains(&i), "{}", i);
+    }
+    assert!(!l.is_stale(&HParam { v: 0 }));
+    assert!(!l.is_stale(&HParam { v: u32::MAX }));
+}
+
+#[test]
+fn handles_run_out() {
+    let mut l: IdList<HParam, Param> = IdList::with_limit(3);
+    assert!(l.add_and_assign_id(param(1.0)).is_some());
+    assert!(l.add_and_assign_id(param(2.0)).is_some());
+    assert!(l.add_and_assign_id(param(3.0)).is_none());
+    assert_eq!(l.len(), 2);
+
+    // Past the last handle there's nowhere to go, rather than giving out
+    // the last one again.
+    let mut l: IdList<HParam, Param> = IdList::new();
+    let mut p = param(1.0);
+    p.h = HParam { v: u32::MAX };
+    l.add(p);
+    assert!(l.fresh_handle().is_none());
+    assert!(l.add_and_assign_id(param(2.0)).is_none());
+    assert_eq!(l[&HParam { v: u32::MAX }].val, 1.0);
+}
+
+#[test]
+fn sketch_runs_out_of_groups() {
+    let mut sk = Sketch::new();
+    let hg = sk.add_group(Group::new(GroupType::Drawing3D)).unwrap();
+    let mut g = Group::new(GroupType::Drawing3D);
+    g.h = HGroup { v: (1 << 14) - 1 };
+    sk.group.add(g);
+    assert!(matches!(
+        sk.add_group(Group::new(GroupType::Drawing3D)),
+        Err(SketchError::OutOfHandles)
+    ));
+    assert!(sk
+        .add_request(Request::new(RequestType::LineSegment, hg, FREE_IN_3D))
+        .is_ok());
+}
@@ -0,0 +1,146 @@
+// Faces are triangulated into meshes, and closed meshes are cut into each
+// other; the volume of what's left says whether it came out right.
+
+use wasm_game_of_life::dsc::{RgbaColor, Vector};
+use wasm_game_of_life::polygon::{SMesh, SOutlineList, SPolygon, STriMeta};
+use wasm_game_of_life::sketch::{Group, GroupCombineAs, GroupType, Sketch};
+
+const META: STriMeta = STriMeta {
+    face: 0,
+    color: RgbaColor {
+        red: 0,
+        green: 0,
+        blue: 0,
+        alpha: 255,
+    },
+};
+
+fn polygon(normal: Vector, contours: &[&[Vector]]) -> SPolygon {
+    let mut sp = SPolygon::new();
+    sp.normal = normal;
+    for c in contours {
+        sp.add_empty_contour();
+        for p in c.iter() {
+            sp.add_point(*p);
+        }
+    }
+    sp
+}
+
+fn area(m: &SMesh) -> f64 {
+    m.l.iter().map(|t| t.area()).sum()
+}
+
+// An axis-aligned box from o, s on a side, each face wound anticlockwise
+// seen from outside.
+fn cube(o: Vector, s: f64) -> SMesh {
+    let p = |x: f64, y: f64, z: f64| o + Vector::new(x, y, z) * s;
+    let faces: [(Vector, [Vector; 4]); 6] = [
+        (
+            Vector::new(0.0, 0.0, -1.0),
+            [p(0., 0., 0.), p(0., 1., 0.), p(1., 1., 0.), p(1., 0., 0.)],
+        ),
+        (
+            Vector::new(0.0, 0.0, 1.0),
+            [p(0., 0., 1.), p(1., 0., 1.), p(1., 1., 1.), p(0., 1., 1.)],
+        ),
+        (
+            Vector::new(0.0, -1.0, 0.0),
+            [p(0., 0., 0.), p(1., 0., 0.), p(1., 0., 1.), p(0., 0., 1.)],
+        ),
+        (
+            Vector::new(0.0, 1.0, 0.0),
+            [p(0., 1., 0.), p(0., 1., 1.), p(1., 1., 1.), p(1., 1., 0.)],
+        ),
+        (
+            Vector::new(-1.0, 0.0, 0.0),
+            [p(0., 0., 0.), p(0., 0., 1.), p(0., 1., 1.), p(0., 1., 0.)],
+        ),
+        (
+            Vector::new(1.0, 0.0, 0.0),
+            [p(1., 0., 0.), p(1., 1., 0.), p(1., 1., 1.), p(1., 0., 1.)],
+        ),
+    ];
+    let mut m = SMesh::new();
+    for (n, pts) in faces.iter() {
+        polygon(*n, &[&pts[..]]).triangulate_into(&mut m, META);
+    }
+    m
+}
+
+fn close(a: f64, b: f64) -> bool {
+    (a - b).abs() < 1e-9
+}
+
+#[test]
+fn triangulate_a_square_with_a_hole() {
+    let v = |x: f64, y: f64| Vector::new(x, y, 0.0);
+    let outer = [v(0.0, 0.0), v(2.0, 0.0), v(2.0, 2.0), v(0.0, 2.0)];
+    let hole = [v(0.5, 0.5), v(0.5, 1.5), v(1.5, 1.5), v(1.5, 0.5)];
+    let mut m = SMesh::new();
+    polygon(Vector::new(0.0, 0.0, 1.0), &[&outer, &hole]).triangulate_into(&mut m, META);
+    assert!(close(area(&m), 3.0), "{}", area(&m));
+    for t in &m.l {
+        assert!(t.normal().z > 0.0);
+    }
+}
+
+#[test]
+fn a_cube_is_closed() {
+    let m = cube(Vector::new(0.0, 0.0, 0.0), 2.0);
+    assert_eq!(m.l.len(), 12);
+    assert!(close(m.calculate_volume(), 8.0));
+    // Just its twelve edges, not the diagonals across its faces.
+    let mut sol = SOutlineList::new();
+    m.make_outlines_into(&mut sol);
+    assert_eq!(sol.l.len(), 12);
+}
+
+#[test]
+fn booleans_of_overlapping_cubes() {
+    let a = cube(Vector::new(0.0, 0.0, 0.0), 1.0);
+    let b = cube(Vector::new(0.5, 0.0, 0.0), 1.0);
+    let vol = |m: SMesh| m.calculate_volume();
+    assert!(close(vol(SMesh::make_from_union_of(&a, &b)), 1.5));
+    assert!(close(vol(SMesh::make_from_difference_of(&a, &b)), 0.5));
+    assert!(close(vol(SMesh::make_from_intersection_of(&a, &b)), 0.5));
+    assert!(close(vol(SMesh::make_from_assembly_of(&a, &b)), 2.0));
+}
+
+#[test]
+fn booleans_of_apart_cubes() {
+    let a = cube(Vector::new(0.0, 0.0, 0.0), 1.0);
+    let b = cube(Vector::new(3.0, 0.0, 0.0), 1.0);
+    let vol = |m: SMesh| m.calculate_volume();
+    assert!(close(vol(SMesh::make_from_union_of(&a, &b)), 2.0));
+    assert!(close(vol(SMesh::make_from_difference_of(&a, &b)), 1.0));
+    assert!(SMesh::make_from_intersection_of(&a, &b).is_empty());
+}
+
+// Each group's solid is cut into the solid so far, in order; a suppressed
+// one just passes on what came before it.
+#[test]
+fn linked_groups_combine_in_order() {
+    let mut sk = Sketch::new();
+    let mut g = Group::new(GroupType::Linked);
+    g.imp_mesh = cube(Vector::new(0.0, 0.0, 0.0), 1.0);
+    let g1 = sk.add_group(g).unwrap();
+    let mut g = Group::new(GroupType::Linked);
+    g.imp_mesh = cube(Vector::new(0.5, 0.0, 0.0), 1.0);
+    g.mesh_combine = GroupCombineAs::Difference;
+    let g2 = sk.add_group(g).unwrap();
+
+    sk.regenerate_all();
+    let vol = |sk: &Sketch, hg| sk.get_group(hg).running_mesh.calculate_volume();
+    assert!(close(vol(&sk, g1), 1.0));
+    assert!(close(vol(&sk, g2), 0.5));
+    assert!(close(sk.get_group(g2).this_mesh.calculate_volume(), 1.0));
+
+    sk.generate_display_items(g1);
+    assert_eq!(sk.get_group(g1).display_outlines.l.len(), 12);
+    assert!(!sk.get_group(g1).display_dirty);
+
+    sk.group.get_mut(&g2).unwrap().suppress = true;
+    sk.regenerate_all();
+    assert!(close(vol(&sk, g2), 1.0));
+}
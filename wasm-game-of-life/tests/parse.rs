@@ -0,0 +1,100 @@
+// What a user types for a variable or a dimension: lengths come out in mm
+// and angles in degrees, whatever units they were given in.
+
+use std::f64::consts::PI;
+use wasm_game_of_life::expr::{Expr, ExprError};
+
+fn value(s: &str) -> Result<f64, ExprError> {
+    Expr::parse_value(s, &mut |n| match n {
+        "w" => Ok(Expr::from_constant(4.0)),
+        _ => Err(ExprError::UnknownVariable(n.to_string())),
+    })
+}
+
+fn check(s: &str, want: f64) {
+    match value(s) {
+        Ok(v) => assert!((v - want).abs() < 1e-9, "{} is {}, not {}", s, v, want),
+        Err(e) => panic!("{}: {}", s, e),
+    }
+}
+
+fn syntax_error_at(s: &str) -> usize {
+    match value(s) {
+        Err(ExprError::Syntax(pos, _)) => pos,
+        r => panic!("{} gave {:?}", s, r),
+    }
+}
+
+#[test]
+fn units() {
+    check("3", 3.0);
+    check("3 mm", 3.0);
+    check("3 cm", 30.0);
+    check("3 m", 3000.0);
+    check("2 in", 50.8);
+    check("2 ft", 609.6);
+    check("30 deg", 30.0);
+    check("pi rad", 180.0);
+    check("1.5e1 cm", 150.0);
+    check("(1 + 1) in", 50.8);
+    check("w cm", 40.0);
+    check("2 * 3 cm", 60.0);
+}
+
+#[test]
+fn precedence() {
+    check("1 + 2 * 3", 7.0);
+    check("(1 + 2) * 3", 9.0);
+    check("8 / 4 / 2", 1.0);
+    check("8 - 4 - 2", 2.0);
+    check("w / 2 + 3 mm", 5.0);
+    check("-2 * 3", -6.0);
+    check("2 * -3", -6.0);
+    check("--w", 4.0);
+    check("-(1 - w)", 3.0);
+    check("+w - -1", 5.0);
+}
+
+#[test]
+fn functions() {
+    check("sqrt(w)", 2.0);
+    check("sin(30)", 0.5);
+    check("cos(60 deg)", 0.5);
+    check("asin(0.5)", 30.0);
+    check("acos(0)", 90.0);
+    // A unit goes with the operand before it, not the whole quotient.
+    check("sin((pi / 2) rad)", 1.0);
+    check("pi / 2 rad", PI * PI / 360.0);
+    check("2 * pi", 2.0 * PI);
+}
+
+#[test]
+fn errors() {
+    assert_eq!(syntax_error_at(""), 0);
+    assert_eq!(syntax_error_at("1 +"), 3);
+    assert_eq!(syntax_error_at("1 2"), 2);
+    assert_eq!(syntax_error_at("(1 + 2"), 6);
+    assert_eq!(syntax_error_at("1 + )"), 4);
+    assert_eq!(syntax_error_at("3 # 4"), 2);
+    assert_eq!(syntax_error_at("1..2"), 0);
+    assert_eq!(syntax_error_at("mm"), 0);
+    assert_eq!(syntax_error_at("sqrt 4"), 5);
+    assert_eq!(
+        value("w + h"),
+        Err(ExprError::UnknownVariable("h".to_string()))
+    );
+    assert_eq!(value("sqrt(-1)"), Err(ExprError::NotFinite));
+    assert_eq!(value("1 / 0"), Err(ExprError::NotFinite));
+}
+
+#[test]
+fn names() {
+    assert!(Expr::is_valid_name("width"));
+    assert!(Expr::is_valid_name("_w2"));
+    assert!(!Expr::is_valid_name("2w"));
+    assert!(!Expr::is_valid_name("a-b"));
+    assert!(!Expr::is_valid_name(""));
+    for reserved in ["mm", "in", "rad", "sin", "pi"] {
+        assert!(!Expr::is_valid_name(reserved), "{}", reserved);
+    }
+}
@@ -0,0 +1,88 @@
+// A reference dimension measures the geometry instead of driving it. A
+// group with all_dims_reference treats every dimension that way, but only
+// for as long as the flag is set.
+
+mod common;
+
+use common::{distance, pinned_line, workplane};
+use wasm_game_of_life::dsc::Vector;
+use wasm_game_of_life::sketch::{Equation, HGroup, HRequest, Sketch};
+
+fn length(sk: &Sketch, hl: HRequest) -> f64 {
+    let a = sk.get_entity(hl.entity(1)).point_get_num(sk);
+    let b = sk.get_entity(hl.entity(2)).point_get_num(sk);
+    (b - a).magnitude()
+}
+
+fn set_all_dims_reference(sk: &mut Sketch, hg: HGroup, on: bool) {
+    sk.group.get_mut(&hg).unwrap().all_dims_reference = on;
+    sk.mark_group_dirty(hg);
+    sk.regenerate_dirty();
+}
+
+#[test]
+fn reference_dimension_writes_no_equation() {
+    let mut sk = Sketch::new();
+    let (_, wrkpl) = workplane(&mut sk);
+    let (hg, hl, _) = pinned_line(&mut sk, wrkpl, 5.0);
+    let href = distance(&mut sk, hg, wrkpl, hl.entity(1), hl.entity(2), 123.0);
+    sk.constraint.get_mut(&href).unwrap().reference = true;
+    let c = sk.get_constraint(href);
+    assert!(c.is_dimension());
+
+    // Unless it's written to be measured.
+    let mut l: Vec<Equation> = Vec::new();
+    c.generate_equations(&sk, &mut l, false);
+    assert!(l.is_empty());
+    c.generate_equations(&sk, &mut l, true);
+    assert_eq!(l.len(), 1);
+    assert!((c.measure(&sk).unwrap() - length(&sk, hl)).abs() < 1e-9);
+}
+
+#[test]
+fn reference_dimension_follows_the_geometry() {
+    let mut sk = Sketch::new();
+    let (_, wrkpl) = workplane(&mut sk);
+    let (hg, hl, hc) = pinned_line(&mut sk, wrkpl, 5.0);
+    let href = distance(&mut sk, hg, wrkpl, hl.entity(1), hl.entity(2), 123.0);
+    sk.constraint.get_mut(&href).unwrap().reference = true;
+    sk.regenerate_all();
+    assert!((sk.constraint[&href].val_a - 5.0).abs() < 1e-9);
+
+    sk.constraint.get_mut(&hc).unwrap().val_a = 7.0;
+    sk.mark_group_dirty(hg);
+    sk.regenerate_dirty();
+    assert!((length(&sk, hl) - 7.0).abs() < 1e-9);
+    let r = sk.reference_dimensions(hg);
+    assert_eq!(r.len(), 1);
+    assert!(r[0].0 == href);
+    assert!((r[0].1 - 7.0).abs() < 1e-9);
+    assert!((sk.constraint[&href].val_a - 7.0).abs() < 1e-9);
+    assert!(sk.constraint[&hc].measured.is_none());
+}
+
+#[test]
+fn all_dims_reference_keeps_the_driving_values() {
+    let mut sk = Sketch::new();
+    let (_, wrkpl) = workplane(&mut sk);
+    let (hg, hl, hc) = pinned_line(&mut sk, wrkpl, 5.0);
+    sk.regenerate_all();
+    assert!(sk.reference_dimensions(hg).is_empty());
+
+    set_all_dims_reference(&mut sk, hg, true);
+    // Nothing holds the far end now, so it stays put wherever it goes.
+    sk.point_force_to(hl.entity(2), Vector::new(6.0, 8.0, 0.0));
+    sk.mark_group_dirty(hg);
+    sk.regenerate_dirty();
+    assert!((length(&sk, hl) - 10.0).abs() < 1e-9);
+    let r = sk.reference_dimensions(hg);
+    assert_eq!(r.len(), 1);
+    assert!(r[0].0 == hc);
+    assert!((r[0].1 - 10.0).abs() < 1e-9);
+    assert_eq!(sk.constraint[&hc].val_a, 5.0);
+
+    set_all_dims_reference(&mut sk, hg, false);
+    assert!((length(&sk, hl) - 5.0).abs() < 1e-9);
+    assert!(sk.reference_dimensions(hg).is_empty());
+    assert!(sk.constraint[&hc].measured.is_none());
+}
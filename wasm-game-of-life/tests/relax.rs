@@ -0,0 +1,142 @@
+// Constraints that can't all hold at once: a relaxed solve settles on the
+// least-squares compromise instead of failing, and reports how far off
+// each constraint is left there.
+
+mod common;
+
+use common::{distance, line, sketch_in, where_dragged, workplane};
+use wasm_game_of_life::dsc::Vector;
+use wasm_game_of_life::expr::Expr;
+use wasm_game_of_life::sketch::{
+    Equation, GroupSolved, HConstraint, HParam, Param, Sketch, NO_PARAM,
+};
+use wasm_game_of_life::system::{SolveResult, System};
+
+const X: HParam = HParam { v: 1 };
+const Y: HParam = HParam { v: 2 };
+
+fn x() -> Expr {
+    Expr::from_param(X)
+}
+
+fn y() -> Expr {
+    Expr::from_param(Y)
+}
+
+fn c(v: f64) -> Expr {
+    Expr::from_constant(v)
+}
+
+// Each equation as the only one of its own constraint, 1, 2, 3 and on.
+fn system(eqs: &[Expr], relaxed: bool) -> System {
+    let mut sys = System::new();
+    sys.relaxed = relaxed;
+    for h in [X, Y] {
+        sys.add_param(Param {
+            tag: 0,
+            h,
+            val: 0.0,
+            known: false,
+            free: false,
+            substd: NO_PARAM,
+        });
+    }
+    for (i, e) in eqs.iter().enumerate() {
+        let hc = HConstraint { v: i as u32 + 1 };
+        sys.add_equation(Equation {
+            tag: 0,
+            h: hc.equation(0),
+            e: e.clone(),
+        });
+    }
+    sys
+}
+
+fn solve(sys: &mut System) -> GroupSolved {
+    let mut solved = GroupSolved::default();
+    sys.solve(&mut solved, false, false, false);
+    solved
+}
+
+fn residual(solved: &GroupSolved, v: u32) -> f64 {
+    match solved.residual.iter().find(|(h, _)| h.v == v) {
+        Some((_, r)) => *r,
+        None => panic!("no residual for constraint {}", v),
+    }
+}
+
+#[test]
+fn least_squares_compromise() {
+    // x + y = 2, x = y and x = 2 give the normal equations 3x = 4, 2y = 2.
+    let eqs = [x() + y() - c(2.0), x() - y(), x() - c(2.0)];
+    let mut sys = system(&eqs, false);
+    assert_eq!(solve(&mut sys).how, SolveResult::RedundantDidntConverge);
+
+    let mut sys = system(&eqs, true);
+    let solved = solve(&mut sys);
+    assert_eq!(solved.how, SolveResult::RedundantOkay);
+    let (vx, vy) = (sys.param[&X].val, sys.param[&Y].val);
+    assert!((vx - 4.0 / 3.0).abs() < 1e-6, "x = {}", vx);
+    assert!((vy - 1.0).abs() < 1e-6, "y = {}", vy);
+
+    // What's reported is what the equations come to where it stopped.
+    assert_eq!(solved.residual.len(), eqs.len());
+    for (i, e) in eqs.iter().enumerate() {
+        let want = e.eval(&sys.param).abs();
+        let got = residual(&solved, i as u32 + 1);
+        assert!(
+            (got - want).abs() < 1e-12,
+            "{}: {} not {}",
+            i + 1,
+            got,
+            want
+        );
+    }
+    assert!((residual(&solved, 1) - 1.0 / 3.0).abs() < 1e-6);
+    assert!((residual(&solved, 2) - 1.0 / 3.0).abs() < 1e-6);
+    assert!((residual(&solved, 3) - 2.0 / 3.0).abs() < 1e-6);
+}
+
+#[test]
+fn consistent_equations_still_solve_exactly() {
+    let eqs = [x() + y() - c(3.0), x() - y() - c(1.0)];
+    let mut sys = system(&eqs, true);
+    let solved = solve(&mut sys);
+    assert_eq!(solved.how, SolveResult::Okay);
+    assert!((sys.param[&X].val - 2.0).abs() < 1e-9);
+    assert!((sys.param[&Y].val - 1.0).abs() < 1e-9);
+    assert!(solved.residual.iter().all(|(_, r)| *r < 1e-9));
+}
+
+// A line held at one end with two lengths it can't both have comes out at
+// the length between them, a unit off each.
+#[test]
+fn group_with_conflicting_lengths() {
+    let mut sk = Sketch::new();
+    let (_, wrkpl) = workplane(&mut sk);
+    let hg = sketch_in(&mut sk, wrkpl);
+    sk.group.get_mut(&hg).unwrap().relax_constraints = true;
+    let hl = line(
+        &mut sk,
+        hg,
+        wrkpl,
+        Vector::new(0.0, 0.0, 0.0),
+        Vector::new(3.0, 1.0, 0.0),
+    );
+    let (a, b) = (hl.entity(1), hl.entity(2));
+    let held = where_dragged(&mut sk, hg, wrkpl, a);
+    let short = distance(&mut sk, hg, wrkpl, a, b, 4.0);
+    let long = distance(&mut sk, hg, wrkpl, a, b, 6.0);
+
+    let how = sk.solve_group(hg, false, false);
+    assert_eq!(how, SolveResult::RedundantOkay);
+    let pa = sk.get_entity(a).point_get_num(&sk);
+    let pb = sk.get_entity(b).point_get_num(&sk);
+    assert!(pa.equals_with_def_tol(Vector::new(0.0, 0.0, 0.0)));
+    assert!(((pb - pa).magnitude() - 5.0).abs() < 1e-6);
+
+    let solved = &sk.get_group(hg).solved;
+    assert!(residual(solved, held.v) < 1e-9);
+    assert!((residual(solved, short.v) - 1.0).abs() < 1e-6);
+    assert!((residual(solved, long.v) - 1.0).abs() < 1e-6);
+}
@@ -0,0 +1,103 @@
+// A request makes its entities and params under handles derived from its
+// own, so generating it again gives the same ones, keeping their values.
+// What it can be asked to make is limited: a cubic's extra points have to
+// fit in one entity along with the four it always has.
+
+use wasm_game_of_life::dsc::Vector;
+use wasm_game_of_life::sketch::{
+    EntityBaseType, Group, GroupType, Request, RequestType, Sketch, SketchError, FREE_IN_3D,
+    MAX_POINTS_IN_ENTITY,
+};
+
+fn cubic(sk: &mut Sketch, extra_points: usize) -> Request {
+    let hg = sk.add_group(Group::new(GroupType::Drawing3D)).unwrap();
+    let mut r = Request::new(RequestType::Cubic, hg, FREE_IN_3D);
+    r.extra_points = extra_points;
+    r
+}
+
+#[test]
+fn line_segment() {
+    let mut sk = Sketch::new();
+    let g = sk.add_group(Group::new(GroupType::Drawing3D)).unwrap();
+    let hr = sk
+        .add_request(Request::new(RequestType::LineSegment, g, FREE_IN_3D))
+        .unwrap();
+    let l = sk.get_entity(hr.entity(0));
+    assert!(l.type_ == EntityBaseType::LineSegment);
+    assert!(l.point[0] == hr.entity(1) && l.point[1] == hr.entity(2));
+    for i in 1..=2 {
+        let p = sk.get_entity(hr.entity(i));
+        assert!(p.type_ == EntityBaseType::PointIn3D);
+        assert!(p.group == g);
+        assert!(p.param[0] == hr.param(16 + 3 * (i - 1)));
+    }
+    assert_eq!(sk.entity.len(), 3);
+    assert_eq!(sk.param.len(), 6);
+    assert!(sk.param.keys().all(|h| h.request() == hr));
+
+    sk.point_force_to(hr.entity(2), Vector::new(3.0, 4.0, 5.0));
+    sk.generate_requests(i32::MIN);
+    assert_eq!(sk.entity.len(), 3);
+    let p = sk.get_entity(hr.entity(2)).point_get_num(&sk);
+    assert!(p.equals_with_def_tol(Vector::new(3.0, 4.0, 5.0)));
+}
+
+#[test]
+fn workplane_and_a_line_in_it() {
+    let mut sk = Sketch::new();
+    let g = sk.add_group(Group::new(GroupType::Drawing3D)).unwrap();
+    let hw = sk
+        .add_request(Request::new(RequestType::Workplane, g, FREE_IN_3D))
+        .unwrap();
+    let wrkpl = hw.entity(0);
+    let w = sk.get_entity(wrkpl);
+    assert!(w.type_ == EntityBaseType::Workplane);
+    // A new normal starts out as the identity rotation.
+    let n = sk.get_entity(w.normal);
+    assert!(n.type_ == EntityBaseType::NormalIn3D);
+    let q: Vec<f64> = n.param[..4].iter().map(|h| sk.get_param(*h).val).collect();
+    assert_eq!(q, vec![1.0, 0.0, 0.0, 0.0]);
+    sk.point_force_to(w.point[0], Vector::new(0.0, 0.0, 7.0));
+
+    let hl = sk
+        .add_request(Request::new(RequestType::LineSegment, g, wrkpl))
+        .unwrap();
+    assert!(hl != hw);
+    let b = hl.entity(2);
+    assert!(sk.get_entity(b).type_ == EntityBaseType::PointIn2D);
+    // Only u and v; off the plane is projected onto it.
+    sk.point_force_to(b, Vector::new(1.0, 2.0, 3.0));
+    let p = sk.get_entity(b).point_get_num(&sk);
+    assert!(p.equals_with_def_tol(Vector::new(1.0, 2.0, 7.0)));
+    let vals: Vec<f64> = (16 + 3..16 + 5)
+        .map(|i: u32| sk.get_param(hl.param(i)).val)
+        .collect();
+    assert_eq!(vals, vec![1.0, 2.0]);
+    assert!(sk.param.get(&hl.param(16 + 5)).is_none());
+}
+
+#[test]
+fn cubic_with_as_many_points_as_fit() {
+    let mut sk = Sketch::new();
+    let max = MAX_POINTS_IN_ENTITY - 4;
+    let r = cubic(&mut sk, max);
+    let hr = sk.add_request(r).unwrap();
+    let e = sk.get_entity(hr.entity(0));
+    assert_eq!(e.extra_points, max);
+    assert!(sk.entity.contains_key(&e.point[MAX_POINTS_IN_ENTITY - 1]));
+}
+
+#[test]
+fn cubic_with_too_many_points() {
+    let mut sk = Sketch::new();
+    let max = MAX_POINTS_IN_ENTITY - 4;
+    let r = cubic(&mut sk, max + 1);
+    assert_eq!(
+        sk.add_request(r).err(),
+        Some(SketchError::TooManyPoints(max))
+    );
+    assert!(sk.request.is_empty());
+    assert!(sk.entity.is_empty());
+    assert!(sk.param.is_empty());
+}
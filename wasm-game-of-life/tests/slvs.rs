@@ -0,0 +1,155 @@
+// The headless solver, described the way libslvs is: params, entities and
+// constraints by handle, solved a group at a time. It takes whatever the
+// caller describes, so anything it can't make sense of has to come back as
+// BadInput rather than a panic, with the params left as they were.
+
+use wasm_game_of_life::sketch::ConstraintType;
+use wasm_game_of_life::slvs::{
+    SlvsConstraint, SlvsEntity, SlvsParam, SlvsResult, SlvsSystem, SLVS_FREE_IN_3D,
+};
+
+const G: u32 = 1;
+
+// Two points in 3d, 101 from params 1-3 and 102 from params 4-6, and a
+// line 200 between them.
+fn system() -> SlvsSystem {
+    let mut sys = SlvsSystem::new();
+    for h in 1..=6 {
+        sys.param.push(SlvsParam::new(h, G, h as f64));
+    }
+    sys.entity.push(SlvsEntity::point_3d(101, G, 1, 2, 3));
+    sys.entity.push(SlvsEntity::point_3d(102, G, 4, 5, 6));
+    sys.entity
+        .push(SlvsEntity::line_segment(200, G, SLVS_FREE_IN_3D, 101, 102));
+    sys
+}
+
+fn distance(a: u32, b: u32) -> SlvsConstraint {
+    let mut c = SlvsConstraint::new(1, G, ConstraintType::PtPtDistance, SLVS_FREE_IN_3D);
+    c.pt_a = a;
+    c.pt_b = b;
+    c.val_a = 10.0;
+    c
+}
+
+fn val(sys: &SlvsSystem, h: u32) -> f64 {
+    sys.param.iter().find(|p| p.h == h).unwrap().val
+}
+
+fn length(sys: &SlvsSystem) -> f64 {
+    (1..=3)
+        .map(|h| (val(sys, h + 3) - val(sys, h)).powi(2))
+        .sum::<f64>()
+        .sqrt()
+}
+
+fn check_bad(mut sys: SlvsSystem) {
+    let before: Vec<f64> = sys.param.iter().map(|p| p.val).collect();
+    assert_eq!(sys.solve(G), SlvsResult::BadInput);
+    assert_eq!(sys.result, SlvsResult::BadInput);
+    assert_eq!(sys.dof, 0);
+    assert!(sys.failed.is_empty());
+    let after: Vec<f64> = sys.param.iter().map(|p| p.val).collect();
+    assert_eq!(before, after);
+}
+
+#[test]
+fn good_input_solves() {
+    let mut sys = system();
+    sys.constraint.push(distance(101, 102));
+    assert_eq!(sys.solve(G), SlvsResult::Okay);
+    assert_eq!(sys.result, SlvsResult::Okay);
+    assert_eq!(sys.dof, 5);
+    assert!((length(&sys) - 10.0).abs() < 1e-9);
+}
+
+#[test]
+fn other_groups_are_fixed() {
+    let mut sys = system();
+    for p in sys.param.iter_mut().filter(|p| p.h <= 3) {
+        p.group = 2;
+    }
+    sys.entity[0].group = 2;
+    sys.constraint.push(distance(101, 102));
+    assert_eq!(sys.solve(G), SlvsResult::Okay);
+    assert_eq!(sys.dof, 2);
+    for h in 1..=3 {
+        assert_eq!(val(&sys, h), h as f64);
+    }
+    assert!((length(&sys) - 10.0).abs() < 1e-9);
+}
+
+#[test]
+fn inconsistent_reports_what_failed() {
+    let mut sys = system();
+    sys.constraint.push(distance(101, 102));
+    let mut c = distance(101, 102);
+    c.h = 2;
+    c.val_a = 20.0;
+    sys.constraint.push(c);
+    sys.calculate_faileds = true;
+    assert_eq!(sys.solve(G), SlvsResult::Inconsistent);
+    let mut failed = sys.failed.clone();
+    failed.sort();
+    assert_eq!(failed, vec![1, 2]);
+}
+
+#[test]
+fn dangling_point() {
+    let mut sys = system();
+    sys.entity
+        .push(SlvsEntity::line_segment(201, G, SLVS_FREE_IN_3D, 101, 999));
+    check_bad(sys);
+}
+
+#[test]
+fn dangling_normal_and_workplane() {
+    let mut sys = system();
+    sys.entity.push(SlvsEntity::workplane(300, G, 101, 999));
+    check_bad(sys);
+
+    let mut sys = system();
+    sys.param.push(SlvsParam::new(7, G, 0.0));
+    sys.param.push(SlvsParam::new(8, G, 0.0));
+    sys.entity.push(SlvsEntity::point_2d(103, G, 999, 7, 8));
+    check_bad(sys);
+}
+
+#[test]
+fn wrong_entity_type() {
+    let mut sys = system();
+    sys.constraint.push(distance(101, 200));
+    check_bad(sys);
+
+    // A line isn't a workplane to draw in.
+    let mut sys = system();
+    let mut c = distance(101, 102);
+    c.wrkpl = 200;
+    sys.constraint.push(c);
+    check_bad(sys);
+}
+
+#[test]
+fn in_plane_constraints_without_a_workplane() {
+    for type_ in [
+        ConstraintType::Horizontal,
+        ConstraintType::Vertical,
+        ConstraintType::SymmetricHoriz,
+        ConstraintType::SymmetricVert,
+    ] {
+        let mut sys = system();
+        let mut c = SlvsConstraint::new(1, G, type_, SLVS_FREE_IN_3D);
+        c.pt_a = 101;
+        c.pt_b = 102;
+        sys.constraint.push(c);
+        check_bad(sys);
+    }
+}
+
+#[test]
+fn param_never_added() {
+    let mut sys = system();
+    sys.entity.push(SlvsEntity::point_3d(103, G, 1, 2, 99));
+    sys.constraint.push(distance(101, 103));
+    check_bad(sys);
+}
@@ -0,0 +1,112 @@
+// Newton's method on a group's equations all at once: a root is found from
+// nearby, whatever freedom is left is counted, and equations that say the
+// same thing twice are told apart from ones that can't all hold.
+
+use wasm_game_of_life::expr::Expr;
+use wasm_game_of_life::sketch::{Equation, GroupSolved, HEquation, HParam, Param};
+use wasm_game_of_life::system::{SolveResult, System, SYSTEM_MAX_UNKNOWNS};
+
+const X: HParam = HParam { v: 1 };
+const Y: HParam = HParam { v: 2 };
+
+fn x() -> Expr {
+    Expr::from_param(X)
+}
+
+fn y() -> Expr {
+    Expr::from_param(Y)
+}
+
+fn c(v: f64) -> Expr {
+    Expr::from_constant(v)
+}
+
+fn param(h: HParam, val: f64) -> Param {
+    Param {
+        tag: 0,
+        h,
+        val,
+        known: false,
+        free: false,
+        substd: HParam { v: 0 },
+    }
+}
+
+// x and y, starting from (x0, y0), and an equation for each of eqs.
+fn system(x0: f64, y0: f64, eqs: Vec<Expr>) -> System {
+    let mut sys = System::new();
+    sys.add_param(param(X, x0));
+    sys.add_param(param(Y, y0));
+    for (v, e) in eqs.into_iter().enumerate() {
+        sys.add_equation(Equation {
+            tag: 0,
+            h: HEquation { v: v as u32 },
+            e,
+        });
+    }
+    sys
+}
+
+fn solve(sys: &mut System) -> GroupSolved {
+    let mut solved = GroupSolved {
+        how: SolveResult::Okay,
+        dof: -1,
+        find_to_fix_timeout: 0,
+        timeout: false,
+        remove: Vec::new(),
+        residual: Vec::new(),
+    };
+    sys.solve(&mut solved, false, false, false);
+    solved
+}
+
+#[test]
+fn circle_and_line() {
+    let eqs = vec![x().square() + y().square() - c(25.0), x() - y() - c(1.0)];
+    let mut sys = system(5.0, 1.0, eqs);
+    let solved = solve(&mut sys);
+    assert_eq!(solved.how, SolveResult::Okay);
+    assert_eq!(solved.dof, 0);
+    let (vx, vy) = (sys.param[&X].val, sys.param[&Y].val);
+    assert!((vx - 4.0).abs() < 1e-9, "x = {}", vx);
+    assert!((vy - 3.0).abs() < 1e-9, "y = {}", vy);
+}
+
+#[test]
+fn one_equation_two_unknowns() {
+    let mut sys = system(0.0, 0.0, vec![x() + y() - c(3.0)]);
+    let solved = solve(&mut sys);
+    assert_eq!(solved.how, SolveResult::Okay);
+    assert_eq!(solved.dof, 1);
+    // The least-squares step moves each of them the same.
+    assert!((sys.param[&X].val - 1.5).abs() < 1e-9);
+    assert!((sys.param[&Y].val - 1.5).abs() < 1e-9);
+}
+
+#[test]
+fn said_twice() {
+    let eqs = vec![x() - c(1.0), c(2.0) * x() - c(2.0), y() - c(2.0)];
+    let mut sys = system(0.0, 0.0, eqs);
+    let solved = solve(&mut sys);
+    assert_eq!(solved.how, SolveResult::RedundantOkay);
+    assert_eq!(solved.dof, 0);
+    assert!((sys.param[&X].val - 1.0).abs() < 1e-9);
+    assert!((sys.param[&Y].val - 2.0).abs() < 1e-9);
+}
+
+#[test]
+fn cant_all_hold() {
+    let eqs = vec![x() - c(1.0), x() - c(2.0), y()];
+    let mut sys = system(0.0, 0.0, eqs);
+    assert_eq!(solve(&mut sys).how, SolveResult::RedundantDidntConverge);
+    assert!(sys.param.values().all(|p| !p.known));
+}
+
+#[test]
+fn too_many_unknowns() {
+    let mut sys = System::new();
+    for v in 1..=SYSTEM_MAX_UNKNOWNS as u32 + 1 {
+        sys.add_param(param(HParam { v }, 0.0));
+    }
+    assert_eq!(solve(&mut sys).how, SolveResult::TooManyUnknowns);
+}
@@ -0,0 +1,463 @@
+// Randomised tests of the solver on sketches built from the sketch model.
+//
+// Each case is generated from a seed: a set of points in a workplane, fully
+// constrained by dimensions measured off a random "true" layout, then
+// nudged away from that layout and solved. Over-constrained cases add one
+// more dimension and check that every constraint the solver suggests
+// removing really does make the group solvable again.
+//
+// Seeds that fail are appended to tests/corpus/solver_fuzz.txt and replayed
+// on every run. SOLVER_FUZZ_CASES and SOLVER_FUZZ_SEED pick how many new
+// cases to try and where to start.
+
+use wasm_game_of_life::id_list::IdList;
+use wasm_game_of_life::sketch::{
+    Constraint, ConstraintType, EntityBase, EntityBaseType, Equation, GroupSolved, HConstraint,
+    HEntity, HGroup, HParam, Param, Sketch, NO_PARAM,
+};
+use wasm_game_of_life::system::{SolveResult, System};
+
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::panic;
+
+const CORPUS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/corpus/solver_fuzz.txt");
+const DEFAULT_CASES: u64 = 100;
+
+const WORKPLANE: HEntity = HEntity { v: 1 };
+const G_BASE: HGroup = HGroup { v: 1 };
+const G_SKETCH: HGroup = HGroup { v: 2 };
+
+const RESIDUAL_TOLERANCE: f64 = 1e-6;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+enum Kind {
+    Well,
+    Over,
+}
+
+// xorshift64*; good enough, and keeps the cases the same on every platform.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Rng(seed.wrapping_mul(0x9e3779b97f4a7c15) | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545f4914f6cdd1d)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn range(&mut self, a: f64, b: f64) -> f64 {
+        a + (b - a) * ((self.next() >> 11) as f64 / (1u64 << 53) as f64)
+    }
+}
+
+struct Case {
+    sk: Sketch,
+    points: Vec<HEntity>,
+    next_entity: u32,
+    next_constraint: u32,
+}
+
+impl Case {
+    fn new() -> Self {
+        let mut sk = Sketch::new();
+        let origin = HEntity { v: 2 };
+        let normal = HEntity { v: 3 };
+
+        let mut o = EntityBase::new(origin, EntityBaseType::PointIn3D);
+        o.group = G_BASE;
+        let mut n = EntityBase::new(normal, EntityBaseType::NormalIn3D);
+        n.group = G_BASE;
+        for i in 0..4 {
+            let val = if i == 0 { 1.0 } else { 0.0 };
+            n.param[i] = Case::add_param(&mut sk, 1 + i as u32, val);
+            if i < 3 {
+                o.param[i] = Case::add_param(&mut sk, 5 + i as u32, 0.0);
+            }
+        }
+        let mut w = EntityBase::new(WORKPLANE, EntityBaseType::Workplane);
+        w.group = G_BASE;
+        w.point[0] = origin;
+        w.normal = normal;
+        sk.entity.add(o);
+        sk.entity.add(n);
+        sk.entity.add(w);
+
+        Case {
+            sk,
+            points: Vec::new(),
+            next_entity: 100,
+            next_constraint: 1,
+        }
+    }
+
+    fn add_param(sk: &mut Sketch, v: u32, val: f64) -> HParam {
+        let h = HParam { v };
+        sk.param.add(Param {
+            tag: 0,
+            h,
+            val,
+            known: false,
+            free: false,
+            substd: NO_PARAM,
+        });
+        h
+    }
+
+    fn new_entity(&mut self, type_: EntityBaseType) -> EntityBase {
+        let mut e = EntityBase::new(
+            HEntity {
+                v: self.next_entity,
+            },
+            type_,
+        );
+        self.next_entity += 1;
+        e.group = G_SKETCH;
+        e.workplane = WORKPLANE;
+        e
+    }
+
+    fn point(&mut self, u: f64, v: f64) -> HEntity {
+        let mut p = self.new_entity(EntityBaseType::PointIn2D);
+        p.param[0] = Case::add_param(&mut self.sk, 1000 + 2 * p.h.v, u);
+        p.param[1] = Case::add_param(&mut self.sk, 1001 + 2 * p.h.v, v);
+        let h = p.h;
+        self.sk.entity.add(p);
+        self.points.push(h);
+        h
+    }
+
+    fn line(&mut self, a: HEntity, b: HEntity) -> HEntity {
+        let mut l = self.new_entity(EntityBaseType::LineSegment);
+        l.point[0] = a;
+        l.point[1] = b;
+        let h = l.h;
+        self.sk.entity.add(l);
+        h
+    }
+
+    fn constrain(&mut self, type_: ConstraintType, f: impl Fn(&mut Constraint)) -> HConstraint {
+        let h = HConstraint {
+            v: self.next_constraint,
+        };
+        self.next_constraint += 1;
+        let mut c = Constraint::new(h, type_, G_SKETCH);
+        c.workplane = WORKPLANE;
+        f(&mut c);
+        self.sk.constraint.add(c);
+        h
+    }
+
+    fn uv(&self, h: HEntity) -> (f64, f64) {
+        let e = self.sk.get_entity(h);
+        (
+            self.sk.get_param(e.param[0]).val,
+            self.sk.get_param(e.param[1]).val,
+        )
+    }
+
+    fn distance(&self, a: HEntity, b: HEntity) -> f64 {
+        let (au, av) = self.uv(a);
+        let (bu, bv) = self.uv(b);
+        ((au - bu).powi(2) + (av - bv).powi(2)).sqrt()
+    }
+}
+
+// Lays out the points one at a time, each pinned down by two equations
+// relative to the points before it, so the whole sketch has no DOF left.
+fn well_constrained(rng: &mut Rng) -> Case {
+    let mut c = Case::new();
+    let n = 3 + rng.below(6);
+
+    let p0 = c.point(rng.range(-10.0, 10.0), rng.range(-10.0, 10.0));
+    c.constrain(ConstraintType::WhereDragged, |k| k.pt_a = p0);
+
+    while c.points.len() < n {
+        let j = c.points[rng.below(c.points.len())];
+        let (ju, jv) = c.uv(j);
+        let d = rng.range(2.0, 10.0) * if rng.below(2) == 0 { 1.0 } else { -1.0 };
+
+        match rng.below(4) {
+            0 | 1 => {
+                let horiz = rng.below(2) == 0;
+                let p = if horiz {
+                    c.point(ju + d, jv)
+                } else {
+                    c.point(ju, jv + d)
+                };
+                let ln = c.line(j, p);
+                let t = if horiz {
+                    ConstraintType::Horizontal
+                } else {
+                    ConstraintType::Vertical
+                };
+                c.constrain(t, |k| k.entity_a = ln);
+                c.constrain(ConstraintType::PtPtDistance, |k| {
+                    k.pt_a = j;
+                    k.pt_b = p;
+                    k.val_a = d.abs();
+                });
+            }
+            2 if c.points.len() >= 2 => {
+                let k = c.points[rng.below(c.points.len())];
+                let (ku, kv) = c.uv(k);
+                let (u, v) = (rng.range(-20.0, 20.0), rng.range(-20.0, 20.0));
+                // Keep well clear of the two circles meeting tangentially.
+                let cross = (ju - u) * (kv - v) - (jv - v) * (ku - u);
+                if k == j || cross.abs() < 20.0 {
+                    continue;
+                }
+                let p = c.point(u, v);
+                let (dj, dk) = (c.distance(j, p), c.distance(k, p));
+                c.constrain(ConstraintType::PtPtDistance, |x| {
+                    x.pt_a = j;
+                    x.pt_b = p;
+                    x.val_a = dj;
+                });
+                c.constrain(ConstraintType::PtPtDistance, |x| {
+                    x.pt_a = k;
+                    x.pt_b = p;
+                    x.val_a = dk;
+                });
+            }
+            3 if c.points.len() >= 2 => {
+                let k = c.points[rng.below(c.points.len())];
+                if k == j {
+                    continue;
+                }
+                let (ku, kv) = c.uv(k);
+                let p = c.point((ju + ku) / 2.0, (jv + kv) / 2.0);
+                let ln = c.line(j, k);
+                c.constrain(ConstraintType::AtMidpoint, |x| {
+                    x.pt_a = p;
+                    x.entity_a = ln;
+                });
+            }
+            _ => {}
+        }
+    }
+    c
+}
+
+fn nudge(c: &mut Case, rng: &mut Rng) {
+    for e in c.points.iter() {
+        let param = c.sk.get_entity(*e).param;
+        for h in param[..2].iter() {
+            c.sk.param.get_mut(h).unwrap().val += rng.range(-0.1, 0.1);
+        }
+    }
+}
+
+fn solve(sk: &mut Sketch) -> GroupSolved {
+    let mut sys = System::new();
+    sys.write_params_for_group(sk, G_SKETCH);
+    sys.write_equations_for_group(sk, G_SKETCH);
+    let mut solved = GroupSolved {
+        how: SolveResult::Okay,
+        dof: 0,
+        find_to_fix_timeout: 5000,
+        timeout: false,
+        remove: Vec::new(),
+        residual: Vec::new(),
+    };
+    sys.solve(&mut solved, false, true, false);
+    for p in sys.param.values() {
+        sk.param.get_mut(&p.h).unwrap().val = p.val;
+    }
+    solved
+}
+
+fn max_residual(sk: &Sketch) -> f64 {
+    let mut l: Vec<Equation> = Vec::new();
+    for c in sk.constraint.values() {
+        c.generate_equations(sk, &mut l, false);
+    }
+    l.iter()
+        .map(|eq| eq.e.eval(&sk.param).abs())
+        .fold(0.0, f64::max)
+}
+
+fn check_well(seed: u64) -> Result<(), String> {
+    let mut rng = Rng::new(seed);
+    let mut c = well_constrained(&mut rng);
+    nudge(&mut c, &mut rng);
+
+    let solved = solve(&mut c.sk);
+    if solved.how != SolveResult::Okay {
+        return Err(format!("solve gave {:?}", solved.how));
+    }
+    if solved.dof != 0 {
+        return Err(format!("{} DOF left", solved.dof));
+    }
+    let r = max_residual(&c.sk);
+    if r > RESIDUAL_TOLERANCE {
+        return Err(format!("residual {} after solving", r));
+    }
+    Ok(())
+}
+
+fn check_over(seed: u64) -> Result<(), String> {
+    let mut rng = Rng::new(seed);
+    let mut c = well_constrained(&mut rng);
+
+    // One more distance, either consistent with the rest or not.
+    let a = c.points[rng.below(c.points.len())];
+    let b = c.points[rng.below(c.points.len())];
+    if a == b {
+        return Ok(());
+    }
+    let consistent = rng.below(2) == 0;
+    let d = c.distance(a, b) + if consistent { 0.0 } else { 0.5 };
+    let extra = c.constrain(ConstraintType::PtPtDistance, |k| {
+        k.pt_a = a;
+        k.pt_b = b;
+        k.val_a = d;
+    });
+    nudge(&mut c, &mut rng);
+    let start: IdList<HParam, Param> = c.sk.param.clone();
+
+    let solved = solve(&mut c.sk);
+    match solved.how {
+        SolveResult::RedundantOkay | SolveResult::RedundantDidntConverge => {}
+        how => return Err(format!("over-constrained, but solve gave {:?}", how)),
+    }
+    // Suggestions worked out away from a solution say little: an
+    // impossible dimension can pull the rest into a degenerate shape that
+    // loses more than one rank.
+    if solved.timeout || solved.how == SolveResult::RedundantDidntConverge {
+        return Ok(());
+    }
+    // The rest had full rank, so taking the extra one away always works.
+    if !solved.remove.contains(&extra) {
+        return Err(format!(
+            "c{:03x} not among the suggestions {:?}",
+            extra.v,
+            solved.remove.iter().map(|h| h.v).collect::<Vec<_>>()
+        ));
+    }
+
+    for r in solved.remove.iter() {
+        let removed = c.sk.constraint.take(r).unwrap();
+        c.sk.param = start.clone();
+        let again = solve(&mut c.sk);
+        c.sk.constraint.add(removed);
+
+        // Taking away any other suggestion from a consistent sketch leaves
+        // the same solution, though near-tangent geometry can put its rank
+        // either side of the tolerance. What's left might have no solution
+        // at all if it still includes the inconsistent dimension; then all
+        // we can ask is that it's not redundant where it did converge.
+        let ok = if *r == extra {
+            again.how == SolveResult::Okay
+        } else if consistent {
+            again.how == SolveResult::Okay || again.how == SolveResult::RedundantOkay
+        } else {
+            again.how != SolveResult::RedundantOkay
+        };
+        if !ok {
+            return Err(format!("removing c{:03x} gave {:?}", r.v, again.how));
+        }
+    }
+    Ok(())
+}
+
+fn check(seed: u64, kind: Kind) -> Result<(), String> {
+    match kind {
+        Kind::Well => check_well(seed),
+        Kind::Over => check_over(seed),
+    }
+}
+
+fn corpus() -> Vec<(u64, Kind)> {
+    let text = std::fs::read_to_string(CORPUS).unwrap_or_default();
+    text.lines()
+        .map(|l| l.trim())
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .filter_map(|l| {
+            let mut it = l.split_whitespace();
+            let kind = match it.next()? {
+                "well" => Kind::Well,
+                "over" => Kind::Over,
+                _ => return None,
+            };
+            Some((it.next()?.parse().ok()?, kind))
+        })
+        .collect()
+}
+
+fn record(seed: u64, kind: Kind) {
+    if corpus().contains(&(seed, kind)) {
+        return;
+    }
+    let kind = match kind {
+        Kind::Well => "well",
+        Kind::Over => "over",
+    };
+    if let Ok(mut f) = OpenOptions::new().append(true).create(true).open(CORPUS) {
+        let _ = writeln!(f, "{} {}", kind, seed);
+    }
+}
+
+fn env_u64(name: &str, default: u64) -> u64 {
+    std::env::var(name)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(default)
+}
+
+// A panic in the solver is a failure like any other, and gets recorded.
+fn check_caught(seed: u64, kind: Kind) -> Result<(), String> {
+    match panic::catch_unwind(|| check(seed, kind)) {
+        Ok(r) => r,
+        Err(e) => Err(match e.downcast_ref::<&str>() {
+            Some(m) => format!("panicked: {}", m),
+            None => match e.downcast_ref::<String>() {
+                Some(m) => format!("panicked: {}", m),
+                None => "panicked".to_string(),
+            },
+        }),
+    }
+}
+
+fn run(kind: Kind) {
+    let mut failed = Vec::new();
+
+    for (seed, k) in corpus() {
+        if k == kind {
+            if let Err(e) = check_caught(seed, kind) {
+                failed.push(format!("corpus seed {}: {}", seed, e));
+            }
+        }
+    }
+
+    let first = env_u64("SOLVER_FUZZ_SEED", 1);
+    for seed in first..first + env_u64("SOLVER_FUZZ_CASES", DEFAULT_CASES) {
+        if let Err(e) = check_caught(seed, kind) {
+            record(seed, kind);
+            failed.push(format!("seed {}: {}", seed, e));
+        }
+    }
+
+    if !failed.is_empty() {
+        panic!("{:?} cases failed:\n{}", kind, failed.join("\n"));
+    }
+}
+
+#[test]
+fn well_constrained_sketches_solve() {
+    run(Kind::Well);
+}
+
+#[test]
+fn removing_suggested_constraints_fixes_over_constraint() {
+    run(Kind::Over);
+}
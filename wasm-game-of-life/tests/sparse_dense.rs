@@ -0,0 +1,252 @@
+// The sparse backend has to be a drop-in for the dense one: on the same
+// system it should land on the same params, and find the same rank.
+//
+// Each case is a set of points in the plane, the first one pinned and every
+// later one held by its distances to two earlier ones, measured off a random
+// layout and then solved from a nudged copy of it. Dropping a distance
+// leaves the case under-constrained, and repeating one makes it redundant.
+// A larger case drops a few, and both have to find the same params free.
+
+use wasm_game_of_life::expr::Expr;
+use wasm_game_of_life::sketch::{Equation, GroupSolved, HEquation, HParam, Param, NO_PARAM};
+use wasm_game_of_life::system::{SolveResult, SolverBackend, System};
+
+const CASES: u64 = 50;
+
+// xorshift64*, as in solver_fuzz.rs.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Rng(seed.wrapping_mul(0x9e3779b97f4a7c15) | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545f4914f6cdd1d)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn range(&mut self, a: f64, b: f64) -> f64 {
+        a + (b - a) * ((self.next() >> 11) as f64 / (1u64 << 53) as f64)
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+enum Kind {
+    Well,
+    Under,
+    Redundant,
+    // As Well, but any layout at all; nearly in line is as good as
+    // degenerate, so this only asks that both backends see it the same.
+    Anywhere,
+    // Many more points, and short of FREED distances.
+    Larger,
+}
+
+const FREED: usize = 3;
+
+fn x(i: usize) -> HParam {
+    HParam {
+        v: 2 * i as u32 + 1,
+    }
+}
+
+fn y(i: usize) -> HParam {
+    HParam {
+        v: 2 * i as u32 + 2,
+    }
+}
+
+fn distance(a: usize, b: usize, d: f64) -> Expr {
+    let dx = Expr::from_param(x(a)) - Expr::from_param(x(b));
+    let dy = Expr::from_param(y(a)) - Expr::from_param(y(b));
+    (dx.square() + dy.square()).sqrt() - Expr::from_constant(d)
+}
+
+fn sin_between(u: (f64, f64), v: (f64, f64)) -> f64 {
+    (u.0 * v.1 - u.1 * v.0).abs() / (u.0.hypot(u.1) * v.0.hypot(v.1))
+}
+
+fn case(seed: u64, kind: Kind, backend: SolverBackend) -> System {
+    let mut rng = Rng::new(seed);
+    let n = if kind == Kind::Larger {
+        30 + rng.below(10)
+    } else {
+        5 + rng.below(20)
+    };
+
+    // Otherwise keep the layout well away from anything degenerate, so
+    // that a case is as well-posed as its equations say: point 1 isn't
+    // straight above point 0, and no point is nearly in line with the two
+    // it hangs from.
+    let anywhere = kind == Kind::Anywhere;
+    let mut at = vec![(rng.range(-10.0, 10.0), rng.range(-10.0, 10.0))];
+    loop {
+        let p = (rng.range(-10.0, 10.0), rng.range(-10.0, 10.0));
+        if anywhere || (p.0 - at[0].0).abs() > 1.0 {
+            at.push(p);
+            break;
+        }
+    }
+    let mut hung = vec![(0, 0); 2];
+    while at.len() < n {
+        let i = at.len();
+        let a = rng.below(i);
+        let b = (a + 1 + rng.below(i - 1)) % i;
+        let p = (rng.range(-10.0, 10.0), rng.range(-10.0, 10.0));
+        let to_a = (at[a].0 - p.0, at[a].1 - p.1);
+        let to_b = (at[b].0 - p.0, at[b].1 - p.1);
+        if anywhere
+            || to_a.0.hypot(to_a.1) > 1.0
+                && to_b.0.hypot(to_b.1) > 1.0
+                && sin_between(to_a, to_b) > 0.3
+        {
+            at.push(p);
+            hung.push((a, b));
+        }
+    }
+    let dist =
+        |a: usize, b: usize| ((at[a].0 - at[b].0).powi(2) + (at[a].1 - at[b].1).powi(2)).sqrt();
+
+    let mut sys = System::new();
+    sys.backend = backend;
+    for (i, (px, py)) in at.iter().enumerate() {
+        for (h, v) in [(x(i), *px), (y(i), *py)] {
+            sys.add_param(Param {
+                tag: 0,
+                h,
+                val: v + rng.range(-0.05, 0.05),
+                known: false,
+                free: false,
+                substd: NO_PARAM,
+            });
+        }
+    }
+
+    let mut eqs = vec![
+        Expr::from_param(x(0)) - Expr::from_constant(at[0].0),
+        Expr::from_param(y(0)) - Expr::from_constant(at[0].1),
+        distance(1, 0, dist(1, 0)),
+        Expr::from_param(y(1)) - Expr::from_constant(at[1].1),
+    ];
+    for (i, (a, b)) in hung.into_iter().enumerate().skip(2) {
+        eqs.push(distance(i, a, dist(i, a)));
+        eqs.push(distance(i, b, dist(i, b)));
+    }
+    match kind {
+        Kind::Well | Kind::Anywhere => {}
+        Kind::Under => {
+            let drop = 2 + rng.below(eqs.len() - 2);
+            eqs.remove(drop);
+        }
+        Kind::Larger => {
+            for _ in 0..FREED {
+                let drop = 2 + rng.below(eqs.len() - 2);
+                eqs.remove(drop);
+            }
+        }
+        Kind::Redundant => {
+            let again = eqs[2 + rng.below(eqs.len() - 2)].clone();
+            eqs.push(again);
+        }
+    }
+    for (v, e) in eqs.into_iter().enumerate() {
+        sys.add_equation(Equation {
+            tag: 0,
+            h: HEquation { v: v as u32 },
+            e,
+        });
+    }
+    sys
+}
+
+fn solve(seed: u64, kind: Kind, backend: SolverBackend) -> (SolveResult, i32, Vec<f64>) {
+    let mut sys = case(seed, kind, backend);
+    let mut solved = GroupSolved::default();
+    let how = sys.solve(&mut solved, true, false, false);
+    if how == SolveResult::Okay || how == SolveResult::RedundantOkay {
+        assert!(sys.param.values().all(|p| p.known), "seed {}", seed);
+    }
+    let mut p: Vec<(HParam, f64)> = sys.param.values().map(|p| (p.h, p.val)).collect();
+    p.sort_by_key(|(h, _)| *h);
+    (how, solved.dof, p.into_iter().map(|(_, v)| v).collect())
+}
+
+fn check(kind: Kind, expect: Option<(SolveResult, i32)>) {
+    for seed in 0..CASES {
+        let (dense_how, dense_dof, dense) = solve(seed, kind, SolverBackend::Dense);
+        let (sparse_how, sparse_dof, sparse) = solve(seed, kind, SolverBackend::Sparse);
+        assert_eq!(sparse_how, dense_how, "seed {} ({:?})", seed, kind);
+        assert_eq!(sparse_dof, dense_dof, "seed {} ({:?})", seed, kind);
+        if let Some(e) = expect {
+            assert_eq!((dense_how, dense_dof), e, "seed {} ({:?})", seed, kind);
+        }
+        for (d, s) in dense.iter().zip(sparse.iter()) {
+            assert!(
+                (d - s).abs() < 1e-6,
+                "seed {} ({:?}): {} vs {}",
+                seed,
+                kind,
+                d,
+                s
+            );
+        }
+    }
+}
+
+#[test]
+fn well_constrained() {
+    check(Kind::Well, Some((SolveResult::Okay, 0)));
+}
+
+#[test]
+fn under_constrained() {
+    check(Kind::Under, Some((SolveResult::Okay, 1)));
+}
+
+#[test]
+fn redundant() {
+    check(Kind::Redundant, Some((SolveResult::RedundantOkay, 0)));
+}
+
+#[test]
+fn larger_with_free_params() {
+    for seed in 0..3 {
+        let free = |backend: SolverBackend| {
+            let mut sys = case(seed, Kind::Larger, backend);
+            let mut solved = GroupSolved::default();
+            let how = sys.solve(&mut solved, true, false, true);
+            let mut free: Vec<u32> = sys
+                .param
+                .values()
+                .filter(|p| p.free)
+                .map(|p| p.h.v)
+                .collect();
+            free.sort_unstable();
+            (how, solved.dof, free)
+        };
+        let (dense_how, dense_dof, dense) = free(SolverBackend::Dense);
+        let (sparse_how, sparse_dof, sparse) = free(SolverBackend::Sparse);
+        assert_eq!(dense_how, SolveResult::Okay, "seed {}", seed);
+        assert_eq!(dense_dof, FREED as i32, "seed {}", seed);
+        assert!(!dense.is_empty(), "seed {}", seed);
+        assert_eq!(
+            (sparse_how, sparse_dof),
+            (dense_how, dense_dof),
+            "seed {}",
+            seed
+        );
+        assert_eq!(sparse, dense, "seed {}", seed);
+    }
+}
+
+#[test]
+fn anywhere() {
+    check(Kind::Anywhere, None);
+}
@@ -0,0 +1,144 @@
+// A step dimension sweep records a frame per step, with the dimension's
+// value, every param and each traced point, and puts back the expression
+// that drove the dimension once it's done. Only a driving dimension can be
+// stepped. A group that allows redundant constraints solves as
+// RedundantOkay at every step, which is as good as Okay for carrying on
+// with the sweep.
+
+mod common;
+
+use common::{constraint, distance, line, pinned_line, sketch_in, workplane};
+use wasm_game_of_life::dsc::Vector;
+use wasm_game_of_life::sketch::{ConstraintType, HConstraint, HRequest, Sketch, SketchError};
+use wasm_game_of_life::step_dimension::step_frames_csv;
+use wasm_game_of_life::system::SolveResult;
+
+// A line held at one end with its length driven by an expression, and its
+// other end traced.
+fn sketch() -> (Sketch, HRequest, HConstraint) {
+    let mut sk = Sketch::new();
+    let (_, wrkpl) = workplane(&mut sk);
+    let (_, hl, hc) = pinned_line(&mut sk, wrkpl, 5.0);
+    sk.set_traced(hl.entity(2), true);
+    sk.set_dimension_expr(hc, Some("7"));
+    sk.regenerate_all();
+    (sk, hl, hc)
+}
+
+#[test]
+fn a_frame_per_step() {
+    let (mut sk, hl, hc) = sketch();
+    let a = sk.get_entity(hl.entity(1)).point_get_num(&sk);
+    let frames = sk.step_dimension(hc, 2.0, 6.0, 4).unwrap();
+    let vals: Vec<f64> = frames.iter().map(|f| f.val).collect();
+    assert_eq!(vals, vec![2.0, 3.0, 4.0, 5.0, 6.0]);
+    for f in frames.iter() {
+        assert_eq!(f.how, SolveResult::Okay);
+        assert_eq!(f.param.len(), sk.param.len());
+        assert_eq!(f.traced.len(), 1);
+        assert!(f.traced[0].0 == hl.entity(2));
+        assert!(((f.traced[0].1 - a).magnitude() - f.val).abs() < 1e-9);
+    }
+    assert_eq!(sk.constraint[&hc].expr.as_deref(), Some("7"));
+
+    // Zero steps is taken as one.
+    assert_eq!(sk.step_dimension(hc, 0.0, 1.0, 0).unwrap().len(), 2);
+}
+
+#[test]
+fn csv() {
+    let (mut sk, hl, hc) = sketch();
+    let frames = sk.step_dimension(hc, 1.0, 2.0, 1).unwrap();
+    let csv = step_frames_csv(&frames);
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines.len(), 3);
+    let header: Vec<&str> = lines[0].split(',').collect();
+    assert_eq!(header[..2], ["step", "value"]);
+    assert_eq!(header.len(), 2 + sk.param.len() + 3);
+    let traced = format!("e{:08x}", hl.entity(2).v);
+    assert_eq!(
+        header[header.len() - 3..],
+        [
+            format!("{}.x", traced),
+            format!("{}.y", traced),
+            format!("{}.z", traced)
+        ]
+    );
+    assert!(lines[1].starts_with("0,1,") && lines[2].starts_with("1,2,"));
+    assert_eq!(step_frames_csv(&[]), "");
+}
+
+#[test]
+fn sweep_through_a_redundant_group() {
+    let mut sk = Sketch::new();
+    let (_, wrkpl) = workplane(&mut sk);
+    let g2 = sketch_in(&mut sk, wrkpl);
+    sk.group.get_mut(&g2).unwrap().allow_redundant = true;
+
+    let hl = line(
+        &mut sk,
+        g2,
+        wrkpl,
+        Vector::new(0.0, 0.0, 0.0),
+        Vector::new(3.0, 1.0, 0.0),
+    );
+    let (a, b) = (hl.entity(1), hl.entity(2));
+
+    let add = |sk: &mut Sketch, type_: ConstraintType| {
+        let mut c = constraint(type_, g2, wrkpl);
+        c.pt_a = a;
+        c.pt_b = b;
+        sk.add_constraint(c).unwrap()
+    };
+    add(&mut sk, ConstraintType::WhereDragged);
+    add(&mut sk, ConstraintType::Horizontal);
+    add(&mut sk, ConstraintType::Horizontal);
+    let hc = add(&mut sk, ConstraintType::PtPtDistance);
+
+    let frames = sk.step_dimension(hc, 2.0, 6.0, 4).unwrap();
+    assert_eq!(frames.len(), 5);
+    for f in frames.iter() {
+        assert_eq!(f.how, SolveResult::RedundantOkay);
+    }
+    let pa = sk.get_entity(a).point_get_num(&sk);
+    let pb = sk.get_entity(b).point_get_num(&sk);
+    assert!(((pb - pa).magnitude() - 6.0).abs() < 1e-9);
+}
+
+// Only a driving dimension can be stepped; anything else is turned away
+// before the sketch is touched.
+#[test]
+fn rejected_up_front() {
+    let mut sk = Sketch::new();
+    let (_, wrkpl) = workplane(&mut sk);
+    let (hg, hl, hc) = pinned_line(&mut sk, wrkpl, 5.0);
+    let (a, b) = (hl.entity(1), hl.entity(2));
+    let hh = {
+        let mut c = constraint(ConstraintType::Horizontal, hg, wrkpl);
+        c.entity_a = hl.entity(0);
+        sk.add_constraint(c).unwrap()
+    };
+    let href = distance(&mut sk, hg, wrkpl, a, b, 5.0);
+    sk.constraint.get_mut(&href).unwrap().reference = true;
+    sk.regenerate_all();
+    let before = sk.get_entity(b).point_get_num(&sk);
+
+    let step = |sk: &mut Sketch, hc: HConstraint| sk.step_dimension(hc, 1.0, 2.0, 2).err();
+    assert_eq!(
+        step(&mut sk, HConstraint { v: 9999 }),
+        Some(SketchError::UnknownConstraint(9999))
+    );
+    assert_eq!(step(&mut sk, hh), Some(SketchError::NotADimension(hh.v)));
+    assert_eq!(step(&mut sk, href), Some(SketchError::IsReference(href.v)));
+
+    sk.group.get_mut(&hg).unwrap().all_dims_reference = true;
+    assert_eq!(step(&mut sk, hc), Some(SketchError::IsReference(hc.v)));
+    assert_eq!(sk.constraint[&hc].val_a, 5.0);
+    assert!(sk
+        .get_entity(b)
+        .point_get_num(&sk)
+        .equals_with_def_tol(before));
+
+    sk.group.get_mut(&hg).unwrap().all_dims_reference = false;
+    assert_eq!(sk.step_dimension(hc, 1.0, 2.0, 2).unwrap().len(), 3);
+}
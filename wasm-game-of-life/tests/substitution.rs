@@ -0,0 +1,94 @@
+// Equations that just say two unknowns are equal are solved by putting one
+// in place of the other everywhere, before Newton's method sees them; the
+// one replaced gets the value of whatever it was replaced with.
+
+use wasm_game_of_life::expr::Expr;
+use wasm_game_of_life::sketch::{Equation, GroupSolved, HEquation, HParam, Param};
+use wasm_game_of_life::system::{
+    SolveResult, System, EQ_SUBSTITUTED, SYSTEM_MAX_UNKNOWNS, VAR_SUBSTITUTED,
+};
+
+fn h(v: u32) -> HParam {
+    HParam { v }
+}
+
+fn p(v: u32) -> Expr {
+    Expr::from_param(h(v))
+}
+
+// Params 1 to n, all starting at 0, and an equation for each of eqs.
+fn system(n: u32, eqs: Vec<Expr>) -> System {
+    let mut sys = System::new();
+    for v in 1..=n {
+        sys.add_param(Param {
+            tag: 0,
+            h: h(v),
+            val: 0.0,
+            known: false,
+            free: false,
+            substd: HParam { v: 0 },
+        });
+    }
+    for (v, e) in eqs.into_iter().enumerate() {
+        sys.add_equation(Equation {
+            tag: 0,
+            h: HEquation { v: v as u32 },
+            e,
+        });
+    }
+    sys
+}
+
+fn solve(sys: &mut System) -> SolveResult {
+    let mut solved = GroupSolved {
+        how: SolveResult::Okay,
+        dof: 0,
+        find_to_fix_timeout: 0,
+        timeout: false,
+        remove: Vec::new(),
+        residual: Vec::new(),
+    };
+    sys.solve(&mut solved, false, false, false)
+}
+
+#[test]
+fn chain_of_equalities() {
+    let eqs = vec![p(1) - p(2), p(2) - p(3), p(3) - Expr::from_constant(5.0)];
+    let mut sys = system(3, eqs);
+    assert_eq!(solve(&mut sys), SolveResult::Okay);
+    for v in 1..=3 {
+        assert_eq!(sys.param[&h(v)].val, 5.0, "param {}", v);
+    }
+    // Both ends up the chain are replaced by the last one.
+    for v in 1..=2 {
+        assert_eq!(sys.param[&h(v)].tag, VAR_SUBSTITUTED);
+        assert!(sys.param[&h(v)].substd == h(3));
+        assert_eq!(sys.eq[v as usize - 1].tag, EQ_SUBSTITUTED);
+    }
+    assert_eq!(sys.param[&h(3)].tag, 0);
+    assert_eq!(sys.eq[2].tag, 0);
+}
+
+#[test]
+fn substituted_params_arent_unknowns() {
+    let n = SYSTEM_MAX_UNKNOWNS as u32 + 10;
+    let mut eqs: Vec<Expr> = (1..n).map(|v| p(v) - p(v + 1)).collect();
+    eqs.push(p(n) - Expr::from_constant(2.0));
+    let mut sys = system(n, eqs);
+    assert_eq!(solve(&mut sys), SolveResult::Okay);
+    assert!(sys.param.values().all(|p| p.val == 2.0));
+}
+
+#[test]
+fn known_params_stay() {
+    let mut sys = system(2, vec![p(2) - p(1)]);
+    {
+        let x1 = sys.param.get_mut(&h(1)).unwrap();
+        x1.val = 3.0;
+        x1.known = true;
+    }
+    assert_eq!(solve(&mut sys), SolveResult::Okay);
+    assert_eq!(sys.param[&h(1)].tag, 0);
+    assert_eq!(sys.param[&h(2)].tag, 0);
+    assert!((sys.param[&h(2)].val - 3.0).abs() < 1e-9);
+}
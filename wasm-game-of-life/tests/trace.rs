@@ -0,0 +1,90 @@
+// A traced point leaves a path behind it each time the sketch is solved,
+// and that path can be written out or turned into a cubic through it.
+
+mod common;
+
+use common::{pinned_line, workplane};
+use wasm_game_of_life::delete::SketchItem;
+use wasm_game_of_life::dsc::Vector;
+use wasm_game_of_life::sketch::{EntityBaseType, HConstraint, HEntity, Sketch, SketchError};
+
+fn at(sk: &Sketch, h: HEntity) -> Vector {
+    sk.get_entity(h).point_get_num(sk)
+}
+
+fn set_len(sk: &mut Sketch, hc: HConstraint, len: f64) {
+    sk.constraint.get_mut(&hc).unwrap().val_a = len;
+    sk.mark_group_dirty(sk.constraint[&hc].group);
+    sk.regenerate_dirty();
+}
+
+#[test]
+fn path_follows_the_point() {
+    let mut sk = Sketch::new();
+    let (_, wrkpl) = workplane(&mut sk);
+    let (hg, hl, hc) = pinned_line(&mut sk, wrkpl, 5.0);
+    let pt = hl.entity(2);
+    sk.set_traced(pt, true);
+    assert!(sk.trace_path[&pt].is_empty());
+
+    sk.regenerate_all();
+    let first = at(&sk, pt);
+    // Solving again without moving it adds nothing.
+    sk.regenerate_all();
+    set_len(&mut sk, hc, 10.0);
+    let last = at(&sk, pt);
+    assert_eq!(sk.trace_path[&pt].len(), 2);
+    assert!(sk.trace_path[&pt][0].equals_with_def_tol(first));
+    assert!(sk.trace_path[&pt][1].equals_with_def_tol(last));
+
+    let csv = sk.trace_csv(pt);
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[0], "x,y,z");
+    assert_eq!(lines[2], format!("{},{},{}", last.x, last.y, last.z));
+
+    let cubic = sk.trace_to_cubic(pt, hg, wrkpl).unwrap();
+    let e = sk.get_entity(cubic);
+    assert!(e.type_ == EntityBaseType::Cubic);
+    assert!(e.group == hg);
+    let ends = (e.point[0], e.point[3]);
+    assert!(at(&sk, ends.0).equals_with_def_tol(first));
+    assert!(at(&sk, ends.1).equals_with_def_tol(last));
+}
+
+#[test]
+fn stop_and_clear() {
+    let mut sk = Sketch::new();
+    let (_, wrkpl) = workplane(&mut sk);
+    let (hg, hl, hc) = pinned_line(&mut sk, wrkpl, 5.0);
+    let pt = hl.entity(2);
+    sk.set_traced(pt, true);
+    sk.regenerate_all();
+    set_len(&mut sk, hc, 10.0);
+
+    sk.clear_traces();
+    assert!(sk.traced.contains(&pt));
+    assert_eq!(sk.trace_csv(pt), "x,y,z\n");
+    assert_eq!(
+        sk.trace_to_cubic(pt, hg, wrkpl).err(),
+        Some(SketchError::NoPath(pt.v))
+    );
+
+    sk.set_traced(pt, false);
+    set_len(&mut sk, hc, 5.0);
+    assert!(sk.traced.is_empty());
+    assert!(!sk.trace_path.contains_key(&pt));
+    assert_eq!(sk.trace_csv(pt), "x,y,z\n");
+}
+
+#[test]
+fn deleted_point_stops_being_traced() {
+    let mut sk = Sketch::new();
+    let (_, wrkpl) = workplane(&mut sk);
+    let (_, hl, _) = pinned_line(&mut sk, wrkpl, 5.0);
+    let pt = hl.entity(2);
+    sk.set_traced(pt, true);
+    sk.delete(&[SketchItem::Entity(pt)]);
+    sk.regenerate_all();
+    assert!(sk.traced.is_empty());
+}
@@ -0,0 +1,135 @@
+// Variables are worked out in whatever order they refer to each other, and
+// a dimension given as an expression follows them on the next regenerate.
+
+mod common;
+
+use common::{pinned_line, workplane};
+use wasm_game_of_life::expr::ExprError;
+use wasm_game_of_life::sketch::{HRequest, Sketch};
+
+fn val(sk: &Sketch, name: &str) -> f64 {
+    let v = &sk.variable[name];
+    assert!(v.error.is_none(), "{}: {:?}", name, v.error);
+    v.val
+}
+
+fn error(sk: &Sketch, name: &str) -> Option<ExprError> {
+    sk.variable[name].error.clone()
+}
+
+fn length(sk: &Sketch, hl: HRequest) -> f64 {
+    let a = sk.get_entity(hl.entity(1)).point_get_num(sk);
+    let b = sk.get_entity(hl.entity(2)).point_get_num(sk);
+    (b - a).magnitude()
+}
+
+#[test]
+fn in_order_of_use() {
+    let mut sk = Sketch::new();
+    // Sorted by name, c comes first but needs the other two.
+    sk.set_variable("c", "a + b").unwrap();
+    sk.set_variable("b", "2 * a").unwrap();
+    sk.set_variable("a", "1 cm").unwrap();
+    sk.update_expressions();
+    assert_eq!(val(&sk, "a"), 10.0);
+    assert_eq!(val(&sk, "b"), 20.0);
+    assert_eq!(val(&sk, "c"), 30.0);
+}
+
+#[test]
+fn cycle() {
+    let mut sk = Sketch::new();
+    sk.set_variable("a", "b").unwrap();
+    sk.set_variable("b", "a").unwrap();
+    sk.set_variable("c", "a + 1").unwrap();
+    sk.update_expressions();
+    for name in ["a", "b"] {
+        match error(&sk, name) {
+            Some(ExprError::Cycle(names)) => {
+                assert!(names.len() == 3 && names[0] == names[2], "{:?}", names);
+            }
+            e => panic!("{} gave {:?}", name, e),
+        }
+    }
+    assert_eq!(
+        error(&sk, "c"),
+        Some(ExprError::BadVariable("a".to_string()))
+    );
+
+    // Breaking the cycle clears the errors.
+    sk.set_variable("b", "2").unwrap();
+    sk.update_expressions();
+    assert_eq!(val(&sk, "a"), 2.0);
+    assert_eq!(val(&sk, "c"), 3.0);
+}
+
+#[test]
+fn errors_keep_the_last_good_value() {
+    let mut sk = Sketch::new();
+    sk.set_variable("a", "5").unwrap();
+    sk.set_variable("b", "a * 2").unwrap();
+    sk.update_expressions();
+    assert_eq!(val(&sk, "b"), 10.0);
+
+    sk.set_variable("a", "5 +").unwrap();
+    sk.set_variable("b", "z * 2").unwrap();
+    sk.update_expressions();
+    assert!(matches!(error(&sk, "a"), Some(ExprError::Syntax(3, _))));
+    assert_eq!(sk.variable["a"].val, 5.0);
+    assert_eq!(
+        error(&sk, "b"),
+        Some(ExprError::UnknownVariable("z".to_string()))
+    );
+    assert_eq!(sk.variable["b"].val, 10.0);
+
+    assert!(sk.set_variable("mm", "1").is_err());
+    assert!(sk.set_variable("2a", "1").is_err());
+    assert!(!sk.variable.contains_key("mm"));
+}
+
+#[test]
+fn dimension_follows_its_variable() {
+    let mut sk = Sketch::new();
+    let (_, wrkpl) = workplane(&mut sk);
+    let (_, hl, hc) = pinned_line(&mut sk, wrkpl, 1.0);
+    sk.set_variable("len", "2 cm").unwrap();
+    sk.set_dimension_expr(hc, Some("len / 4"));
+    sk.regenerate_dirty();
+    assert_eq!(sk.constraint[&hc].val_a, 5.0);
+    assert!((length(&sk, hl) - 5.0).abs() < 1e-9);
+
+    sk.set_variable("len", "3 cm").unwrap();
+    sk.regenerate_dirty();
+    assert!((length(&sk, hl) - 7.5).abs() < 1e-9);
+
+    // A bad expression leaves the dimension where it was.
+    sk.set_dimension_expr(hc, Some("len / nothing"));
+    sk.regenerate_dirty();
+    assert_eq!(
+        sk.constraint[&hc].expr_error,
+        Some(ExprError::UnknownVariable("nothing".to_string()))
+    );
+    assert!((length(&sk, hl) - 7.5).abs() < 1e-9);
+
+    sk.remove_variable("len");
+    sk.set_dimension_expr(hc, Some("len"));
+    sk.regenerate_dirty();
+    assert!(sk.constraint[&hc].expr_error.is_some());
+    assert!((length(&sk, hl) - 7.5).abs() < 1e-9);
+}
+
+#[test]
+fn reference_dimension_is_measured() {
+    let mut sk = Sketch::new();
+    let (_, wrkpl) = workplane(&mut sk);
+    let (_, hl, hc) = pinned_line(&mut sk, wrkpl, 5.0);
+    sk.regenerate_dirty();
+
+    // Whatever its expression says.
+    sk.set_variable("len", "3 cm").unwrap();
+    sk.set_dimension_expr(hc, Some("len"));
+    sk.constraint.get_mut(&hc).unwrap().reference = true;
+    sk.regenerate_dirty();
+    assert!((sk.constraint[&hc].val_a - length(&sk, hl)).abs() < 1e-9);
+    assert!((sk.constraint[&hc].val_a - 30.0).abs() > 1e-3);
+}
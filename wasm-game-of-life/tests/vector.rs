@@ -0,0 +1,24 @@
+// The cross product's z component once took y * rhs.z where it needed
+// y * rhs.x, which tilted every normal built from two in-plane vectors.
+
+use wasm_game_of_life::dsc::Vector;
+
+#[test]
+fn cross_of_the_axes() {
+    let x = Vector::new(1.0, 0.0, 0.0);
+    let y = Vector::new(0.0, 1.0, 0.0);
+    let z = Vector::new(0.0, 0.0, 1.0);
+    assert!(x.cross(y).equals_with_def_tol(z));
+    assert!(y.cross(z).equals_with_def_tol(x));
+    assert!(z.cross(x).equals_with_def_tol(y));
+}
+
+#[test]
+fn cross_is_perpendicular_to_both() {
+    let a = Vector::new(1.0, 2.0, 3.0);
+    let b = Vector::new(4.0, 5.0, 6.0);
+    let c = a.cross(b);
+    assert!(c.equals_with_def_tol(Vector::new(-3.0, 6.0, -3.0)));
+    assert!(c.dot(a).abs() < 1e-12);
+    assert!(c.dot(b).abs() < 1e-12);
+}